@@ -0,0 +1,323 @@
+use std::rc::Rc;
+
+use yew::{prelude::*, services::DialogService};
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::{
+        app::AppRoute, login::AuthState, login_button::LoginButton, totp_settings::TotpSettings,
+    },
+    services::{
+        auth::{AccountDeletion, AuthAgent, AuthAgentRequest, NameChange, PasswordChange},
+        validation::{self, PasswordStrength},
+    },
+};
+
+/// Lets the logged in user change their name and password, or delete their account
+pub struct AccountSettings {
+    link: ComponentLink<Self>,
+    auth_state: Rc<AuthState>,
+    auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    route_dispatcher: RouteAgentDispatcher,
+
+    /// The change in progress, which is confirmed once the auth agent is done
+    pending: Option<Change>,
+    notice: Option<&'static str>,
+
+    name: String,
+    current_password: String,
+    new_password: String,
+    repeated_password: String,
+    deletion_password: String,
+}
+
+pub enum Msg {
+    NewAuthState(Rc<AuthState>),
+    UpdateName(String),
+    UpdateCurrentPassword(String),
+    UpdateNewPassword(String),
+    UpdateRepeatedPassword(String),
+    UpdateDeletionPassword(String),
+    SaveName,
+    SavePassword,
+    DeleteAccount,
+}
+
+#[derive(Clone, Copy)]
+enum Change {
+    Name,
+    Password,
+    Deletion,
+}
+
+impl Component for AccountSettings {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            auth_state: Rc::new(AuthState::Initial),
+            auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            route_dispatcher: RouteAgentDispatcher::new(),
+            pending: None,
+            notice: None,
+            name: String::new(),
+            current_password: String::new(),
+            new_password: String::new(),
+            repeated_password: String::new(),
+            deletion_password: String::new(),
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewAuthState(auth_state) => {
+                self.auth_state = auth_state;
+
+                match (self.auth_state.as_ref(), self.pending) {
+                    // Wait for the change to complete
+                    (AuthState::Updating(..), _) => {}
+
+                    (AuthState::LoggedIn(user_info), Some(Change::Name)) => {
+                        self.pending = None;
+                        self.notice = Some("Your name has been changed.");
+                        self.name = user_info.name.clone();
+                    }
+                    (AuthState::LoggedIn(_), Some(Change::Password)) => {
+                        self.pending = None;
+                        self.notice = Some("Your password has been changed.");
+                        self.current_password.clear();
+                        self.new_password.clear();
+                        self.repeated_password.clear();
+                    }
+                    (AuthState::Initial, Some(Change::Deletion)) => {
+                        self.pending = None;
+
+                        // There is nothing left to show here
+                        self.route_dispatcher
+                            .send(RouteRequest::ChangeRoute(AppRoute::Home.into()));
+                    }
+                    (state, _) => {
+                        self.pending = None;
+
+                        // Prefill the name once the user is known
+                        if let Some(user_info) = state.user() {
+                            if self.name.is_empty() {
+                                self.name = user_info.name.clone();
+                            }
+                        }
+                    }
+                }
+                true
+            }
+            Msg::UpdateName(name) => {
+                self.name = name;
+                true
+            }
+            Msg::UpdateCurrentPassword(password) => {
+                self.current_password = password;
+                true
+            }
+            Msg::UpdateNewPassword(password) => {
+                self.new_password = password;
+                true
+            }
+            Msg::UpdateRepeatedPassword(password) => {
+                self.repeated_password = password;
+                true
+            }
+            Msg::UpdateDeletionPassword(password) => {
+                self.deletion_password = password;
+                true
+            }
+            Msg::SaveName => {
+                self.start(
+                    Change::Name,
+                    AuthAgentRequest::ChangeName(NameChange {
+                        name: self.name.trim().to_string(),
+                    }),
+                );
+                true
+            }
+            Msg::SavePassword => {
+                self.start(
+                    Change::Password,
+                    AuthAgentRequest::ChangePassword(PasswordChange {
+                        current_password: self.current_password.clone(),
+                        new_password: self.new_password.clone(),
+                    }),
+                );
+                true
+            }
+            Msg::DeleteAccount => {
+                if !DialogService::confirm("Delete your account?\nThis operation cannot be undone.")
+                {
+                    return false;
+                }
+
+                self.start(
+                    Change::Deletion,
+                    AuthAgentRequest::DeleteAccount(AccountDeletion {
+                        password: self.deletion_password.clone(),
+                    }),
+                );
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let user_info = match self.auth_state.as_ref() {
+            AuthState::Probing(_) => return html! { <p>{ "Loading..." }</p> },
+            state => match state.user() {
+                Some(user_info) => user_info,
+                None => {
+                    return html! {
+                        <p>{ "Please log in to manage your account. " }<LoginButton /></p>
+                    }
+                }
+            },
+        };
+
+        let is_busy = self.pending.is_some();
+
+        html! {
+            <div>
+                <h2>{ "Account" }</h2>
+
+                <p>
+                    { "Logged in as " } <strong>{ &user_info.name }</strong> <br />
+                    <small>{ user_info.uuid }</small>
+                </p>
+
+                { self.view_status() }
+
+                <h3>{ "Display name" }</h3>
+
+                <input
+                    type="text"
+                    placeholder="name"
+                    disabled=is_busy
+                    value=self.name.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateName(i.value))
+                /> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::SaveName)
+                    disabled=is_busy
+                        || validation::validate_name(self.name.trim()).is_err()
+                        || self.name.trim() == user_info.name
+                >
+                    { "Save" }
+                </button>
+
+                { match validation::validate_name(self.name.trim()) {
+                    Err(reason) if !self.name.is_empty() => html! { <p class="sfi-error">{ reason }</p> },
+                    _ => html! {},
+                } }
+
+                <h3>{ "Password" }</h3>
+
+                <input
+                    type="password"
+                    placeholder="current password"
+                    disabled=is_busy
+                    value=self.current_password.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateCurrentPassword(i.value))
+                /> <br />
+
+                <input
+                    type="password"
+                    placeholder="new password"
+                    disabled=is_busy
+                    value=self.new_password.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateNewPassword(i.value))
+                /> { " " }
+
+                <input
+                    type="password"
+                    placeholder="repeat new password"
+                    disabled=is_busy
+                    value=self.repeated_password.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateRepeatedPassword(i.value))
+                /> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::SavePassword)
+                    disabled=is_busy || !self.is_password_valid()
+                >
+                    { "Change password" }
+                </button>
+
+                { if self.new_password.is_empty() {
+                    html! {}
+                } else {
+                    let strength = PasswordStrength::of(&self.new_password);
+                    let class = if strength.is_acceptable() { "" } else { "sfi-error" };
+
+                    html! { <p class=class>{ "Password strength: " }{ strength.describe() }</p> }
+                } }
+
+                { if !self.repeated_password.is_empty() && self.new_password != self.repeated_password {
+                    html! { <p class="sfi-error">{ "The new passwords don't match" }</p> }
+                } else {
+                    html! {}
+                } }
+
+                <TotpSettings />
+
+                <h3>{ "Delete account" }</h3>
+
+                <p>{ "Please enter your password to delete your account." }</p>
+
+                <input
+                    type="password"
+                    placeholder="password"
+                    disabled=is_busy
+                    value=self.deletion_password.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateDeletionPassword(i.value))
+                /> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::DeleteAccount)
+                    disabled=is_busy || self.deletion_password.is_empty()
+                >
+                    { "Delete account" }
+                </button>
+            </div>
+        }
+    }
+}
+
+impl AccountSettings {
+    fn start(&mut self, change: Change, request: AuthAgentRequest) {
+        self.auth_bridge.send(request);
+
+        self.pending = Some(change);
+        self.notice = None;
+    }
+
+    fn is_password_valid(&self) -> bool {
+        !self.current_password.is_empty()
+            && PasswordStrength::of(&self.new_password).is_acceptable()
+            && self.new_password == self.repeated_password
+    }
+
+    fn view_status(&self) -> Html {
+        match self.auth_state.as_ref() {
+            AuthState::Updating(..) => html! { <p>{ "Saving..." }</p> },
+            AuthState::UpdateFailed(_, error) => {
+                html! { <p class="sfi-error">{ "Couldn't change your account: " }{ error }</p> }
+            }
+            _ => match self.notice {
+                Some(notice) => html! { <p>{ notice }</p> },
+                None => html! {},
+            },
+        }
+    }
+}
@@ -1,11 +1,18 @@
 use crate::components::{
+    account_settings::AccountSettings,
     core::{
-        create_inventory::CreateInventory, create_item::CreateItem, inventories::Inventories,
-        items::Items, units::Units, update_inventory::UpdateInventory, update_item::UpdateItem,
+        accept_invitation::AcceptInvitation, conflicts::Conflicts,
+        create_inventory::CreateInventory, create_item::CreateItem, create_unit::CreateUnit,
+        expiring::Expiring, inventories::Inventories, items::Items, search::Search, units::Units,
+        update_inventory::UpdateInventory, update_item::UpdateItem, update_unit::UpdateUnit,
     },
     debug_tools::DebugTools,
+    guard::Guard,
     home::Home,
     login::LoginComponent,
+    register::Register,
+    settings::Settings,
+    toolbar::Toolbar,
 };
 use uuid::Uuid;
 use yew::prelude::*;
@@ -25,6 +32,9 @@ pub enum AppRoute {
     #[to = "/account!"]
     AccountSettings,
 
+    #[to = "/settings!"]
+    Settings,
+
     #[to = "/inventories/{inv_uuid}/items/{it_uuid}/units/new!"]
     CreateUnit(Uuid, Uuid),
 
@@ -52,6 +62,18 @@ pub enum AppRoute {
     #[to = "/inventories!"]
     Inventories,
 
+    #[to = "/conflicts!"]
+    Conflicts,
+
+    #[to = "/expiring!"]
+    Expiring,
+
+    #[to = "/search!"]
+    Search,
+
+    #[to = "/invite/{token}"]
+    AcceptInvitation(String),
+
     #[to = "/!"]
     Home,
 
@@ -82,15 +104,12 @@ impl Component for App {
     fn view(&self) -> Html {
         html! {
             <>
-            // TODO implement some kind of nav bar
-
+            // The header showing the logged in user
+            <Toolbar />
 
             // Include debug tools in debug builds
             { self.view_debug_tools() }
 
-            // The login component
-            <LoginComponent />
-
             // The router outlet
             <AppRouter render=AppRouter::render(Self::handle_route) />
 
@@ -99,8 +118,44 @@ impl Component for App {
     }
 }
 
+impl AppRoute {
+    /// Whether the route can be viewed without logging in
+    pub fn is_public(&self) -> bool {
+        match self {
+            AppRoute::Home
+            | AppRoute::Login
+            | AppRoute::Register
+            | AppRoute::Settings
+            | AppRoute::PageNotFound(_) => true,
+            AppRoute::AccountSettings
+            | AppRoute::CreateUnit(..)
+            | AppRoute::UpdateUnit(..)
+            | AppRoute::Units(..)
+            | AppRoute::CreateItem(_)
+            | AppRoute::UpdateItem(..)
+            | AppRoute::Items(_)
+            | AppRoute::CreateInventory
+            | AppRoute::UpdateInventory(_)
+            | AppRoute::Inventories
+            | AppRoute::Conflicts
+            | AppRoute::Expiring
+            | AppRoute::Search
+            | AppRoute::AcceptInvitation(_) => false,
+        }
+    }
+}
+
 impl App {
     fn handle_route(route: AppRoute) -> Html {
+        if route.is_public() {
+            Self::view_route(route)
+        } else {
+            // Don't show (cached) data to users who aren't logged in
+            html! { <Guard>{ Self::view_route(route) }</Guard> }
+        }
+    }
+
+    fn view_route(route: AppRoute) -> Html {
         match route {
             AppRoute::Home => {
                 html! { <Home /> }
@@ -132,6 +187,38 @@ impl App {
                 html! { <Units inventory_uuid=inv_uuid item_uuid=it_uuid /> }
             }
 
+            AppRoute::CreateUnit(inv_uuid, it_uuid) => {
+                html! { <CreateUnit inventory_uuid=inv_uuid item_uuid=it_uuid /> }
+            }
+
+            AppRoute::UpdateUnit(inv_uuid, it_uuid, un_uuid) => {
+                html! { <UpdateUnit inventory_uuid=inv_uuid item_uuid=it_uuid unit_uuid=un_uuid /> }
+            }
+
+            AppRoute::Conflicts => {
+                html! { <Conflicts /> }
+            }
+
+            AppRoute::Settings => {
+                html! { <Settings /> }
+            }
+
+            AppRoute::AccountSettings => {
+                html! { <AccountSettings /> }
+            }
+
+            AppRoute::Expiring => {
+                html! { <Expiring /> }
+            }
+
+            AppRoute::Search => {
+                html! { <Search /> }
+            }
+
+            AppRoute::AcceptInvitation(token) => {
+                html! { <AcceptInvitation token=token /> }
+            }
+
             // The 404-like display
             AppRoute::PageNotFound(path) => {
                 html! {
@@ -155,21 +242,12 @@ impl App {
                 }
             }
 
-            // TODO implement the route handlers
             AppRoute::Login => {
-                html! { "still need to implement this"}
+                html! { <LoginComponent /> }
             }
+
             AppRoute::Register => {
-                html! { "still need to implement this"}
-            }
-            AppRoute::AccountSettings => {
-                html! { "still need to implement this"}
-            }
-            AppRoute::CreateUnit(inv_uuid, it_uuid) => {
-                html! { "still need to implement this"}
-            }
-            AppRoute::UpdateUnit(inv_uuid, it_uuid, un_uuid) => {
-                html! { "still need to implement this"}
+                html! { <Register /> }
             }
         }
     }
@@ -0,0 +1,186 @@
+use std::rc::Rc;
+
+use sfi_core::core::users::StatusNotice;
+use yew::{
+    agent::{Dispatched, Dispatcher},
+    prelude::*,
+    services::fetch::FetchTask,
+};
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::{app::AppRoute, login::AuthState, login_button::LoginButton},
+    services::{
+        api::{ApiClient, ApiError},
+        auth::AuthAgent,
+        data::{DataAgent, DataAgentRequest},
+        invitations::Invitation,
+    },
+};
+
+/// Shows an invitation to the recipient, who can join the inventory once logged in
+pub struct AcceptInvitation {
+    link: ComponentLink<Self>,
+    props: Props,
+    auth_state: Rc<AuthState>,
+    _auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    data_dispatcher: Dispatcher<DataAgent>,
+    route_dispatcher: RouteAgentDispatcher,
+
+    invitation: Option<Invitation>,
+    error: Option<String>,
+
+    // Avoid dropping requests
+    fetch_task: Option<FetchTask>,
+}
+
+pub enum Msg {
+    NewAuthState(Rc<AuthState>),
+    Loaded(Result<Invitation, ApiError>),
+    Accept,
+    Accepted(Result<StatusNotice, ApiError>),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub token: String,
+}
+
+impl Component for AcceptInvitation {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            auth_state: Rc::new(AuthState::Initial),
+            _auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            data_dispatcher: DataAgent::dispatcher(),
+            route_dispatcher: RouteAgentDispatcher::new(),
+            invitation: None,
+            error: None,
+            fetch_task: None,
+            link,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewAuthState(auth_state) => {
+                self.auth_state = auth_state;
+
+                // Only users can look at invitations
+                if self.auth_state.user().is_some() {
+                    if self.invitation.is_none() && self.fetch_task.is_none() {
+                        let callback = self.link.callback(Msg::Loaded);
+
+                        match ApiClient::configured().invitation(&self.props.token, callback) {
+                            Ok(task) => self.fetch_task = Some(task),
+                            Err(error) => self.error = Some(error.to_string()),
+                        }
+                    }
+                }
+                true
+            }
+            Msg::Loaded(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(invitation) => self.invitation = Some(invitation),
+                    Err(ApiError::Status { code: 404, .. }) => {
+                        self.error = Some(
+                            "This invitation doesn't exist (anymore), it may have been revoked"
+                                .to_string(),
+                        )
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::Accept => {
+                let callback = self.link.callback(Msg::Accepted);
+
+                match ApiClient::configured().accept_invitation(&self.props.token, callback) {
+                    Ok(task) => self.fetch_task = Some(task),
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::Accepted(result) => {
+                self.fetch_task = None;
+
+                match (result, &self.invitation) {
+                    (Ok(_), Some(invitation)) => {
+                        // Download the inventory right away
+                        self.data_dispatcher.send(DataAgentRequest::Sync.into());
+
+                        self.route_dispatcher.send(RouteRequest::ChangeRoute(
+                            AppRoute::Items(invitation.inventory_uuid).into(),
+                        ));
+                    }
+                    (Ok(_), None) => {}
+                    (Err(error), _) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <h2>{ "Invitation" }</h2>
+
+                { self.view_invitation() }
+
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+            </div>
+        }
+    }
+}
+
+impl AcceptInvitation {
+    fn view_invitation(&self) -> Html {
+        match self.auth_state.as_ref() {
+            AuthState::Probing(_) => return html! { <p>{ "Loading..." }</p> },
+            state if state.user().is_none() => {
+                return html! {
+                    <p>{ "Please log in to accept this invitation. " }<LoginButton /></p>
+                }
+            }
+            _ => {}
+        }
+
+        let invitation = match &self.invitation {
+            Some(invitation) => invitation,
+            None if self.error.is_none() => return html! { <p>{ "Loading the invitation..." }</p> },
+            None => return html! {},
+        };
+
+        if invitation.is_expired() {
+            return html! { <p>{ "This invitation has expired, please ask for a new one." }</p> };
+        }
+
+        html! {
+            <p>
+                { "You have been invited to join " }
+                <strong>{ &invitation.inventory_name }</strong>
+                { format!(" ({}). ", invitation.role.name().to_lowercase()) }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::Accept)
+                    disabled=self.fetch_task.is_some()
+                >
+                    { "Join inventory" }
+                </button>
+            </p>
+        }
+    }
+}
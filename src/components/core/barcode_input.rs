@@ -0,0 +1,122 @@
+use crate::services::barcode;
+use yew::{
+    prelude::*,
+    services::{
+        reader::{FileData, ReaderTask},
+        ReaderService,
+    },
+};
+
+/// A file input which reads the barcode from a photo of a package
+pub struct BarcodeInput {
+    link: ComponentLink<Self>,
+    props: Props,
+    reader: ReaderService,
+    status: Status,
+
+    // Keep the task alive until the file is read
+    task: Option<ReaderTask>,
+}
+
+enum Status {
+    Idle,
+    Reading,
+    Found(String),
+    Failed(String),
+}
+
+pub enum Msg {
+    PickFile(ChangeData),
+    Loaded(FileData),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    /// Receives the digits of the barcode found in the photo
+    pub on_detect: Callback<String>,
+    #[prop_or_default]
+    pub disabled: bool,
+}
+
+impl Component for BarcodeInput {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            reader: ReaderService::new(),
+            status: Status::Idle,
+            task: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::PickFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    match self.reader.read_file(file, self.link.callback(Msg::Loaded)) {
+                        Ok(task) => {
+                            self.task = Some(task);
+                            self.status = Status::Reading;
+                        }
+                        Err(error) => self.status = Status::Failed(error.to_string()),
+                    }
+                }
+                true
+            }
+            Msg::PickFile(_) => false,
+            Msg::Loaded(file) => {
+                self.task = None;
+
+                self.status = match barcode::decode(&file.content) {
+                    Ok(barcode) => {
+                        self.props.on_detect.emit(barcode.code.clone());
+                        Status::Found(barcode.code)
+                    }
+                    Err(error) => Status::Failed(error.to_string()),
+                };
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let should_render = self.props.disabled != props.disabled;
+        self.props = props;
+        should_render
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <>
+
+            <label>
+                { "Scan barcode " }
+                <input
+                    type="file"
+                    accept="image/*"
+                    capture="environment"
+                    disabled=self.props.disabled
+                    onchange=self.link.callback(Msg::PickFile)
+                />
+            </label> { " " }
+
+            { self.view_status() }
+
+            </>
+        }
+    }
+}
+
+impl BarcodeInput {
+    fn view_status(&self) -> Html {
+        match &self.status {
+            Status::Idle => html! {},
+            Status::Reading => html! { <span>{ "Reading barcode..." }</span> },
+            Status::Found(code) => html! { <span>{ "Found " } { code }</span> },
+            Status::Failed(reason) => html! { <span class="sfi-error">{ reason }</span> },
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::services::{
+    data::Resolution,
+    events::{Conflict, ConflictVersion, Entity},
+};
+use yew::prelude::*;
+
+pub struct ConflictCard {
+    link: ComponentLink<Self>,
+    props: Props,
+
+    /// The index of the chosen version for every field
+    choices: Vec<usize>,
+}
+
+pub enum Msg {
+    Choose { field: usize, version: usize },
+    ApplySelection,
+    KeepBoth,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub conflict: Conflict,
+    pub on_resolve: Callback<(Conflict, Resolution)>,
+    #[prop_or_default]
+    pub disabled: bool,
+}
+
+/// The fields of an entity which can be picked individually
+#[derive(Clone, Copy)]
+enum Field {
+    Name,
+    Ean,
+    Members,
+    Details,
+}
+
+impl Component for ConflictCard {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            choices: Self::default_choices(&props.conflict),
+            props,
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Choose { field, version } => {
+                self.choices[field] = version;
+                true
+            }
+            Msg::ApplySelection => {
+                let resolution = Resolution::Pick(self.assemble());
+                self.props
+                    .on_resolve
+                    .emit((self.props.conflict.clone(), resolution));
+                false
+            }
+            Msg::KeepBoth => {
+                self.props
+                    .on_resolve
+                    .emit((self.props.conflict.clone(), Resolution::KeepBoth));
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.choices = Self::default_choices(&props.conflict);
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let title = match &self.latest().entity {
+            Entity::Inventory(record) => format!("Inventory {}", record.name),
+            Entity::Item(record) => format!("Item {}", record.name),
+            Entity::Unit(record) => format!("Unit {}", record.name),
+        };
+
+        html! {
+            <div class="sfi-card">
+                <h3>{ title }</h3>
+                <span class="sfi-subtitle">{ self.props.conflict.entity_uuid }</span>
+
+                { for self.fields().iter().enumerate().map(|(index, field)| self.view_field(index, *field)) }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::ApplySelection)
+                    disabled=self.props.disabled
+                >
+                    { "Apply selection" }
+                </button> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::KeepBoth)
+                    disabled=self.props.disabled
+                >
+                    { "Keep both" }
+                </button>
+            </div>
+        }
+    }
+}
+
+impl ConflictCard {
+    fn view_field(&self, index: usize, field: Field) -> Html {
+        let group = format!("{}-{}", self.props.conflict.entity_uuid, index);
+
+        html! {
+            <fieldset disabled=self.props.disabled>
+                <legend>{ Self::field_name(field) }</legend>
+
+                { for self.props.conflict.versions.iter().enumerate().map(|(version, conflicting)| html! {
+                    <label>
+                        <input
+                            type="radio"
+                            name=group.clone()
+                            checked=self.choices[index] == version
+                            onclick=self.link.callback(move |_| Msg::Choose { field: index, version })
+                        />
+                        { Self::describe(&conflicting.entity, field) }
+                        <span class="sfi-subtitle">
+                            { conflicting.recorded_at.format("%Y-%m-%d %H:%M").to_string() }
+                        </span>
+                    </label>
+                }) }
+            </fieldset>
+        }
+    }
+
+    /// Chooses the most recent version for every field
+    fn default_choices(conflict: &Conflict) -> Vec<usize> {
+        let latest = conflict.versions.len().saturating_sub(1);
+        vec![latest; 3]
+    }
+
+    fn latest(&self) -> &ConflictVersion {
+        self.props
+            .conflict
+            .versions
+            .last()
+            .expect("A conflict has at least two versions")
+    }
+
+    fn fields(&self) -> &'static [Field] {
+        match &self.latest().entity {
+            Entity::Inventory(_) => &[Field::Name, Field::Members],
+            Entity::Item(_) => &[Field::Name, Field::Ean],
+            Entity::Unit(_) => &[Field::Name, Field::Details],
+        }
+    }
+
+    fn field_name(field: Field) -> &'static str {
+        match field {
+            Field::Name => "Name",
+            Field::Ean => "EAN",
+            Field::Members => "Members",
+            Field::Details => "Details",
+        }
+    }
+
+    fn describe(entity: &Entity, field: Field) -> String {
+        match (entity, field) {
+            (Entity::Inventory(record), Field::Name) => record.name.clone(),
+            (Entity::Item(record), Field::Name) => record.name.clone(),
+            (Entity::Unit(record), Field::Name) => record.name.clone(),
+            (Entity::Item(record), Field::Ean) => {
+                record.ean.clone().unwrap_or_else(|| "(none)".to_string())
+            }
+            (Entity::Inventory(record), Field::Members) => format!(
+                "{} admins, {} writables, {} readables",
+                record.admins.len(),
+                record.writables.len(),
+                record.readables.len()
+            ),
+            (Entity::Unit(record), Field::Details) => {
+                let details = &record.details;
+                let expiry = match details.expires {
+                    Some(expires) => format!("Expires {}", expires),
+                    None => "No expiry date".to_string(),
+                };
+
+                format!(
+                    "{}, {} of {} left",
+                    expiry,
+                    details.quantity.measure.describe(details.remaining()),
+                    details.quantity
+                )
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Builds the resolved version from the chosen fields
+    fn assemble(&self) -> Entity {
+        let versions = &self.props.conflict.versions;
+        let mut resolved = self.latest().entity.clone();
+
+        for (index, field) in self.fields().iter().enumerate() {
+            let source = &versions[self.choices[index]].entity;
+
+            match (&mut resolved, source, field) {
+                (Entity::Inventory(target), Entity::Inventory(source), Field::Name) => {
+                    target.name = source.name.clone()
+                }
+                (Entity::Inventory(target), Entity::Inventory(source), Field::Members) => {
+                    target.owner = source.owner;
+                    target.admins = source.admins.clone();
+                    target.writables = source.writables.clone();
+                    target.readables = source.readables.clone();
+                }
+                (Entity::Item(target), Entity::Item(source), Field::Name) => {
+                    target.name = source.name.clone()
+                }
+                (Entity::Item(target), Entity::Item(source), Field::Ean) => {
+                    target.ean = source.ean.clone()
+                }
+                (Entity::Unit(target), Entity::Unit(source), Field::Name) => {
+                    target.name = source.name.clone()
+                }
+                (Entity::Unit(target), Entity::Unit(source), Field::Details) => {
+                    target.details = source.details.clone()
+                }
+                _ => {}
+            }
+        }
+
+        resolved
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    components::{
+        app::{AppRoute, AppRouterButton},
+        core::conflict_card::ConflictCard,
+    },
+    services::{
+        data::{
+            DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+            DataNotification, Resolution, Topic,
+        },
+        events::Conflict,
+    },
+};
+use yew::prelude::*;
+
+pub struct Conflicts {
+    link: ComponentLink<Self>,
+    data_bridge: DataBridge,
+    conflicts: Option<Vec<Conflict>>,
+    is_busy: bool,
+    error: Option<DataError>,
+}
+
+pub enum Msg {
+    AgentOutput(DataAgentOutput),
+    Resolve(Conflict, Resolution),
+}
+
+impl Component for Conflicts {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
+        data_bridge.send(DataAgentRequest::GetConflicts);
+
+        // Resolving (or synchronising) changes the remaining conflicts
+        data_bridge.subscribe(Topic::Conflicts);
+
+        Self {
+            link,
+            data_bridge,
+            conflicts: None,
+            is_busy: false,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Resolve(conflict, resolution) => {
+                self.data_bridge.send(DataAgentRequest::ResolveConflict {
+                    conflict,
+                    resolution,
+                });
+
+                self.error = None;
+                self.is_busy = true;
+                true
+            }
+            Msg::AgentOutput(DataAgentOutput::Reply(_, res)) => match res {
+                DataAgentResponse::Conflicts(conflicts) => {
+                    self.conflicts = Some(conflicts);
+                    true
+                }
+                DataAgentResponse::ResolvedConflict(_) => {
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+                    self.is_busy = false;
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::Conflicts(conflicts) => {
+                    self.conflicts = Some(conflicts);
+                    true
+                }
+                _ => false,
+            },
+        }
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <>
+
+            <h1>{ "Conflicts" }</h1>
+
+            <p>
+                { "These entries were edited on multiple devices at the same time. " }
+                { "Pick the correct value of every field, or keep both versions." }
+            </p>
+
+            <p>
+                { "Entries which were deleted on one device while being edited on another " }
+                { "stay deleted, so they aren't listed here." }
+            </p>
+
+            <AppRouterButton route=AppRoute::Inventories>{ "Go to inventories" }</AppRouterButton>
+
+            <br /> <br />
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
+
+            <div class="sfi-cards-container">
+                { self.view_conflicts() }
+            </div>
+
+            </>
+        }
+    }
+}
+
+impl Conflicts {
+    fn view_conflicts(&self) -> Html {
+        if let Some(conflicts) = &self.conflicts {
+            if conflicts.is_empty() {
+                html! { <p>{ "There are no conflicts to be resolved" }</p> }
+            } else {
+                conflicts
+                    .iter()
+                    .map(|conflict| self.view_conflict(conflict))
+                    .collect()
+            }
+        } else {
+            html! { <p>{ "Loading conflicts..." }</p> }
+        }
+    }
+
+    fn view_conflict(&self, conflict: &Conflict) -> Html {
+        html! {
+            <ConflictCard
+                conflict=conflict.clone()
+                disabled=self.is_busy
+                on_resolve=self.link.callback(|(conflict, resolution)| Msg::Resolve(conflict, resolution))
+            />
+        }
+    }
+}
@@ -3,20 +3,21 @@ use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
 
 use crate::{
     components::app::AppRoute,
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    services::data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
 };
 
 pub struct CreateInventory {
     link: ComponentLink<Self>,
     name: String,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     route_dispatcher: RouteAgentDispatcher,
     is_busy: bool,
+    error: Option<DataError>,
 }
 
 pub enum Msg {
     UpdateName(String),
-    DataAgentResponse(DataAgentResponse),
+    DataAgentOutput(DataAgentOutput),
     Confirm,
     Cancel,
 }
@@ -27,10 +28,11 @@ impl Component for CreateInventory {
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
         Self {
-            data_bridge: DataAgent::bridge(link.callback(Msg::DataAgentResponse)),
+            data_bridge: DataBridge::new(link.callback(Msg::DataAgentOutput)),
             route_dispatcher: RouteAgentDispatcher::new(),
             name: String::new(),
             is_busy: false,
+            error: None,
             link,
         }
     }
@@ -46,6 +48,7 @@ impl Component for CreateInventory {
                 self.data_bridge
                     .send(DataAgentRequest::CreateInventory(self.name.clone()));
 
+                self.error = None;
                 self.is_busy = true;
                 true
             }
@@ -57,7 +60,7 @@ impl Component for CreateInventory {
                 self.is_busy = true;
                 true
             }
-            Msg::DataAgentResponse(response) => match response {
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, response)) => match response {
                 DataAgentResponse::NewInventoryUuid(_uuid) => {
                     self.route_dispatcher
                         .send(RouteRequest::ChangeRoute(AppRoute::Inventories.into()));
@@ -65,8 +68,15 @@ impl Component for CreateInventory {
                     self.is_busy = false;
                     true
                 }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
                 _ => false,
             },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
         }
     }
 
@@ -105,6 +115,11 @@ impl Component for CreateInventory {
                     { "Cancel" }
                 </button>
 
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
             </div>
         }
     }
@@ -1,13 +1,18 @@
 use std::sync::{Arc, RwLock, RwLockReadGuard};
 
-use sfi_core::core::Inventory;
+use sfi_core::core::{Inventory, Item};
 use uuid::Uuid;
 use yew::prelude::*;
 use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
 
 use crate::{
-    components::app::AppRoute,
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    components::{app::AppRoute, core::barcode_input::BarcodeInput},
+    services::{
+        catalogue::{CatalogueAgent, CatalogueRequest, CatalogueResponse},
+        data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
+        ean::{self, EanError},
+    },
+    types::Product,
 };
 
 pub struct CreateItem {
@@ -17,16 +22,32 @@ pub struct CreateItem {
     inventory_uuid: Uuid,
 
     ean: Option<String>,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    ean_error: Option<EanError>,
+
+    /// The catalogue entry of the entered EAN, if any
+    product: Option<Product>,
+
+    /// Whether the name was filled in from the catalogue (and may thus be replaced)
+    name_from_catalogue: bool,
+
+    /// An existing item with the same EAN, which the user may add a unit to instead
+    duplicate: Option<Arc<RwLock<Item>>>,
+
+    data_bridge: DataBridge,
+    catalogue_bridge: Box<dyn Bridge<CatalogueAgent>>,
     route_dispatcher: RouteAgentDispatcher,
     is_busy: bool,
+    error: Option<DataError>,
 }
 
 pub enum Msg {
     UpdateName(String),
     UpdateEan(String),
-    DataAgentResponse(DataAgentResponse),
+    DataAgentOutput(DataAgentOutput),
+    CatalogueResponse(CatalogueResponse),
     Confirm,
+    CreateAnyway,
+    AddUnitToDuplicate,
     Cancel,
 }
 
@@ -42,15 +63,21 @@ impl Component for CreateItem {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let inventory_uuid = props.inventory_uuid;
 
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::DataAgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::DataAgentOutput));
         data_bridge.send(DataAgentRequest::GetInventory(inventory_uuid));
 
         Self {
             data_bridge,
+            catalogue_bridge: CatalogueAgent::bridge(link.callback(Msg::CatalogueResponse)),
             route_dispatcher: RouteAgentDispatcher::new(),
             name: String::new(),
             is_busy: false,
+            error: None,
             ean: None,
+            ean_error: None,
+            product: None,
+            name_from_catalogue: false,
+            duplicate: None,
             link,
             inventory: None,
             inventory_uuid,
@@ -61,19 +88,52 @@ impl Component for CreateItem {
         match msg {
             Msg::UpdateName(name) => {
                 self.name = name;
+                self.name_from_catalogue = false;
                 false
             }
             Msg::UpdateEan(ean) => {
                 self.ean = if ean.is_empty() { None } else { Some(ean) };
+                self.product = None;
+                self.duplicate = None;
+
+                match ean::normalize_optional(self.ean.as_deref()) {
+                    Ok(Some(ean)) => {
+                        self.ean_error = None;
+                        self.catalogue_bridge.send(CatalogueRequest::Lookup(ean));
+                    }
+                    Ok(None) => self.ean_error = None,
+                    Err(error) => self.ean_error = Some(error),
+                }
+                true
+            }
+            Msg::CatalogueResponse(CatalogueResponse::Product(ean, product)) => {
+                // Ignore answers to lookups of EANs entered before
+                if ean::normalize_optional(self.ean.as_deref()) != Ok(Some(ean)) {
+                    return false;
+                }
+
+                if let Some(product) = &product {
+                    // Don't overwrite a name typed by the user
+                    if self.name.is_empty() || self.name_from_catalogue {
+                        self.name = product.name.clone();
+                        self.name_from_catalogue = true;
+                    }
+                }
+
+                self.product = product;
                 true
             }
-            Msg::Confirm => {
-                // Give the new card to the listing component
-                self.data_bridge.send(DataAgentRequest::CreateItem(
-                    self.inventory_uuid,
-                    self.name.clone(),
-                    self.ean.clone(),
-                ));
+            Msg::CatalogueResponse(_) => false,
+            Msg::Confirm => self.create(false),
+            Msg::CreateAnyway => self.create(true),
+            Msg::AddUnitToDuplicate => {
+                if let Some(duplicate) = &self.duplicate {
+                    let item_uuid = duplicate.read().expect("Cannot read item").uuid;
+
+                    self.route_dispatcher.send(RouteRequest::ChangeRoute(
+                        AppRoute::CreateUnit(self.inventory_uuid, item_uuid).into(),
+                    ));
+                }
 
                 self.is_busy = true;
                 true
@@ -86,15 +146,11 @@ impl Component for CreateItem {
                 self.is_busy = true;
                 true
             }
-            Msg::DataAgentResponse(response) => match response {
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, response)) => match response {
                 DataAgentResponse::Inventory(inventory) => {
                     self.inventory = Some(inventory);
                     true
                 }
-                DataAgentResponse::InvalidInventoryUuid => {
-                    self.inventory = None;
-                    true
-                }
                 DataAgentResponse::NewItemUuid(_) => {
                     self.route_dispatcher.send(RouteRequest::ChangeRoute(
                         AppRoute::Items(self.inventory_uuid).into(),
@@ -103,14 +159,29 @@ impl Component for CreateItem {
                     self.is_busy = false;
                     true
                 }
-                DataAgentResponse::Inventories(_)
-                | DataAgentResponse::NewInventoryUuid(_)
-                | DataAgentResponse::UpdatedItem
-                | DataAgentResponse::Item(_)
-                | DataAgentResponse::DeletedInventory(_)
-                | DataAgentResponse::DeletedItem(_)
-                | DataAgentResponse::UpdatedInventory(_) => false,
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::InvalidEan(error) => {
+                    self.ean_error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::DuplicateEan(item) => {
+                    self.duplicate = Some(item);
+
+                    self.is_busy = false;
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
             },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
         }
     }
 
@@ -122,7 +193,10 @@ impl Component for CreateItem {
         let inventory = if let Some(inventory) = &self.inventory {
             inventory.read().expect("Cannot read inventory")
         } else {
-            return html! { <p>{ "Cannot find this inventory" }</p> };
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
         };
 
         html! {
@@ -148,10 +222,27 @@ impl Component for CreateItem {
                     oninput=self.link.callback(|i: InputData| Msg::UpdateEan(i.value))
                 /> { " " }
 
+                // Explain what's wrong with the EAN
+                { self.view_ean_error() }
+
+                // Read the EAN from a photo of the barcode
+                <BarcodeInput
+                    disabled=self.is_busy
+                    on_detect=self.link.callback(Msg::UpdateEan)
+                />
+
+                // What the catalogue knows about the product
+                { self.view_product() }
+
+                // Offer to use the existing item with the same EAN
+                { self.view_duplicate() }
+
+                <br /> <br />
+
                 // Save edits button
                 <button
                     onclick=self.link.callback(|_| Msg::Confirm)
-                    disabled=self.is_busy
+                    disabled=self.is_busy || self.ean_error.is_some() || self.duplicate.is_some()
                 >
                     { "Save" }
                 </button>  { " " }
@@ -164,7 +255,100 @@ impl Component for CreateItem {
                     { "Cancel" }
                 </button>
 
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
             </div>
         }
     }
 }
+
+impl CreateItem {
+    fn create(&mut self, allow_duplicate: bool) -> ShouldRender {
+        let ean = match ean::normalize_optional(self.ean.as_deref()) {
+            Ok(ean) => ean,
+            Err(error) => {
+                self.ean_error = Some(error);
+                return true;
+            }
+        };
+
+        // Give the new card to the listing component
+        self.data_bridge.send(DataAgentRequest::CreateItem {
+            inventory_uuid: self.inventory_uuid,
+            name: self.name.clone(),
+            ean,
+            allow_duplicate,
+        });
+
+        self.error = None;
+        self.is_busy = true;
+        true
+    }
+
+    fn view_duplicate(&self) -> Html {
+        let duplicate = match &self.duplicate {
+            Some(duplicate) => duplicate.read().expect("Cannot read item"),
+            None => return html! {},
+        };
+
+        html! {
+            <p>
+                <span class="sfi-error">
+                    { "This inventory already contains " }
+                    <strong>{ &duplicate.name }</strong>
+                    { " with the same EAN. " }
+                </span>
+
+                <button
+                    onclick=self.link.callback(|_| Msg::AddUnitToDuplicate)
+                    disabled=self.is_busy
+                >
+                    { "Add a unit to it instead" }
+                </button> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::CreateAnyway)
+                    disabled=self.is_busy
+                >
+                    { "Create another item anyway" }
+                </button>
+            </p>
+        }
+    }
+
+    fn view_ean_error(&self) -> Html {
+        match &self.ean_error {
+            Some(error) => html! { <span class="sfi-error">{ error }</span> },
+            None => html! {},
+        }
+    }
+
+    fn view_product(&self) -> Html {
+        let product = match &self.product {
+            Some(product) => product,
+            None => return html! {},
+        };
+
+        let details: Vec<String> = vec![
+            product.brand.clone(),
+            product.category.clone(),
+            product
+                .shelf_life_days
+                .map(|days| format!("keeps for {} days", days)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        html! {
+            <p>
+                { "Found in the catalogue: " }
+                <strong>{ &product.name }</strong>
+                { if details.is_empty() { String::new() } else { format!(" ({})", details.join(", ")) } }
+            </p>
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::NaiveDate;
+use sfi_core::core::Item;
+use uuid::Uuid;
+use yew::prelude::*;
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::app::AppRoute,
+    services::data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
+    types::{Measure, Quantity, UnitDetails},
+};
+
+pub struct CreateUnit {
+    link: ComponentLink<Self>,
+    name: String,
+    expires: Option<NaiveDate>,
+    amount: String,
+    measure: Measure,
+    item: Option<Arc<RwLock<Item>>>,
+    props: Props,
+
+    data_bridge: DataBridge,
+    route_dispatcher: RouteAgentDispatcher,
+    is_busy: bool,
+    error: Option<DataError>,
+}
+
+pub enum Msg {
+    UpdateName(String),
+    UpdateExpires(String),
+    UpdateAmount(String),
+    UpdateMeasure(String),
+    DataAgentOutput(DataAgentOutput),
+    Confirm,
+    Cancel,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub inventory_uuid: Uuid,
+    pub item_uuid: Uuid,
+}
+
+impl Component for CreateUnit {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut data_bridge = DataBridge::new(link.callback(Msg::DataAgentOutput));
+        data_bridge.send(DataAgentRequest::GetItem(
+            props.inventory_uuid,
+            props.item_uuid,
+        ));
+
+        Self {
+            data_bridge,
+            route_dispatcher: RouteAgentDispatcher::new(),
+            name: String::new(),
+            expires: None,
+            amount: "1".to_string(),
+            measure: Measure::Pieces,
+            is_busy: false,
+            error: None,
+            link,
+            item: None,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::UpdateName(name) => {
+                self.name = name;
+                false
+            }
+            Msg::UpdateExpires(expires) => {
+                self.expires = NaiveDate::parse_from_str(&expires, "%Y-%m-%d").ok();
+                false
+            }
+            Msg::UpdateAmount(amount) => {
+                self.amount = amount;
+                true
+            }
+            Msg::UpdateMeasure(measure) => {
+                if let Some(measure) = Measure::from_key(&measure) {
+                    self.measure = measure;
+                }
+                true
+            }
+            Msg::Confirm => {
+                self.data_bridge.send(DataAgentRequest::CreateUnit(
+                    self.props.inventory_uuid,
+                    self.props.item_uuid,
+                    self.name.clone(),
+                    UnitDetails {
+                        expires: self.expires,
+                        quantity: Quantity {
+                            amount: self.parsed_amount().unwrap_or(1.0),
+                            measure: self.measure,
+                        },
+                        history: vec![],
+                    },
+                ));
+
+                self.error = None;
+                self.is_busy = true;
+                true
+            }
+            Msg::Cancel => {
+                // Cancel the creation of the unit
+                self.route_dispatcher.send(RouteRequest::ChangeRoute(
+                    AppRoute::Units(self.props.inventory_uuid, self.props.item_uuid).into(),
+                ));
+
+                self.is_busy = true;
+                true
+            }
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, response)) => match response {
+                DataAgentResponse::Item(item) => {
+                    self.item = Some(item);
+                    true
+                }
+                DataAgentResponse::NewUnitUuid(_) => {
+                    self.route_dispatcher.send(RouteRequest::ChangeRoute(
+                        AppRoute::Units(self.props.inventory_uuid, self.props.item_uuid).into(),
+                    ));
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let item = if let Some(item) = &self.item {
+            item.read().expect("Cannot read item")
+        } else {
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
+        };
+
+        html! {
+            <div>
+                // A heading
+                <h2>{ "Add a new unit of " } {item.name.clone()}</h2>
+
+                // The name input
+                <input
+                    type="text"
+                    placeholder="name"
+                    disabled=self.is_busy
+                    value={self.name.to_owned()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateName(i.value))
+                /> { " " }
+
+                // The expiry date input
+                <label>
+                    { "Expires " }
+                    <input
+                        type="date"
+                        disabled=self.is_busy
+                        value={self.expires.map(|date| date.to_string()).unwrap_or_default()}
+                        oninput=self.link.callback(|i: InputData| Msg::UpdateExpires(i.value))
+                    />
+                </label> { " " }
+
+                // The quantity inputs
+                <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    placeholder="quantity"
+                    disabled=self.is_busy
+                    value={self.amount.to_owned()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateAmount(i.value))
+                /> { " " }
+
+                <select
+                    disabled=self.is_busy
+                    onchange=self.link.callback(|c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::UpdateMeasure(select.value()),
+                        _ => Msg::UpdateMeasure(String::new()),
+                    })
+                >
+                    { for Measure::ALL.iter().map(|measure| html! {
+                        <option value=measure.key() selected=*measure == self.measure>
+                            { measure.name() }
+                        </option>
+                    }) }
+                </select> { " " }
+
+                // Save edits button
+                <button
+                    onclick=self.link.callback(|_| Msg::Confirm)
+                    disabled=self.is_busy || self.parsed_amount().is_none()
+                >
+                    { "Save" }
+                </button>  { " " }
+
+                // Cancel button
+                <button
+                    onclick=self.link.callback(|_| Msg::Cancel)
+                    disabled=self.is_busy
+                >
+                    { "Cancel" }
+                </button>
+
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
+            </div>
+        }
+    }
+}
+
+impl CreateUnit {
+    /// Returns the entered quantity, if it's a positive number
+    fn parsed_amount(&self) -> Option<f64> {
+        self.amount
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| *amount > 0.0)
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    components::app::{AppRoute, AppRouterButton},
+    services::{
+        config,
+        data::{
+            DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+            DataNotification, Topic,
+        },
+    },
+    types::ExpiringUnit,
+};
+use yew::prelude::*;
+
+/// Lists every unit expiring within the configured window, soonest first
+pub struct Expiring {
+    link: ComponentLink<Self>,
+    data_bridge: DataBridge,
+    units: Option<Vec<ExpiringUnit>>,
+    window_days: i64,
+    error: Option<DataError>,
+}
+
+pub enum Msg {
+    AgentOutput(DataAgentOutput),
+    UpdateWindow(String),
+    RequestNewState,
+}
+
+impl Component for Expiring {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let window_days = config::expiry_window_days();
+
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
+        data_bridge.send(DataAgentRequest::GetExpiringUnits(window_days));
+        data_bridge.subscribe(Topic::Inventories);
+
+        Self {
+            link,
+            data_bridge,
+            units: None,
+            window_days,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::UpdateWindow(days) => {
+                if let Ok(days) = days.parse::<i64>() {
+                    if days >= 0 {
+                        self.window_days = days;
+
+                        // Without localStorage, the window only applies until leaving the page
+                        config::set_expiry_window_days(days).ok();
+                        self.link.send_message(Msg::RequestNewState);
+                    }
+                }
+                false
+            }
+            Msg::RequestNewState => {
+                self.data_bridge
+                    .send(DataAgentRequest::GetExpiringUnits(self.window_days));
+                false
+            }
+            Msg::AgentOutput(DataAgentOutput::Reply(_, res)) => match res {
+                DataAgentResponse::ExpiringUnits(units) => {
+                    self.units = Some(units);
+                    self.error = None;
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::Conflicts(_) => false,
+                _ => {
+                    // The data has changed, fetch the new state
+                    self.link.send_message(Msg::RequestNewState);
+                    false
+                }
+            },
+        }
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <>
+
+            <h1>{ "Expiring soon" }</h1>
+
+            <label>
+                { "Show units expiring within " }
+                <input
+                    type="number"
+                    min="0"
+                    value={self.window_days.to_string()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateWindow(i.value))
+                />
+                { " days" }
+            </label>
+
+            <br /> <br />
+
+            <AppRouterButton route=AppRoute::Inventories>{ "Inventories" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::Search>{ "Search" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::Settings>{ "Settings" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::AccountSettings>{ "Account" }</AppRouterButton>
+
+            <br /> <br />
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
+
+            <div class="sfi-cards-container">
+                { self.view_units() }
+            </div>
+
+            </>
+        }
+    }
+}
+
+impl Expiring {
+    fn view_units(&self) -> Html {
+        match &self.units {
+            None => html! { <p>{ "Loading units..." }</p> },
+            Some(units) if units.is_empty() => {
+                html! { <p>{ "Nothing expires within this time frame." }</p> }
+            }
+            Some(units) => units.iter().map(|unit| self.view_unit(unit)).collect(),
+        }
+    }
+
+    fn view_unit(&self, expiring: &ExpiringUnit) -> Html {
+        let details = &expiring.details;
+        let units_route = AppRoute::Units(expiring.inventory_uuid, expiring.item_uuid);
+
+        html! {
+            <div class=("sfi-card", details.urgency().css_class())>
+                <h3>{ &expiring.item_name } { " (" } { &expiring.unit.name } { ")" }</h3>
+                <span class="sfi-subtitle">{ &expiring.inventory_name }</span>
+
+                <p class="sfi-expiry">
+                    { details.describe_expiry() } { " (" } { expiring.expires } { ")" }
+                </p>
+
+                <p>
+                    { details.quantity.measure.describe(details.remaining()) } { " left" }
+                </p>
+
+                <AppRouterButton route=units_route>{ "Open item" }</AppRouterButton>
+            </div>
+        }
+    }
+}
@@ -1,26 +1,35 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use crate::{
     components::{
         app::{AppRoute, AppRouterButton},
         core::inventory_card::InventoryCard,
     },
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    services::data::{
+        DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+        DataNotification, Topic,
+    },
+    types::Role,
 };
 use sfi_core::core::Inventory;
-use yew::{prelude::*, Bridge};
+use uuid::Uuid;
+use yew::prelude::*;
 
 pub enum Msg {
     // NewState(&'static Vec<InventoryHandle<'static>>),
     // NewState(Vec<InventoryHandle<'static>>),
-    AgentResponse(DataAgentResponse),
-    RequestNewState,
+    AgentOutput(DataAgentOutput),
 }
 
 pub struct Inventories {
-    link: ComponentLink<Inventories>,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     inventories: Option<Vec<Arc<RwLock<Inventory>>>>,
+    roles: HashMap<Uuid, Role>,
+    conflicts: usize,
+    error: Option<DataError>,
 }
 
 impl Component for Inventories {
@@ -29,41 +38,93 @@ impl Component for Inventories {
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
         // Initiate a bridge to the data agent
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::AgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
 
         // Request a list of the currently accessible inventory handles
         data_bridge.send(DataAgentRequest::GetInventories);
 
+        // Check for concurrent edits which need to be resolved by the user
+        data_bridge.send(DataAgentRequest::GetConflicts);
+
+        // Keep both up to date, e.g. after edits or a synchronisation
+        data_bridge.subscribe(Topic::Inventories);
+        data_bridge.subscribe(Topic::Conflicts);
+
         // Create the component
         Self {
             data_bridge,
-            link,
             inventories: None,
+            roles: HashMap::new(),
+            conflicts: 0,
+            error: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::RequestNewState => {
-                self.data_bridge.send(DataAgentRequest::GetInventories);
-                false
-            }
-            Msg::AgentResponse(response) => match response {
+            Msg::AgentOutput(DataAgentOutput::Reply(_, response)) => match response {
                 DataAgentResponse::Inventories(inventories) => {
-                    self.inventories = Some(inventories);
+                    self.set_inventories(inventories);
+                    true
+                }
+                DataAgentResponse::Roles(roles) => {
+                    self.roles = roles;
                     true
                 }
                 DataAgentResponse::NewInventoryUuid(_uuid) => false,
+                DataAgentResponse::Conflicts(conflicts) => {
+                    self.conflicts = conflicts.len();
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+                    true
+                }
 
-                // These responses should be ignored
-                DataAgentResponse::Inventory(_)
-                | DataAgentResponse::InvalidInventoryUuid
-                | DataAgentResponse::Item(_)
-                | DataAgentResponse::UpdatedItem
-                | DataAgentResponse::NewItemUuid(_)
-                | DataAgentResponse::DeletedInventory(_)
-                | DataAgentResponse::DeletedItem(_)
-                | DataAgentResponse::UpdatedInventory(_) => false,
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::Inventories(inventories) => {
+                    self.set_inventories(inventories);
+                    true
+                }
+                DataNotification::CreatedInventory(inventory) => {
+                    if let Some(inventories) = &mut self.inventories {
+                        inventories.push(inventory);
+                    }
+
+                    self.data_bridge.send(DataAgentRequest::GetRoles);
+                    true
+                }
+                DataNotification::UpdatedInventory(inventory) => {
+                    let uuid = inventory.read().expect("Cannot read inventory").uuid;
+
+                    if let Some(inventories) = &mut self.inventories {
+                        for existing in inventories.iter_mut() {
+                            if existing.read().expect("Cannot read inventory").uuid == uuid {
+                                *existing = inventory.clone();
+                            }
+                        }
+                    }
+
+                    // The memberships may have changed as well
+                    self.data_bridge.send(DataAgentRequest::GetRoles);
+                    true
+                }
+                DataNotification::DeletedInventory(uuid) => {
+                    if let Some(inventories) = &mut self.inventories {
+                        inventories.retain(|inventory| {
+                            inventory.read().expect("Cannot read inventory").uuid != uuid
+                        });
+                    }
+                    true
+                }
+                DataNotification::Conflicts(conflicts) => {
+                    self.conflicts = conflicts.len();
+                    true
+                }
+                _ => false,
             },
         }
     }
@@ -78,17 +139,21 @@ impl Component for Inventories {
 
             <h1>{ "Inventories" }</h1>
 
-            <button onclick=self.link.callback(|_| Msg::RequestNewState)>
-                { "Refresh inventories" }
-            </button>  { " " }
-
             <AppRouterButton route=AppRoute::Home>{ "Go to home" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::Search>{ "Search" }</AppRouterButton> { " " }
 
             // Create inventory
             <AppRouterButton route=AppRoute::CreateInventory>{ "New inventory" }</AppRouterButton>
 
             <br /> <br />
 
+            { self.view_conflicts() }
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
+
             <div class="sfi-cards-container">
                 { self.view_inventories() }
             </div>
@@ -99,6 +164,27 @@ impl Component for Inventories {
 }
 
 impl Inventories {
+    fn set_inventories(&mut self, inventories: Vec<Arc<RwLock<Inventory>>>) {
+        self.inventories = Some(inventories);
+        self.error = None;
+
+        // The memberships may have changed as well
+        self.data_bridge.send(DataAgentRequest::GetRoles);
+    }
+
+    fn view_conflicts(&self) -> Html {
+        if self.conflicts == 0 {
+            return html! {};
+        }
+
+        html! {
+            <p>
+                { format!("{} conflicting edits need your attention ", self.conflicts) }
+                <AppRouterButton route=AppRoute::Conflicts>{ "Resolve conflicts" }</AppRouterButton>
+            </p>
+        }
+    }
+
     fn view_inventories(&self) -> Html {
         if let Some(handles) = &self.inventories {
             if handles.is_empty() {
@@ -106,7 +192,7 @@ impl Inventories {
             } else {
                 handles
                     .iter()
-                    .map(|inventory| Self::view_inventory(inventory))
+                    .map(|inventory| self.view_inventory(inventory))
                     .collect()
             }
         } else {
@@ -114,8 +200,10 @@ impl Inventories {
         }
     }
 
-    fn view_inventory(inventory: &Arc<RwLock<Inventory>>) -> Html {
+    fn view_inventory(&self, inventory: &Arc<RwLock<Inventory>>) -> Html {
         let inventory = inventory.read().expect("Cannot read inventory").to_owned();
-        html! { <InventoryCard inventory=inventory /> }
+        let role = self.roles.get(&inventory.uuid).copied();
+
+        html! { <InventoryCard inventory=inventory role=role /> }
     }
 }
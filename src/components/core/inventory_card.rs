@@ -1,6 +1,7 @@
 use crate::{
     components::app::{AppRoute, AppRouterButton},
     services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    types::{Action, Role},
 };
 use sfi_core::core::Inventory;
 use yew::prelude::*;
@@ -20,6 +21,10 @@ pub enum Msg {
 #[derive(Clone, Properties)]
 pub struct Props {
     pub inventory: Inventory,
+
+    /// The role of the current user, if they are a member
+    #[prop_or_default]
+    pub role: Option<Role>,
 }
 
 impl Component for InventoryCard {
@@ -44,18 +49,26 @@ impl Component for InventoryCard {
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
     }
 
     fn view(&self) -> Html {
+        let may_edit = self
+            .props
+            .role
+            .map_or(false, |role| role.allows(Action::EditInventory));
+
         html! {
             <div class="sfi-card">
                 <h3>{ self.props.inventory.name.clone() }</h3>
                 <span class="sfi-subtitle">{ self.props.inventory.uuid }</span>
 
                 <AppRouterButton route=AppRoute::Items(self.props.inventory.uuid)>{ "Open inventory" }</AppRouterButton> { " " }
-                <AppRouterButton route=AppRoute::UpdateInventory(self.props.inventory.uuid)>{ "Edit" }</AppRouterButton> { " " }
+                { if may_edit { html! {
+                    <AppRouterButton route=AppRoute::UpdateInventory(self.props.inventory.uuid)>{ "Edit" }</AppRouterButton>
+                } } else { html! {} } } { " " }
                 <button disabled=true onclick=self.link.callback(|_| Msg::ExportInventory)>{ "Export" }</button> { " " }
             </div>
         }
@@ -0,0 +1,310 @@
+use chrono::Local;
+use sfi_core::core::users::StatusNotice;
+use uuid::Uuid;
+use yew::{
+    prelude::*,
+    services::{fetch::FetchTask, DialogService},
+};
+
+use crate::{
+    components::core::qr_code::QrCode,
+    services::{
+        api::{ApiClient, ApiError},
+        invitations::{Invitation, NewInvitation, DEFAULT_VALIDITY_DAYS},
+    },
+    types::Role,
+};
+
+/// Lets admins invite people to an inventory using links and QR codes
+pub struct Invitations {
+    link: ComponentLink<Self>,
+    props: Props,
+
+    /// The outstanding invitations, once loaded
+    invitations: Option<Vec<Invitation>>,
+
+    /// The invitation created last, which is shown as a QR code
+    created: Option<Invitation>,
+
+    role: Role,
+    valid_for_days: String,
+    error: Option<String>,
+
+    // Avoid dropping requests
+    fetch_task: Option<FetchTask>,
+}
+
+pub enum Msg {
+    Loaded(Result<Vec<Invitation>, ApiError>),
+    UpdateRole(String),
+    UpdateValidity(String),
+    Create,
+    Created(Result<Invitation, ApiError>),
+    Revoke(String),
+    Revoked(String, Result<StatusNotice, ApiError>),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub inventory_uuid: Uuid,
+}
+
+impl Component for Invitations {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut invitations = Self {
+            link,
+            props,
+            invitations: None,
+            created: None,
+            role: Role::Writer,
+            valid_for_days: DEFAULT_VALIDITY_DAYS.to_string(),
+            error: None,
+            fetch_task: None,
+        };
+
+        invitations.load();
+        invitations
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Loaded(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(invitations) => self.invitations = Some(invitations),
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::UpdateRole(key) => {
+                self.role = Role::from_key(&key).unwrap_or(Role::Writer);
+                false
+            }
+            Msg::UpdateValidity(days) => {
+                self.valid_for_days = days;
+                true
+            }
+            Msg::Create => {
+                let valid_for_days = match self.parsed_validity() {
+                    Some(days) => days,
+                    None => return false,
+                };
+
+                let invitation = NewInvitation {
+                    role: self.role,
+                    valid_for_days,
+                };
+
+                let callback = self.link.callback(Msg::Created);
+                let task = ApiClient::configured().create_invitation(
+                    self.props.inventory_uuid,
+                    &invitation,
+                    callback,
+                );
+
+                self.start(task)
+            }
+            Msg::Created(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(invitation) => {
+                        if let Some(invitations) = &mut self.invitations {
+                            invitations.push(invitation.clone());
+                        }
+
+                        self.created = Some(invitation);
+                        self.error = None;
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::Revoke(token) => {
+                if !DialogService::confirm("Revoke this invitation?\nIts link will stop working.") {
+                    return false;
+                }
+
+                let revoked_token = token.clone();
+                let callback = self
+                    .link
+                    .callback(move |result| Msg::Revoked(revoked_token.clone(), result));
+
+                let task = ApiClient::configured().revoke_invitation(
+                    self.props.inventory_uuid,
+                    &token,
+                    callback,
+                );
+
+                self.start(task)
+            }
+            Msg::Revoked(token, result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(_) => {
+                        if let Some(invitations) = &mut self.invitations {
+                            invitations.retain(|invitation| invitation.token != token);
+                        }
+
+                        if self.created.as_ref().map(|i| &i.token) == Some(&token) {
+                            self.created = None;
+                        }
+
+                        self.error = None;
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.inventory_uuid != props.inventory_uuid {
+            self.props = props;
+            self.load();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        let is_busy = self.fetch_task.is_some();
+
+        html! {
+            <div>
+                <h3>{ "Invitations" }</h3>
+
+                <p>{ "Anyone with an invitation link can join this inventory after logging in." }</p>
+
+                <select
+                    disabled=is_busy
+                    onchange=self.link.callback(|c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::UpdateRole(select.value()),
+                        _ => Msg::UpdateRole(String::new()),
+                    })
+                >
+                    { for Role::MEMBER_ROLES.iter().map(|role| html! {
+                        <option value=role.key() selected=*role == self.role>
+                            { role.name() }
+                        </option>
+                    }) }
+                </select> { " for " }
+
+                <input
+                    type="number"
+                    min="1"
+                    disabled=is_busy
+                    value=self.valid_for_days.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateValidity(i.value))
+                /> { " days " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::Create)
+                    disabled=is_busy || self.parsed_validity().is_none()
+                >
+                    { "Create invitation" }
+                </button>
+
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
+                { self.view_created() }
+
+                { self.view_invitations() }
+            </div>
+        }
+    }
+}
+
+impl Invitations {
+    fn load(&mut self) {
+        let callback = self.link.callback(Msg::Loaded);
+        let task = ApiClient::configured().invitations(self.props.inventory_uuid, callback);
+
+        self.start(task);
+    }
+
+    /// Keeps a request alive, or shows why it couldn't be sent
+    fn start(&mut self, task: Result<FetchTask, ApiError>) -> ShouldRender {
+        match task {
+            Ok(task) => self.fetch_task = Some(task),
+            Err(error) => self.error = Some(error.to_string()),
+        }
+        true
+    }
+
+    fn parsed_validity(&self) -> Option<u32> {
+        self.valid_for_days
+            .trim()
+            .parse()
+            .ok()
+            .filter(|days| *days > 0)
+    }
+
+    fn view_created(&self) -> Html {
+        let invitation = match &self.created {
+            Some(invitation) => invitation,
+            None => return html! {},
+        };
+
+        let url = invitation.url();
+
+        html! {
+            <div>
+                <p>
+                    { "Share this link or let the recipient scan the code:" } <br />
+                    <a href=url.clone()>{ &url }</a>
+                </p>
+
+                <QrCode data=url />
+            </div>
+        }
+    }
+
+    fn view_invitations(&self) -> Html {
+        let invitations = match &self.invitations {
+            Some(invitations) => invitations,
+            None => return html! { <p>{ "Loading invitations..." }</p> },
+        };
+
+        let outstanding: Vec<&Invitation> = invitations
+            .iter()
+            .filter(|invitation| !invitation.is_expired())
+            .collect();
+
+        if outstanding.is_empty() {
+            return html! { <p>{ "There are no outstanding invitations." }</p> };
+        }
+
+        html! {
+            <ul>
+                { for outstanding.into_iter().map(|invitation| {
+                    let token = invitation.token.clone();
+
+                    html! {
+                        <li>
+                            { invitation.role.name() }
+                            { format!(", valid until {} ", invitation.expires.with_timezone(&Local).format("%Y-%m-%d %H:%M")) }
+
+                            <button
+                                onclick=self.link.callback(move |_| Msg::Revoke(token.clone()))
+                                disabled=self.fetch_task.is_some()
+                            >
+                                { "Revoke" }
+                            </button>
+                        </li>
+                    }
+                }) }
+            </ul>
+        }
+    }
+}
@@ -19,6 +19,10 @@ pub enum Msg {
 #[derive(Clone, Properties)]
 pub struct Props {
     pub item: Item,
+
+    /// Hides the actions the current user isn't allowed to perform
+    #[prop_or_default]
+    pub read_only: bool,
 }
 
 impl Component for ItemCard {
@@ -39,8 +43,9 @@ impl Component for ItemCard {
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
     }
 
     fn view(&self) -> Html {
@@ -54,7 +59,9 @@ impl Component for ItemCard {
                 <span class="sfi-subtitle">{ self.props.item.uuid }</span>
 
                 <AppRouterButton route=open_item_route>{ "Open Item" }</AppRouterButton> { " " }
-                <AppRouterButton route=update_item_route>{ "Edit" }</AppRouterButton> { " " }
+                { if self.props.read_only { html! {} } else { html! {
+                    <AppRouterButton route=update_item_route>{ "Edit" }</AppRouterButton>
+                } } } { " " }
             </div>
         }
     }
@@ -5,22 +5,29 @@ use crate::{
         app::{AppRoute, AppRouterButton},
         core::item_card::ItemCard,
     },
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    services::data::{
+        DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+        DataNotification, Topic,
+    },
+    types::{Action, Role},
 };
 use sfi_core::core::{Inventory, Item};
 use uuid::Uuid;
 use yew::prelude::*;
 
 pub struct Items {
-    link: ComponentLink<Self>,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     inventory: Option<Arc<RwLock<Inventory>>>,
     inventory_uuid: Uuid,
+
+    /// The role of the current user in this inventory
+    role: Option<Role>,
+
+    error: Option<DataError>,
 }
 
 pub enum Msg {
-    AgentResponse(DataAgentResponse),
-    RequestNewState,
+    AgentOutput(DataAgentOutput),
 }
 
 #[derive(Clone, Properties)]
@@ -35,43 +42,65 @@ impl Component for Items {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let inventory_uuid = props.inventory_uuid;
 
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::AgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
         data_bridge.send(DataAgentRequest::GetInventory(inventory_uuid));
+        data_bridge.send(DataAgentRequest::GetRoles);
+
+        // Keep the items up to date, e.g. after a synchronisation
+        data_bridge.subscribe(Topic::Inventory(inventory_uuid));
 
         Self {
-            link,
             data_bridge,
             inventory: None,
             inventory_uuid,
+            role: None,
+            error: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::RequestNewState => {
-                self.data_bridge
-                    .send(DataAgentRequest::GetInventory(self.inventory_uuid));
-                false
-            }
-            Msg::AgentResponse(res) => match res {
+            Msg::AgentOutput(DataAgentOutput::Reply(_, res)) => match res {
                 DataAgentResponse::Inventory(inventory) => {
                     self.inventory = Some(inventory);
+                    self.error = None;
+                    true
+                }
+                DataAgentResponse::Roles(roles) => {
+                    self.role = roles.get(&self.inventory_uuid).copied();
                     true
                 }
-                DataAgentResponse::InvalidInventoryUuid => {
+                DataAgentResponse::Error(error) => {
+                    if let DataError::UnknownInventory(_) = error {
+                        self.inventory = None;
+                    }
+
+                    self.error = Some(error);
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::UpdatedInventory(inventory) => {
+                    self.inventory = Some(inventory);
+
+                    // The memberships may have changed as well
+                    self.data_bridge.send(DataAgentRequest::GetRoles);
+                    true
+                }
+                DataNotification::DeletedInventory(uuid) => {
                     self.inventory = None;
+                    self.error = Some(DataError::UnknownInventory(uuid));
                     true
                 }
 
-                // These responses should be ignored
-                DataAgentResponse::Inventories(_)
-                | DataAgentResponse::NewInventoryUuid(_)
-                | DataAgentResponse::Item(_)
-                | DataAgentResponse::UpdatedItem
-                | DataAgentResponse::DeletedInventory(_)
-                | DataAgentResponse::NewItemUuid(_)
-                | DataAgentResponse::DeletedItem(_)
-                | DataAgentResponse::UpdatedInventory(_) => false,
+                // The items are part of the inventory, so they only need to be shown again
+                DataNotification::CreatedItem(_)
+                | DataNotification::UpdatedItem(_)
+                | DataNotification::DeletedItem { .. } => true,
+                _ => false,
             },
         }
     }
@@ -84,7 +113,10 @@ impl Component for Items {
         let inventory = if let Some(inventory) = &self.inventory {
             inventory.read().expect("Cannot read inventory")
         } else {
-            return html! { <p>{ "Cannot find this inventory" }</p> };
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
         };
 
         html! {
@@ -92,15 +124,18 @@ impl Component for Items {
 
             <h1>{ "Items of " } {inventory.name.clone()}</h1>
 
-            <button onclick=self.link.callback(|_| Msg::RequestNewState)>
-                { "Refresh items" }
-            </button>  { " " }
-
             <AppRouterButton route=AppRoute::Home>{ "Go to home" }</AppRouterButton> { " " }
             <AppRouterButton route=AppRoute::Inventories>{ "Go to inventories" }</AppRouterButton> { " " }
 
-            // Create inventory
-            <AppRouterButton route=AppRoute::CreateItem(self.inventory_uuid)>{ "New item" }</AppRouterButton>
+            // Create item
+            { if self.may_edit() { html! {
+                <AppRouterButton route=AppRoute::CreateItem(self.inventory_uuid)>{ "New item" }</AppRouterButton>
+            } } else { html! {} } }
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
 
             <br /> <br />
 
@@ -128,12 +163,17 @@ impl Items {
         if items.is_empty() {
             html! { <p>{ "This inventory doesn't currently contain any items." }</p> }
         } else {
-            items.iter().map(|item| Self::view_item(item)).collect()
+            items.iter().map(|item| self.view_item(item)).collect()
         }
     }
 
-    fn view_item(item: &Arc<RwLock<Item>>) -> Html {
+    fn view_item(&self, item: &Arc<RwLock<Item>>) -> Html {
         let item = item.read().expect("Cannot read item").to_owned();
-        html! { <ItemCard item=item /> }
+        html! { <ItemCard item=item read_only=!self.may_edit() /> }
+    }
+
+    fn may_edit(&self) -> bool {
+        self.role
+            .map_or(false, |role| role.allows(Action::EditContents))
     }
 }
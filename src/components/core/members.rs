@@ -0,0 +1,347 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use sfi_core::core::users::{UserIdentifier, UserInfo};
+use uuid::Uuid;
+use yew::{
+    prelude::*,
+    services::{fetch::FetchTask, DialogService},
+};
+
+use crate::{
+    components::login::AuthState,
+    services::{
+        api::{ApiClient, ApiError},
+        auth::AuthAgent,
+    },
+    types::{Membership, Role},
+};
+
+/// Lists the members of an inventory by role, and lets admins edit them
+pub struct Members {
+    link: ComponentLink<Self>,
+    props: Props,
+    _auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    current_user: Option<Uuid>,
+
+    /// The names of the members, as far as they are known already
+    names: HashMap<Uuid, String>,
+    name_tasks: HashMap<Uuid, FetchTask>,
+
+    /// The members whose names couldn't be resolved, which aren't asked for again
+    unresolved: HashSet<Uuid>,
+
+    new_member_name: String,
+    new_member_role: Role,
+    lookup_task: Option<FetchTask>,
+    error: Option<String>,
+}
+
+pub enum Msg {
+    NewAuthState(Rc<AuthState>),
+    ResolvedName(Uuid, Result<UserInfo, ApiError>),
+
+    UpdateNewMemberName(String),
+    UpdateNewMemberRole(String),
+    AddMember,
+    FoundUser(Result<UserInfo, ApiError>),
+
+    ChangeRole(Uuid, String),
+    Remove(Uuid),
+    TransferOwnership(Uuid),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub membership: Membership,
+    pub on_change: Callback<Membership>,
+
+    #[prop_or_default]
+    pub disabled: bool,
+}
+
+impl Component for Members {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut members = Self {
+            _auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            current_user: None,
+            names: HashMap::new(),
+            name_tasks: HashMap::new(),
+            unresolved: HashSet::new(),
+            new_member_name: String::new(),
+            new_member_role: Role::Writer,
+            lookup_task: None,
+            error: None,
+            link,
+            props,
+        };
+
+        members.resolve_names();
+        members
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewAuthState(auth_state) => {
+                self.current_user = auth_state.user().map(|user_info| {
+                    self.names.insert(user_info.uuid, user_info.name.clone());
+                    user_info.uuid
+                });
+                true
+            }
+            Msg::ResolvedName(uuid, result) => {
+                self.name_tasks.remove(&uuid);
+
+                match result {
+                    Ok(user_info) => {
+                        self.names.insert(uuid, user_info.name);
+                        true
+                    }
+                    Err(error) => {
+                        log::warn!("Cannot resolve the name of user {}: {}", uuid, error);
+                        self.unresolved.insert(uuid);
+                        false
+                    }
+                }
+            }
+            Msg::UpdateNewMemberName(name) => {
+                self.new_member_name = name;
+                self.error = None;
+                true
+            }
+            Msg::UpdateNewMemberRole(key) => {
+                self.new_member_role = Role::from_key(&key).unwrap_or(Role::Writer);
+                false
+            }
+            Msg::AddMember => {
+                let identifier = UserIdentifier::Name(self.new_member_name.trim().to_string());
+                let callback = self.link.callback(Msg::FoundUser);
+
+                match ApiClient::configured().find_user(&identifier, callback) {
+                    Ok(task) => self.lookup_task = Some(task),
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::FoundUser(result) => {
+                self.lookup_task = None;
+
+                match result {
+                    Ok(user_info) => {
+                        let uuid = user_info.uuid;
+
+                        if self.props.membership.role_of(uuid).is_some() {
+                            self.error = Some(format!("{} is a member already", user_info.name));
+                            return true;
+                        }
+
+                        self.names.insert(uuid, user_info.name);
+                        self.new_member_name = String::new();
+
+                        let role = self.new_member_role;
+                        self.edit(|membership| membership.set_role(uuid, role));
+                    }
+                    Err(ApiError::Status { code: 404, .. }) => {
+                        self.error =
+                            Some(format!("There is no user named {}", self.new_member_name));
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::ChangeRole(uuid, key) => {
+                if let Some(role) = Role::from_key(&key) {
+                    self.edit(|membership| membership.set_role(uuid, role));
+                }
+                false
+            }
+            Msg::Remove(uuid) => {
+                self.edit(|membership| membership.remove(uuid));
+                false
+            }
+            Msg::TransferOwnership(uuid) => {
+                let should_transfer = DialogService::confirm(&format!(
+                    "Make {} the owner of this inventory?\nYou will remain an admin.",
+                    self.name_of(uuid)
+                ));
+
+                if should_transfer {
+                    self.edit(|membership| membership.transfer_ownership(uuid));
+                }
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let membership_changed = self.props.membership != props.membership;
+        self.props = props;
+
+        if membership_changed {
+            self.resolve_names();
+        }
+        true
+    }
+
+    fn view(&self) -> Html {
+        let membership = &self.props.membership;
+        let my_role = self.current_user.and_then(|uuid| membership.role_of(uuid));
+
+        let may_edit = matches!(my_role, Some(Role::Owner) | Some(Role::Admin));
+        let may_transfer = my_role == Some(Role::Owner);
+
+        html! {
+            <div>
+                <h3>{ "Members" }</h3>
+
+                <p>
+                    <strong>{ Role::Owner.name() }{ ": " }</strong>
+                    { self.name_of(membership.owner) }
+                </p>
+
+                <ul>
+                    { for membership.members().into_iter().map(|(uuid, role)| {
+                        self.view_member(uuid, role, may_edit, may_transfer)
+                    }) }
+                </ul>
+
+                { if may_edit { self.view_add_member() } else { html! {} } }
+            </div>
+        }
+    }
+}
+
+impl Members {
+    fn view_member(&self, uuid: Uuid, role: Role, may_edit: bool, may_transfer: bool) -> Html {
+        let disabled = self.props.disabled || !may_edit;
+
+        html! {
+            <li>
+                { self.name_of(uuid) } { " " }
+
+                <select
+                    disabled=disabled
+                    onchange=self.link.callback(move |c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::ChangeRole(uuid, select.value()),
+                        _ => Msg::ChangeRole(uuid, String::new()),
+                    })
+                >
+                    { for Role::MEMBER_ROLES.iter().map(|option| html! {
+                        <option value=option.key() selected=*option == role>
+                            { option.name() }
+                        </option>
+                    }) }
+                </select> { " " }
+
+                { if may_edit { html! {
+                    <button
+                        onclick=self.link.callback(move |_| Msg::Remove(uuid))
+                        disabled=self.props.disabled
+                    >
+                        { "Remove" }
+                    </button>
+                } } else { html! {} } } { " " }
+
+                { if may_transfer { html! {
+                    <button
+                        onclick=self.link.callback(move |_| Msg::TransferOwnership(uuid))
+                        disabled=self.props.disabled
+                    >
+                        { "Make owner" }
+                    </button>
+                } } else { html! {} } }
+            </li>
+        }
+    }
+
+    fn view_add_member(&self) -> Html {
+        let is_busy = self.props.disabled || self.lookup_task.is_some();
+
+        html! {
+            <p>
+                // The name of the user to be added
+                <input
+                    type="text"
+                    placeholder="user name"
+                    disabled=is_busy
+                    value=self.new_member_name.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateNewMemberName(i.value))
+                /> { " " }
+
+                <select
+                    disabled=is_busy
+                    onchange=self.link.callback(|c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::UpdateNewMemberRole(select.value()),
+                        _ => Msg::UpdateNewMemberRole(String::new()),
+                    })
+                >
+                    { for Role::MEMBER_ROLES.iter().map(|role| html! {
+                        <option value=role.key() selected=*role == self.new_member_role>
+                            { role.name() }
+                        </option>
+                    }) }
+                </select> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::AddMember)
+                    disabled=is_busy || self.new_member_name.trim().is_empty()
+                >
+                    { "Add member" }
+                </button> { " " }
+
+                { match &self.error {
+                    Some(error) => html! { <span class="sfi-error">{ error }</span> },
+                    None => html! {},
+                } }
+            </p>
+        }
+    }
+
+    fn name_of(&self, uuid: Uuid) -> String {
+        self.names
+            .get(&uuid)
+            .cloned()
+            .unwrap_or_else(|| uuid.to_string())
+    }
+
+    /// Applies a change to the membership and hands the result to the parent
+    fn edit(&self, apply: impl FnOnce(&mut Membership)) {
+        let mut membership = self.props.membership.clone();
+        apply(&mut membership);
+        self.props.on_change.emit(membership);
+    }
+
+    /// Asks sfi-server for the names of members which aren't known or being resolved yet
+    fn resolve_names(&mut self) {
+        let membership = &self.props.membership;
+        let uuids = std::iter::once(membership.owner)
+            .chain(membership.members().into_iter().map(|(uuid, _)| uuid))
+            .filter(|uuid| {
+                !self.names.contains_key(uuid)
+                    && !self.name_tasks.contains_key(uuid)
+                    && !self.unresolved.contains(uuid)
+            })
+            .collect::<Vec<_>>();
+
+        for uuid in uuids {
+            let callback = self
+                .link
+                .callback(move |result| Msg::ResolvedName(uuid, result));
+
+            match ApiClient::configured().user(uuid, callback) {
+                Ok(task) => {
+                    self.name_tasks.insert(uuid, task);
+                }
+                Err(error) => {
+                    log::warn!("Cannot resolve the name of user {}: {}", uuid, error);
+                    self.unresolved.insert(uuid);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,21 @@
+pub mod accept_invitation;
+pub mod barcode_input;
+pub mod conflict_card;
+pub mod conflicts;
 pub mod create_inventory;
 pub mod create_item;
+pub mod create_unit;
+pub mod expiring;
 pub mod inventories;
 pub mod inventory_card;
+pub mod invitations;
 pub mod item_card;
 pub mod items;
+pub mod members;
+pub mod qr_code;
+pub mod search;
 pub mod unit_card;
 pub mod units;
 pub mod update_inventory;
 pub mod update_item;
+pub mod update_unit;
@@ -0,0 +1,79 @@
+use qrcode::{Color, QrCode as Code};
+use yew::prelude::*;
+
+/// The number of light modules around the code, which scanners need to find it
+const QUIET_ZONE: usize = 4;
+
+/// Renders text (e.g. a link) as a QR code
+pub struct QrCode {
+    props: Props,
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct Props {
+    pub data: String,
+
+    /// The width and height of the code in pixels
+    #[prop_or(200)]
+    pub size: u32,
+}
+
+impl Component for QrCode {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        let code = match Code::new(self.props.data.as_bytes()) {
+            Ok(code) => code,
+            Err(error) => {
+                log::error!("Cannot create a QR code: {}", error);
+                return html! { <span class="sfi-error">{ "Cannot create a QR code" }</span> };
+            }
+        };
+
+        let width = code.width();
+        let total = width + 2 * QUIET_ZONE;
+
+        // Draw every dark module as a 1x1 square of a single path
+        let path: String = code
+            .to_colors()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, color)| *color == Color::Dark)
+            .map(|(index, _)| {
+                let x = index % width + QUIET_ZONE;
+                let y = index / width + QUIET_ZONE;
+                format!("M{},{}h1v1h-1z", x, y)
+            })
+            .collect();
+
+        html! {
+            <svg
+                width=self.props.size.to_string()
+                height=self.props.size.to_string()
+                viewBox=format!("0 0 {} {}", total, total)
+                shape-rendering="crispEdges"
+            >
+                <rect width="100%" height="100%" fill="#fff" />
+                <path d=path fill="#000" />
+            </svg>
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::BTreeSet,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    components::{
+        app::{AppRoute, AppRouterButton},
+        login::AuthState,
+    },
+    services::{
+        auth::AuthAgent,
+        data::{
+            DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+            DataNotification, RequestId, Topic,
+        },
+    },
+    types::{SearchQuery, SearchResult},
+};
+use sfi_core::core::Inventory;
+use uuid::Uuid;
+use yew::prelude::*;
+
+/// Searches items and units across all inventories
+pub struct Search {
+    link: ComponentLink<Self>,
+    data_bridge: DataBridge,
+    _auth_bridge: Box<dyn Bridge<AuthAgent>>,
+
+    query: SearchQuery,
+    results: Option<Vec<SearchResult>>,
+
+    /// The latest search, whose results supersede those of earlier ones
+    latest_search: Option<RequestId>,
+
+    /// The inventories which can be picked as a filter
+    inventories: Vec<(Uuid, String)>,
+
+    /// The owners of these inventories
+    owners: BTreeSet<Uuid>,
+
+    /// The UUID of the logged-in user (used to label their own inventories)
+    user_uuid: Option<Uuid>,
+
+    error: Option<DataError>,
+}
+
+pub enum Msg {
+    AgentOutput(DataAgentOutput),
+    NewAuthState(Rc<AuthState>),
+    UpdateText(String),
+    UpdateInventory(String),
+    UpdateExpiresWithin(String),
+    UpdateOwner(String),
+}
+
+impl Component for Search {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
+        // Searching starts once the inventories are known
+        data_bridge.send(DataAgentRequest::GetInventories);
+        data_bridge.subscribe(Topic::Inventories);
+
+        Self {
+            _auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            link,
+            data_bridge,
+            query: SearchQuery::default(),
+            results: None,
+            latest_search: None,
+            inventories: vec![],
+            owners: BTreeSet::new(),
+            user_uuid: None,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::UpdateText(text) => {
+                self.query.text = text;
+                self.search();
+                false
+            }
+            Msg::UpdateInventory(uuid) => {
+                self.query.inventory = uuid.parse().ok();
+                self.search();
+                false
+            }
+            Msg::UpdateExpiresWithin(days) => {
+                self.query.expires_within = days.parse().ok().filter(|days| *days >= 0);
+                self.search();
+                false
+            }
+            Msg::UpdateOwner(uuid) => {
+                self.query.owner = uuid.parse().ok();
+                self.search();
+                false
+            }
+            Msg::NewAuthState(state) => {
+                self.user_uuid = state.user().map(|user| user.uuid);
+                true
+            }
+            Msg::AgentOutput(DataAgentOutput::Reply(id, res)) => match res {
+                // Skip the results of queries which have been superseded already
+                DataAgentResponse::SearchResults(_) if self.latest_search != Some(id) => false,
+                DataAgentResponse::SearchResults(results) => {
+                    self.results = Some(results);
+                    self.error = None;
+                    true
+                }
+                DataAgentResponse::Inventories(inventories) => {
+                    self.set_inventories(&inventories);
+                    self.search();
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::Inventories(inventories) => {
+                    self.set_inventories(&inventories);
+
+                    // The data has changed (e.g. by a synchronisation), search again
+                    self.search();
+                    true
+                }
+                DataNotification::CreatedInventory(_)
+                | DataNotification::UpdatedInventory(_)
+                | DataNotification::DeletedInventory(_) => {
+                    // Update the filters, which is followed by searching again
+                    self.data_bridge.send(DataAgentRequest::GetInventories);
+                    false
+                }
+                DataNotification::Conflicts(_) => false,
+                _ => {
+                    self.search();
+                    false
+                }
+            },
+        }
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <>
+
+            <h1>{ "Search" }</h1>
+
+            <AppRouterButton route=AppRoute::Home>{ "Go to home" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::Inventories>{ "Go to inventories" }</AppRouterButton>
+
+            <br /> <br />
+
+            // The search text
+            <input
+                type="search"
+                placeholder="name or EAN"
+                value={self.query.text.to_owned()}
+                oninput=self.link.callback(|i: InputData| Msg::UpdateText(i.value))
+            /> { " " }
+
+            // The inventory filter
+            <select onchange=self.link.callback(|c: ChangeData| match c {
+                ChangeData::Select(select) => Msg::UpdateInventory(select.value()),
+                _ => Msg::UpdateInventory(String::new()),
+            })>
+                <option value="" selected=self.query.inventory.is_none()>{ "All inventories" }</option>
+                { for self.inventories.iter().map(|(uuid, name)| html! {
+                    <option value=uuid.to_string() selected=self.query.inventory == Some(*uuid)>
+                        { name }
+                    </option>
+                }) }
+            </select> { " " }
+
+            // The owner filter
+            <select onchange=self.link.callback(|c: ChangeData| match c {
+                ChangeData::Select(select) => Msg::UpdateOwner(select.value()),
+                _ => Msg::UpdateOwner(String::new()),
+            })>
+                <option value="" selected=self.query.owner.is_none()>{ "Any owner" }</option>
+                { for self.owners.iter().map(|uuid| html! {
+                    <option value=uuid.to_string() selected=self.query.owner == Some(*uuid)>
+                        { self.describe_owner(*uuid) }
+                    </option>
+                }) }
+            </select> { " " }
+
+            // The expiry filter
+            <label>
+                { "Expiring within " }
+                <input
+                    type="number"
+                    min="0"
+                    placeholder="any"
+                    value={self.query.expires_within.map(|days| days.to_string()).unwrap_or_default()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateExpiresWithin(i.value))
+                />
+                { " days" }
+            </label>
+
+            <br /> <br />
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
+
+            <div class="sfi-cards-container">
+                { self.view_results() }
+            </div>
+
+            </>
+        }
+    }
+}
+
+impl Search {
+    fn search(&mut self) {
+        let id = self
+            .data_bridge
+            .send(DataAgentRequest::Search(self.query.clone()));
+
+        self.latest_search = Some(id);
+    }
+
+    /// Updates the options of the inventory and owner filters
+    fn set_inventories(&mut self, inventories: &[Arc<RwLock<Inventory>>]) {
+        let mut options: Vec<(Uuid, String)> = inventories
+            .iter()
+            .map(|inventory| {
+                let inventory = inventory.read().expect("Cannot read inventory");
+                (inventory.uuid, inventory.name.clone())
+            })
+            .collect();
+        options.sort_by(|a, b| a.1.cmp(&b.1));
+
+        self.owners = inventories
+            .iter()
+            .map(|inventory| inventory.read().expect("Cannot read inventory").owner)
+            .collect();
+        self.inventories = options;
+    }
+
+    fn describe_owner(&self, uuid: Uuid) -> String {
+        if self.user_uuid == Some(uuid) {
+            "Owned by me".to_string()
+        } else {
+            format!("Owned by {}", uuid)
+        }
+    }
+
+    fn view_results(&self) -> Html {
+        match &self.results {
+            None => html! { <p>{ "Searching..." }</p> },
+            Some(results) if results.is_empty() => html! { <p>{ "Nothing found" }</p> },
+            Some(results) => results
+                .iter()
+                .map(|result| self.view_result(result))
+                .collect(),
+        }
+    }
+
+    fn view_result(&self, result: &SearchResult) -> Html {
+        let update_item_route = AppRoute::UpdateItem(result.inventory_uuid, result.item.uuid);
+        let units_route = AppRoute::Units(result.inventory_uuid, result.item.uuid);
+
+        html! {
+            <div class="sfi-card">
+                <h3>{ &result.item.name }</h3>
+                <span class="sfi-subtitle">
+                    { &result.inventory_name }
+                    { result.item.ean.as_ref().map(|ean| format!(" · EAN {}", ean)).unwrap_or_default() }
+                </span>
+
+                <ul>
+                    { for result.units.iter().map(|(unit, details)| html! {
+                        <li class=details.urgency().css_class()>
+                            { &unit.name } { ": " } { details.describe_expiry() }
+                        </li>
+                    }) }
+                </ul>
+
+                <AppRouterButton route=units_route>{ "Open item" }</AppRouterButton> { " " }
+                <AppRouterButton route=update_item_route>{ "Edit" }</AppRouterButton>
+            </div>
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     components::app::{AppRoute, AppRouterButton},
     services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    types::{ConsumptionKind, UnitDetails},
 };
 use sfi_core::core::Unit;
 use uuid::Uuid;
@@ -10,17 +11,31 @@ use yew::{prelude::*, Bridge};
 pub struct UnitCard {
     link: ComponentLink<Self>,
     props: Props,
+
+    /// The amount entered for a partial consumption
+    amount: String,
 }
 
 pub enum Msg {
-    OpenItem,
-    EditItem,
+    UpdateAmount(String),
+    ConsumePart,
+    ConsumeAll,
+    Discard,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub unit: Unit,
     pub inventory_uuid: Uuid,
+    #[prop_or_default]
+    pub details: UnitDetails,
+    pub on_consume: Callback<(Uuid, Option<f64>, ConsumptionKind)>,
+    #[prop_or_default]
+    pub disabled: bool,
+
+    /// Hides the actions the current user isn't allowed to perform
+    #[prop_or_default]
+    pub read_only: bool,
 }
 
 impl Component for UnitCard {
@@ -28,21 +43,49 @@ impl Component for UnitCard {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self { props, link }
+        Self {
+            props,
+            link,
+            amount: String::new(),
+        }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let uuid = self.props.unit.uuid;
+
         match msg {
-            Msg::OpenItem => false,
-            Msg::EditItem => {
-                // TODO implement item edits
+            Msg::UpdateAmount(amount) => {
+                self.amount = amount;
                 true
             }
+            Msg::ConsumePart => {
+                if let Some(amount) = self.parsed_amount() {
+                    self.props
+                        .on_consume
+                        .emit((uuid, Some(amount), ConsumptionKind::Eaten));
+                    self.amount = String::new();
+                }
+                true
+            }
+            Msg::ConsumeAll => {
+                self.props
+                    .on_consume
+                    .emit((uuid, None, ConsumptionKind::Eaten));
+                false
+            }
+            Msg::Discard => {
+                self.props
+                    .on_consume
+                    .emit((uuid, None, ConsumptionKind::Discarded));
+                false
+            }
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // The unit may have been renamed, which isn't part of its details
+        self.props = props;
+        true
     }
 
     fn view(&self) -> Html {
@@ -52,13 +95,104 @@ impl Component for UnitCard {
             self.props.unit.uuid,
         );
 
+        let details = &self.props.details;
+        let expiry_class = if details.is_used_up() {
+            ""
+        } else {
+            details.urgency().css_class()
+        };
+
         html! {
-            <div class="sfi-card">
+            <div class=("sfi-card", expiry_class)>
                 <h3>{ self.props.unit.name.clone() }</h3>
                 <span class="sfi-subtitle">{ self.props.unit.uuid }</span>
 
-                <AppRouterButton route=update_unit_route>{ "Edit" }</AppRouterButton> { " " }
+                <p class="sfi-expiry">{ details.describe_expiry() }</p>
+
+                { self.view_quantity() }
+
+                { if self.props.read_only { html! {} } else { html! {
+                    <AppRouterButton route=update_unit_route>{ "Edit" }</AppRouterButton>
+                } } } { " " }
             </div>
         }
     }
 }
+
+impl UnitCard {
+    /// Returns the entered amount, if it's a positive number
+    fn parsed_amount(&self) -> Option<f64> {
+        self.amount
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| *amount > 0.0)
+    }
+
+    fn view_quantity(&self) -> Html {
+        let details = &self.props.details;
+        let measure = details.quantity.measure;
+
+        if details.is_used_up() {
+            return html! {
+                <p>
+                    { "Used up (" }
+                    { measure.describe(details.total(ConsumptionKind::Eaten)) } { " eaten, " }
+                    { measure.describe(details.total(ConsumptionKind::Discarded)) } { " discarded)" }
+                </p>
+            };
+        }
+
+        let remaining = html! {
+            <p>
+                { measure.describe(details.remaining()) } { " of " } { details.quantity } { " left" }
+            </p>
+        };
+
+        if self.props.read_only {
+            return remaining;
+        }
+
+        html! {
+            <>
+
+            { remaining }
+
+            // The amount to be consumed
+            <input
+                type="number"
+                min="0"
+                step="any"
+                placeholder=measure.name()
+                disabled=self.props.disabled
+                value={self.amount.to_owned()}
+                oninput=self.link.callback(|i: InputData| Msg::UpdateAmount(i.value))
+            /> { " " }
+
+            <button
+                onclick=self.link.callback(|_| Msg::ConsumePart)
+                disabled=self.props.disabled || self.parsed_amount().is_none()
+            >
+                { "Consume" }
+            </button> { " " }
+
+            <button
+                onclick=self.link.callback(|_| Msg::ConsumeAll)
+                disabled=self.props.disabled
+            >
+                { "Consume all" }
+            </button> { " " }
+
+            <button
+                onclick=self.link.callback(|_| Msg::Discard)
+                disabled=self.props.disabled
+            >
+                { "Discard" }
+            </button>
+
+            <br /> <br />
+
+            </>
+        }
+    }
+}
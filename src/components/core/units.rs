@@ -1,11 +1,18 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard},
+};
 
 use crate::{
     components::{
         app::{AppRoute, AppRouterButton},
         core::unit_card::UnitCard,
     },
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    services::data::{
+        DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError,
+        DataNotification, Topic,
+    },
+    types::{Action, ConsumptionKind, Role, UnitDetails},
 };
 use sfi_core::core::{Inventory, Item, Unit};
 use uuid::Uuid;
@@ -13,14 +20,22 @@ use yew::prelude::*;
 
 pub struct Units {
     link: ComponentLink<Self>,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     item: Option<Arc<RwLock<Item>>>,
+    details: HashMap<Uuid, UnitDetails>,
     props: Props,
+    is_busy: bool,
+
+    /// The role of the current user in the inventory of the item
+    role: Option<Role>,
+
+    error: Option<DataError>,
 }
 
 pub enum Msg {
-    AgentResponse(DataAgentResponse),
+    AgentOutput(DataAgentOutput),
     RequestNewState,
+    Consume(Uuid, Option<f64>, ConsumptionKind),
 }
 
 #[derive(Clone, Properties)]
@@ -34,17 +49,25 @@ impl Component for Units {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::AgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::AgentOutput));
         data_bridge.send(DataAgentRequest::GetItem(
             props.inventory_uuid,
             props.item_uuid,
         ));
+        data_bridge.send(DataAgentRequest::GetRoles);
+
+        // Keep the units up to date, e.g. after a synchronisation
+        data_bridge.subscribe(Topic::Inventory(props.inventory_uuid));
 
         Self {
             link,
             data_bridge,
             item: None,
+            details: HashMap::new(),
             props,
+            is_busy: false,
+            role: None,
+            error: None,
         }
     }
 
@@ -55,27 +78,119 @@ impl Component for Units {
                     self.props.inventory_uuid,
                     self.props.item_uuid,
                 ));
+                self.data_bridge.send(DataAgentRequest::GetRoles);
                 false
             }
-            Msg::AgentResponse(res) => match res {
+            Msg::Consume(unit_uuid, amount, kind) => {
+                let target = self.item.as_ref().and_then(|item| {
+                    item.read()
+                        .expect("Cannot read item")
+                        .units
+                        .iter()
+                        .find(|unit| unit.read().expect("Cannot read unit").uuid == unit_uuid)
+                        .cloned()
+                });
+
+                if let Some(target) = target {
+                    self.data_bridge.send(DataAgentRequest::ConsumeUnit {
+                        target,
+                        amount,
+                        kind,
+                    });
+
+                    self.error = None;
+                    self.is_busy = true;
+                }
+                true
+            }
+            Msg::AgentOutput(DataAgentOutput::Reply(_, res)) => match res {
                 DataAgentResponse::Item(item) => {
                     self.item = Some(item);
+                    self.error = None;
+
+                    // Fetch the expiry dates of the units
+                    self.data_bridge.send(DataAgentRequest::GetUnitDetails(
+                        self.props.inventory_uuid,
+                        self.props.item_uuid,
+                    ));
                     true
                 }
-                DataAgentResponse::InvalidInventoryUuid => {
+                DataAgentResponse::UnitDetails(details) => {
+                    self.details = details;
+                    true
+                }
+                DataAgentResponse::ConsumedUnit(unit_uuid, details) => {
+                    self.details.insert(unit_uuid, details);
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::Roles(roles) => {
+                    self.role = roles.get(&self.props.inventory_uuid).copied();
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    match error {
+                        DataError::UnknownInventory(_) | DataError::UnknownItem(_) => {
+                            self.item = None
+                        }
+                        _ => {}
+                    }
+
+                    self.error = Some(error);
+                    self.is_busy = false;
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::AgentOutput(DataAgentOutput::Notification(notification)) => match notification {
+                DataNotification::UpdatedInventory(_) => {
+                    // The item may have been replaced, fetch the new state
+                    self.link.send_message(Msg::RequestNewState);
+                    false
+                }
+                DataNotification::DeletedInventory(uuid) => {
                     self.item = None;
+                    self.error = Some(DataError::UnknownInventory(uuid));
                     true
                 }
+                DataNotification::UpdatedItem(item) => {
+                    if item.read().expect("Cannot read item").uuid != self.props.item_uuid {
+                        return false;
+                    }
 
-                // These responses should be ignored
-                DataAgentResponse::Inventories(_)
-                | DataAgentResponse::NewInventoryUuid(_)
-                | DataAgentResponse::Inventory(_)
-                | DataAgentResponse::UpdatedItem
-                | DataAgentResponse::DeletedInventory(_)
-                | DataAgentResponse::NewItemUuid(_)
-                | DataAgentResponse::DeletedItem(_)
-                | DataAgentResponse::UpdatedInventory(_) => false,
+                    self.item = Some(item);
+
+                    // Units of another item may have been merged into this one
+                    self.data_bridge.send(DataAgentRequest::GetUnitDetails(
+                        self.props.inventory_uuid,
+                        self.props.item_uuid,
+                    ));
+                    true
+                }
+                DataNotification::DeletedItem { item_uuid, .. }
+                    if item_uuid == self.props.item_uuid =>
+                {
+                    self.item = None;
+                    self.error = Some(DataError::UnknownItem(item_uuid));
+                    true
+                }
+                DataNotification::ChangedUnit(unit, details)
+                    if unit.item_uuid == self.props.item_uuid =>
+                {
+                    self.details.insert(unit.uuid, details);
+                    true
+                }
+                DataNotification::DeletedUnit {
+                    item_uuid,
+                    unit_uuid,
+                } if item_uuid == self.props.item_uuid => {
+                    self.details.remove(&unit_uuid);
+                    true
+                }
+                _ => false,
             },
         }
     }
@@ -88,7 +203,10 @@ impl Component for Units {
         let item = if let Some(item) = &self.item {
             item.read().expect("Cannot read item")
         } else {
-            return html! { <p>{ "Cannot find this item" }</p> };
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
         };
 
         html! {
@@ -96,15 +214,18 @@ impl Component for Units {
 
             <h1>{ "Units of " } {item.name.clone()}</h1>
 
-            <button onclick=self.link.callback(|_| Msg::RequestNewState)>
-                { "Refresh units" }
-            </button>  { " " }
-
             <AppRouterButton route=AppRoute::Home>{ "Go to home" }</AppRouterButton> { " " }
             <AppRouterButton route=AppRoute::Inventories>{ "Go to inventories" }</AppRouterButton> { " " }
 
-            // Create inventory
-            <AppRouterButton route=AppRoute::CreateUnit(self.props.inventory_uuid, self.props.item_uuid)>{ "New unit" }</AppRouterButton>
+            // Create unit
+            { if self.may_edit() { html! {
+                <AppRouterButton route=AppRoute::CreateUnit(self.props.inventory_uuid, self.props.item_uuid)>{ "New unit" }</AppRouterButton>
+            } } else { html! {} } }
+
+            { match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! {},
+            } }
 
             <br /> <br />
 
@@ -134,6 +255,22 @@ impl Units {
 
     fn view_unit(&self, unit: &Arc<RwLock<Unit>>) -> Html {
         let unit = unit.read().expect("Cannot read unit").to_owned();
-        html! { <UnitCard inventory_uuid=self.props.inventory_uuid unit=unit /> }
+        let details = self.details.get(&unit.uuid).cloned().unwrap_or_default();
+
+        html! {
+            <UnitCard
+                inventory_uuid=self.props.inventory_uuid
+                unit=unit
+                details=details
+                disabled=self.is_busy
+                read_only=!self.may_edit()
+                on_consume=self.link.callback(|(uuid, amount, kind)| Msg::Consume(uuid, amount, kind))
+            />
+        }
+    }
+
+    fn may_edit(&self) -> bool {
+        self.role
+            .map_or(false, |role| role.allows(Action::EditContents))
     }
 }
@@ -6,24 +6,33 @@ use yew::{prelude::*, services::DialogService};
 use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
 
 use crate::{
-    components::app::AppRoute,
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    components::{
+        app::AppRoute,
+        core::{invitations::Invitations, members::Members},
+    },
+    services::data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
+    types::{Action, Membership, Role},
 };
 
 pub struct UpdateInventory {
     link: ComponentLink<Self>,
     inventory: Option<Arc<RwLock<Inventory>>>,
     old_name: String,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     route_dispatcher: RouteAgentDispatcher,
     is_busy: bool,
+    error: Option<DataError>,
 
     form_data: FormData,
+
+    /// The role of the current user in this inventory
+    role: Option<Role>,
 }
 
 pub enum Msg {
     UpdateName(String),
-    DataAgentResponse(DataAgentResponse),
+    UpdateMembership(Membership),
+    DataAgentOutput(DataAgentOutput),
     Confirm,
     Cancel,
     Delete,
@@ -41,14 +50,17 @@ impl Component for UpdateInventory {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let inventory_uuid = props.inventory_uuid;
 
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::DataAgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::DataAgentOutput));
         data_bridge.send(DataAgentRequest::GetInventory(inventory_uuid));
+        data_bridge.send(DataAgentRequest::GetRoles);
 
         Self {
             data_bridge,
             route_dispatcher: RouteAgentDispatcher::new(),
             form_data: FormData::default(),
+            role: None,
             is_busy: false,
+            error: None,
             link,
             inventory: None,
             old_name: String::default(),
@@ -61,6 +73,13 @@ impl Component for UpdateInventory {
                 self.form_data.name = name;
                 true
             }
+            Msg::UpdateMembership(membership) => {
+                self.form_data.owner = membership.owner;
+                self.form_data.admins = membership.admins;
+                self.form_data.writables = membership.writables;
+                self.form_data.readables = membership.readables;
+                true
+            }
             Msg::Confirm => {
                 // Give the new card to the listing component
                 self.data_bridge.send(DataAgentRequest::UpdateInventory {
@@ -72,6 +91,7 @@ impl Component for UpdateInventory {
                     readables: self.form_data.readables.clone(),
                 });
 
+                self.error = None;
                 self.is_busy = true;
                 true
             }
@@ -97,7 +117,7 @@ impl Component for UpdateInventory {
 
                 should_kaboom
             }
-            Msg::DataAgentResponse(res) => match res {
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, res)) => match res {
                 DataAgentResponse::Inventory(inventory) => {
                     {
                         let inventory = inventory.read().expect("Cannot read inventory");
@@ -114,6 +134,19 @@ impl Component for UpdateInventory {
                     self.inventory = Some(inventory);
                     true
                 }
+                DataAgentResponse::Roles(roles) => {
+                    self.role = self.inventory.as_ref().and_then(|inventory| {
+                        let uuid = inventory.read().expect("Cannot read inventory").uuid;
+                        roles.get(&uuid).copied()
+                    });
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
                 DataAgentResponse::UpdatedInventory(_) => {
                     // Navigate back to the inventories
                     self.route_dispatcher
@@ -130,15 +163,10 @@ impl Component for UpdateInventory {
                     true
                 }
 
-                // These responses should be ignored
-                DataAgentResponse::Inventories(_)
-                | DataAgentResponse::NewInventoryUuid(_)
-                | DataAgentResponse::InvalidInventoryUuid
-                | DataAgentResponse::UpdatedItem
-                | DataAgentResponse::Item(_)
-                | DataAgentResponse::DeletedItem(_)
-                | DataAgentResponse::NewItemUuid(_) => false,
+                // This component doesn't send any other requests
+                _ => false,
             },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
         }
     }
 
@@ -150,9 +178,14 @@ impl Component for UpdateInventory {
         let inventory = if let Some(inventory) = &self.inventory {
             inventory.read().expect("Cannot read inventory")
         } else {
-            return html! { <p>{ "Cannot find this inventory" }</p> };
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
         };
 
+        let allows = |action| self.role.map_or(false, |role: Role| role.allows(action));
+
         html! {
             <div>
                 // A heading
@@ -162,7 +195,7 @@ impl Component for UpdateInventory {
                 <input
                     type="text"
                     placeholder="name"
-                    disabled=self.is_busy
+                    disabled=self.is_busy || !allows(Action::EditInventory)
                     value={self.form_data.name.to_owned()}
                     oninput=self.link.callback(|i: InputData| Msg::UpdateName(i.value))
                 /> { " " }
@@ -170,7 +203,7 @@ impl Component for UpdateInventory {
                 // Save edits button
                 <button
                     onclick=self.link.callback(|_| Msg::Confirm)
-                    disabled=self.is_busy
+                    disabled=self.is_busy || !allows(Action::EditInventory)
                 >
                     { "Save" }
                 </button>  { " " }
@@ -186,12 +219,27 @@ impl Component for UpdateInventory {
                 // Delete button
                 <button
                     onclick=self.link.callback(|_| Msg::Delete)
-                    disabled=self.is_busy
+                    disabled=self.is_busy || !allows(Action::DeleteInventory)
                 >
                     { "Delete" }
                 </button>
 
-                // TODO implement edit options for owner,
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
+                // Sharing with other users
+                <Members
+                    membership=self.form_data.membership()
+                    on_change=self.link.callback(Msg::UpdateMembership)
+                    disabled=self.is_busy
+                />
+
+                // Inviting others using links
+                { if allows(Action::ManageMembers) { html! {
+                    <Invitations inventory_uuid=inventory.uuid />
+                } } else { html! {} } }
 
             </div>
         }
@@ -206,3 +254,14 @@ struct FormData {
     writables: Vec<Uuid>,
     readables: Vec<Uuid>,
 }
+
+impl FormData {
+    fn membership(&self) -> Membership {
+        Membership {
+            owner: self.owner,
+            admins: self.admins.clone(),
+            writables: self.writables.clone(),
+            readables: self.readables.clone(),
+        }
+    }
+}
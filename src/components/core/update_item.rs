@@ -1,13 +1,16 @@
 use std::sync::{Arc, RwLock};
 
-use sfi_core::core::{Inventory, Item};
+use sfi_core::core::Item;
 use uuid::Uuid;
 use yew::{prelude::*, services::DialogService};
 use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
 
 use crate::{
-    components::app::AppRoute,
-    services::data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    components::{app::AppRoute, core::barcode_input::BarcodeInput},
+    services::{
+        data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
+        ean::{self, EanError},
+    },
 };
 
 pub struct UpdateItem {
@@ -15,20 +18,28 @@ pub struct UpdateItem {
     props: Props,
     item: Option<Arc<RwLock<Item>>>,
     old_name: String,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     route_dispatcher: RouteAgentDispatcher,
     is_busy: bool,
+    error: Option<DataError>,
 
     form_data: FormData,
+    ean_error: Option<EanError>,
+
+    /// The other items of the inventory, which this item may be merged into
+    other_items: Vec<Arc<RwLock<Item>>>,
+    merge_target: Option<Uuid>,
 }
 
 pub enum Msg {
     UpdateName(String),
     UpdateEan(String),
-    DataAgentResponse(DataAgentResponse),
+    DataAgentOutput(DataAgentOutput),
     Confirm,
     Cancel,
     Delete,
+    SelectMergeTarget(String),
+    Merge,
 }
 
 #[derive(Clone, Properties)]
@@ -45,14 +56,19 @@ impl Component for UpdateItem {
         let inventory_uuid = props.inventory_uuid;
         let item_uuid = props.item_uuid;
 
-        let mut data_bridge = DataAgent::bridge(link.callback(Msg::DataAgentResponse));
+        let mut data_bridge = DataBridge::new(link.callback(Msg::DataAgentOutput));
         data_bridge.send(DataAgentRequest::GetItem(inventory_uuid, item_uuid));
+        data_bridge.send(DataAgentRequest::GetInventory(inventory_uuid));
 
         Self {
             data_bridge,
             route_dispatcher: RouteAgentDispatcher::new(),
             form_data: FormData::default(),
+            ean_error: None,
+            other_items: vec![],
+            merge_target: None,
             is_busy: false,
+            error: None,
             link,
             item: None,
             old_name: String::default(),
@@ -68,16 +84,26 @@ impl Component for UpdateItem {
             }
             Msg::UpdateEan(ean) => {
                 self.form_data.ean = if ean.is_empty() { None } else { Some(ean) };
+                self.ean_error = ean::normalize_optional(self.form_data.ean.as_deref()).err();
                 true
             }
             Msg::Confirm => {
+                let ean = match ean::normalize_optional(self.form_data.ean.as_deref()) {
+                    Ok(ean) => ean,
+                    Err(error) => {
+                        self.ean_error = Some(error);
+                        return true;
+                    }
+                };
+
                 // Give the new card to the listing component
                 self.data_bridge.send(DataAgentRequest::UpdateItem {
                     target: self.item.clone().expect("Cannot be none"),
                     name: self.form_data.name.clone(),
-                    ean: self.form_data.ean.clone(),
+                    ean,
                 });
 
+                self.error = None;
                 self.is_busy = true;
                 true
             }
@@ -103,7 +129,43 @@ impl Component for UpdateItem {
 
                 should_kaboom
             }
-            Msg::DataAgentResponse(res) => match res {
+            Msg::SelectMergeTarget(uuid) => {
+                self.merge_target = Uuid::parse_str(&uuid).ok();
+                true
+            }
+            Msg::Merge => {
+                let target = self.merge_target.and_then(|uuid| {
+                    self.other_items
+                        .iter()
+                        .find(|item| item.read().expect("Cannot read item").uuid == uuid)
+                        .cloned()
+                });
+
+                let target = match target {
+                    Some(target) => target,
+                    None => return false,
+                };
+
+                let should_merge = DialogService::confirm(&format!(
+                    "Move every unit of \"{}\" to \"{}\" and delete \"{}\"?\nThis operation cannot be undone.",
+                    self.old_name,
+                    target.read().expect("Cannot read item").name,
+                    self.old_name
+                ));
+
+                if should_merge {
+                    self.data_bridge.send(DataAgentRequest::MergeItems {
+                        source: self.item.clone().expect("Must be Some"),
+                        target,
+                    });
+
+                    self.error = None;
+                    self.is_busy = true;
+                }
+
+                should_merge
+            }
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, res)) => match res {
                 DataAgentResponse::Item(item) => {
                     {
                         let item = item.read().expect("Cannot read item");
@@ -113,10 +175,47 @@ impl Component for UpdateItem {
                             name: item.name.clone(),
                             ean: item.ean.clone(),
                         };
+
+                        // Items from before EANs were validated may need fixing
+                        self.ean_error = ean::normalize_optional(item.ean.as_deref()).err();
                     }
                     self.item = Some(item);
                     true
                 }
+                DataAgentResponse::Inventory(inventory) => {
+                    self.other_items = inventory
+                        .read()
+                        .expect("Cannot read inventory")
+                        .items
+                        .iter()
+                        .filter(|item| {
+                            item.read().expect("Cannot read item").uuid != self.props.item_uuid
+                        })
+                        .cloned()
+                        .collect();
+                    true
+                }
+                DataAgentResponse::MergedItems(target_uuid) => {
+                    // Show the units in their new place
+                    self.route_dispatcher.send(RouteRequest::ChangeRoute(
+                        AppRoute::Units(self.props.inventory_uuid, target_uuid).into(),
+                    ));
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::InvalidEan(error) => {
+                    self.ean_error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
                 DataAgentResponse::UpdatedItem => {
                     // Navigate back to the inventories
                     self.route_dispatcher.send(RouteRequest::ChangeRoute(
@@ -143,15 +242,10 @@ impl Component for UpdateItem {
                     true
                 }
 
-                // These responses should be ignored
-                DataAgentResponse::Inventories(_)
-                | DataAgentResponse::NewInventoryUuid(_)
-                | DataAgentResponse::InvalidInventoryUuid
-                | DataAgentResponse::UpdatedInventory(_)
-                | DataAgentResponse::Inventory(_)
-                | DataAgentResponse::DeletedInventory(_)
-                | DataAgentResponse::NewItemUuid(_) => false,
+                // This component doesn't send any other requests
+                _ => false,
             },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
         }
     }
 
@@ -163,7 +257,10 @@ impl Component for UpdateItem {
         let item = if let Some(item) = &self.item {
             item.read().expect("Cannot read item")
         } else {
-            return html! { <p>{ "Cannot find this item" }</p> };
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
         };
 
         html! {
@@ -189,10 +286,21 @@ impl Component for UpdateItem {
                     oninput=self.link.callback(|i: InputData| Msg::UpdateEan(i.value))
                 /> { " " }
 
+                // Explain what's wrong with the EAN
+                { self.view_ean_error() }
+
+                // Read the EAN from a photo of the barcode
+                <BarcodeInput
+                    disabled=self.is_busy
+                    on_detect=self.link.callback(Msg::UpdateEan)
+                />
+
+                <br /> <br />
+
                 // Save edits button
                 <button
                     onclick=self.link.callback(|_| Msg::Confirm)
-                    disabled=self.is_busy
+                    disabled=self.is_busy || self.ean_error.is_some()
                 >
                     { "Save" }
                 </button>  { " " }
@@ -213,6 +321,13 @@ impl Component for UpdateItem {
                     { "Delete" }
                 </button>
 
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
+                { self.view_merge() }
+
                 // TODO implement edit options for owner,
 
             </div>
@@ -220,6 +335,59 @@ impl Component for UpdateItem {
     }
 }
 
+impl UpdateItem {
+    fn view_merge(&self) -> Html {
+        if self.other_items.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <>
+                <h3>{ "Merge into another item" }</h3>
+
+                <p>{ "Moves every unit of this item to the selected item, then deletes this item." }</p>
+
+                <select
+                    disabled=self.is_busy
+                    onchange=self.link.callback(|c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::SelectMergeTarget(select.value()),
+                        _ => Msg::SelectMergeTarget(String::new()),
+                    })
+                >
+                    <option value="" selected=self.merge_target.is_none()>{ "Choose an item" }</option>
+                    { for self.other_items.iter().map(|item| {
+                        let item = item.read().expect("Cannot read item");
+
+                        html! {
+                            <option
+                                value=item.uuid.to_string()
+                                selected=self.merge_target == Some(item.uuid)
+                            >
+                                { &item.name }
+                                { item.ean.as_ref().map(|ean| format!(" ({})", ean)).unwrap_or_default() }
+                            </option>
+                        }
+                    }) }
+                </select> { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::Merge)
+                    disabled=self.is_busy || self.merge_target.is_none()
+                >
+                    { "Merge" }
+                </button>
+            </>
+        }
+    }
+
+    fn view_ean_error(&self) -> Html {
+        match &self.ean_error {
+            Some(error) => html! { <span class="sfi-error">{ error }</span> },
+            None => html! {},
+        }
+    }
+}
+
 #[derive(Default)]
 struct FormData {
     name: String,
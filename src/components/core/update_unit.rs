@@ -0,0 +1,325 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::{Local, NaiveDate};
+use sfi_core::core::Unit;
+use uuid::Uuid;
+use yew::{prelude::*, services::DialogService};
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::app::AppRoute,
+    services::data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, DataError},
+    types::{ConsumptionKind, Measure, Quantity, UnitDetails},
+};
+
+pub struct UpdateUnit {
+    link: ComponentLink<Self>,
+    props: Props,
+    unit: Option<Arc<RwLock<Unit>>>,
+    old_name: String,
+    details: UnitDetails,
+    data_bridge: DataBridge,
+    route_dispatcher: RouteAgentDispatcher,
+    is_busy: bool,
+    error: Option<DataError>,
+
+    form_data: FormData,
+}
+
+pub enum Msg {
+    UpdateName(String),
+    UpdateExpires(String),
+    UpdateAmount(String),
+    UpdateMeasure(String),
+    DataAgentOutput(DataAgentOutput),
+    Confirm,
+    Cancel,
+    Delete,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub inventory_uuid: Uuid,
+    pub item_uuid: Uuid,
+    pub unit_uuid: Uuid,
+}
+
+impl Component for UpdateUnit {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut data_bridge = DataBridge::new(link.callback(Msg::DataAgentOutput));
+        data_bridge.send(DataAgentRequest::GetUnit(
+            props.inventory_uuid,
+            props.item_uuid,
+            props.unit_uuid,
+        ));
+
+        Self {
+            data_bridge,
+            route_dispatcher: RouteAgentDispatcher::new(),
+            form_data: FormData::default(),
+            is_busy: false,
+            error: None,
+            link,
+            unit: None,
+            old_name: String::default(),
+            details: UnitDetails::default(),
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::UpdateName(name) => {
+                self.form_data.name = name;
+                true
+            }
+            Msg::UpdateExpires(expires) => {
+                self.form_data.expires = NaiveDate::parse_from_str(&expires, "%Y-%m-%d").ok();
+                true
+            }
+            Msg::UpdateAmount(amount) => {
+                self.form_data.amount = amount;
+                true
+            }
+            Msg::UpdateMeasure(measure) => {
+                if let Some(measure) = Measure::from_key(&measure) {
+                    self.form_data.measure = measure;
+                }
+                true
+            }
+            Msg::Confirm => {
+                self.data_bridge.send(DataAgentRequest::UpdateUnit {
+                    target: self.unit.clone().expect("Cannot be none"),
+                    name: self.form_data.name.clone(),
+                    details: UnitDetails {
+                        expires: self.form_data.expires,
+                        quantity: Quantity {
+                            amount: self.form_data.parsed_amount().unwrap_or(1.0),
+                            measure: self.form_data.measure,
+                        },
+                        // Keep the consumption history
+                        history: self.details.history.clone(),
+                    },
+                });
+
+                self.error = None;
+                self.is_busy = true;
+                true
+            }
+            Msg::Cancel => {
+                // Cancel the update of the unit
+                self.navigate_to_units();
+
+                self.is_busy = true;
+                true
+            }
+            Msg::Delete => {
+                let should_kaboom = DialogService::confirm(&format!(
+                    "Delete unit \"{}\"?\nThis operation cannot be undone.",
+                    self.old_name
+                ));
+
+                if should_kaboom {
+                    self.data_bridge.send(DataAgentRequest::DeleteUnit(
+                        self.unit.clone().expect("Must be Some"),
+                    ))
+                }
+
+                should_kaboom
+            }
+            Msg::DataAgentOutput(DataAgentOutput::Reply(_, res)) => match res {
+                DataAgentResponse::Unit(unit, details) => {
+                    {
+                        let unit = unit.read().expect("Cannot read unit");
+                        self.old_name = unit.name.clone();
+
+                        self.form_data = FormData {
+                            name: unit.name.clone(),
+                            expires: details.expires,
+                            amount: details.quantity.amount.to_string(),
+                            measure: details.quantity.measure,
+                        };
+                    }
+                    self.details = details;
+                    self.unit = Some(unit);
+                    true
+                }
+                DataAgentResponse::UpdatedUnit | DataAgentResponse::DeletedUnit(_) => {
+                    self.navigate_to_units();
+
+                    self.is_busy = false;
+                    true
+                }
+                DataAgentResponse::Error(error) => {
+                    self.error = Some(error);
+
+                    self.is_busy = false;
+                    true
+                }
+
+                // This component doesn't send any other requests
+                _ => false,
+            },
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => false,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let unit = if let Some(unit) = &self.unit {
+            unit.read().expect("Cannot read unit")
+        } else {
+            return match &self.error {
+                Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                None => html! { <p>{ "Loading..." }</p> },
+            };
+        };
+
+        html! {
+            <div>
+                // A heading
+                <h2>{ "Edit unit " } {unit.name.clone()}</h2>
+
+                // The name input
+                <input
+                    type="text"
+                    placeholder="name"
+                    disabled=self.is_busy
+                    value={self.form_data.name.to_owned()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateName(i.value))
+                /> { " " }
+
+                // The expiry date input
+                <label>
+                    { "Expires " }
+                    <input
+                        type="date"
+                        disabled=self.is_busy
+                        value={self.form_data.expires.map(|date| date.to_string()).unwrap_or_default()}
+                        oninput=self.link.callback(|i: InputData| Msg::UpdateExpires(i.value))
+                    />
+                </label> { " " }
+
+                // The quantity inputs
+                <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    placeholder="quantity"
+                    disabled=self.is_busy
+                    value={self.form_data.amount.to_owned()}
+                    oninput=self.link.callback(|i: InputData| Msg::UpdateAmount(i.value))
+                /> { " " }
+
+                <select
+                    disabled=self.is_busy
+                    onchange=self.link.callback(|c: ChangeData| match c {
+                        ChangeData::Select(select) => Msg::UpdateMeasure(select.value()),
+                        _ => Msg::UpdateMeasure(String::new()),
+                    })
+                >
+                    { for Measure::ALL.iter().map(|measure| html! {
+                        <option value=measure.key() selected=*measure == self.form_data.measure>
+                            { measure.name() }
+                        </option>
+                    }) }
+                </select> { " " }
+
+                // Save edits button
+                <button
+                    onclick=self.link.callback(|_| Msg::Confirm)
+                    disabled=self.is_busy || self.form_data.parsed_amount().is_none()
+                >
+                    { "Save" }
+                </button>  { " " }
+
+                // Cancel button
+                <button
+                    onclick=self.link.callback(|_| Msg::Cancel)
+                    disabled=self.is_busy
+                >
+                    { "Cancel" }
+                </button>  { " " }
+
+                // Delete button
+                <button
+                    onclick=self.link.callback(|_| Msg::Delete)
+                    disabled=self.is_busy
+                >
+                    { "Delete" }
+                </button>
+
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+
+                { self.view_history() }
+
+            </div>
+        }
+    }
+}
+
+impl UpdateUnit {
+    fn view_history(&self) -> Html {
+        if self.details.history.is_empty() {
+            return html! {};
+        }
+
+        let measure = self.details.quantity.measure;
+
+        html! {
+            <>
+
+            <h3>{ "History" }</h3>
+
+            <ul>
+                { for self.details.history.iter().map(|entry| html! {
+                    <li>
+                        { entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string() }
+                        { ": " }
+                        { measure.describe(entry.amount) }
+                        { match entry.kind {
+                            ConsumptionKind::Eaten => " eaten",
+                            ConsumptionKind::Discarded => " discarded",
+                        } }
+                    </li>
+                }) }
+            </ul>
+
+            </>
+        }
+    }
+
+    fn navigate_to_units(&mut self) {
+        self.route_dispatcher.send(RouteRequest::ChangeRoute(
+            AppRoute::Units(self.props.inventory_uuid, self.props.item_uuid).into(),
+        ));
+    }
+}
+
+#[derive(Default)]
+struct FormData {
+    name: String,
+    expires: Option<NaiveDate>,
+    amount: String,
+    measure: Measure,
+}
+
+impl FormData {
+    /// Returns the entered quantity, if it's a positive number
+    fn parsed_amount(&self) -> Option<f64> {
+        self.amount
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| *amount > 0.0)
+    }
+}
@@ -4,22 +4,24 @@ use yew::prelude::*;
 
 use crate::services::{
     auth::{AuthAgent, AuthAgentRequest},
-    data::{DataAgent, DataAgentRequest, DataAgentResponse},
+    data::{DataAgentOutput, DataAgentRequest, DataAgentResponse, DataBridge, RequestId},
 };
 
 use super::login::AuthState;
 
 pub struct DebugTools {
     link: ComponentLink<Self>,
-    data_bridge: Box<dyn Bridge<DataAgent>>,
+    data_bridge: DataBridge,
     auth_bridge: Box<dyn Bridge<AuthAgent>>,
-    inspect_next: bool,
+
+    /// The request for the inventories to be logged
+    inspection: Option<RequestId>,
 }
 
 pub enum Msg {
     DeleteAllData,
     ProbeAuth,
-    DataAgentResponse(DataAgentResponse),
+    DataAgentOutput(DataAgentOutput),
     AuthAgentResponse(Rc<AuthState>),
     MakeDebugInventory,
     InspectInventories,
@@ -31,21 +33,23 @@ impl Component for DebugTools {
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
         Self {
-            data_bridge: DataAgent::bridge(link.callback(Msg::DataAgentResponse)),
-            auth_bridge: AuthAgent::bridge(link.callback(Msg::AuthAgentResponse)),
-            inspect_next: false,
+            data_bridge: DataBridge::new(link.callback(Msg::DataAgentOutput)),
+            auth_bridge: AuthAgent::subscribe(link.callback(Msg::AuthAgentResponse)),
+            inspection: None,
             link,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::DeleteAllData => self.data_bridge.send(DataAgentRequest::DeleteAllData),
+            Msg::DeleteAllData => {
+                self.data_bridge.send(DataAgentRequest::DeleteAllData);
+            }
             Msg::ProbeAuth => self.auth_bridge.send(AuthAgentRequest::GetAuthStatus),
-            Msg::DataAgentResponse(response) => {
-                if self.inspect_next {
+            Msg::DataAgentOutput(DataAgentOutput::Reply(id, response)) => {
+                if self.inspection == Some(id) {
                     if let DataAgentResponse::Inventories(inventories) = response {
-                        self.inspect_next = false;
+                        self.inspection = None;
 
                         if inventories.is_empty() {
                             log::debug!("No inventories present");
@@ -57,6 +61,7 @@ impl Component for DebugTools {
                     }
                 }
             }
+            Msg::DataAgentOutput(DataAgentOutput::Notification(_)) => {}
             Msg::AuthAgentResponse(_auth_state) => {
                 // log::debug!("Received response is {:?}", &_auth_state);
             }
@@ -64,8 +69,7 @@ impl Component for DebugTools {
                 self.data_bridge.send(DataAgentRequest::MakeDebugInventory);
             }
             Msg::InspectInventories => {
-                self.inspect_next = true;
-                self.data_bridge.send(DataAgentRequest::GetInventories)
+                self.inspection = Some(self.data_bridge.send(DataAgentRequest::GetInventories));
             }
         }
 
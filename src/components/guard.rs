@@ -0,0 +1,75 @@
+use std::rc::Rc;
+
+use yew::prelude::*;
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::{app::AppRoute, login::AuthState},
+    services::auth::{self, AuthAgent},
+};
+
+/// Only renders its children for logged in users, and sends everyone else to the login page
+pub struct Guard {
+    props: Props,
+
+    /// The state of the auth agent, once it has responded
+    state: Option<Rc<AuthState>>,
+    _auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    route_dispatcher: RouteAgentDispatcher,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    #[prop_or_default]
+    pub children: Children,
+}
+
+impl Component for Guard {
+    type Message = Rc<AuthState>;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            state: None,
+            _auth_bridge: AuthAgent::subscribe(link.callback(|state| state)),
+            route_dispatcher: RouteAgentDispatcher::new(),
+        }
+    }
+
+    fn update(&mut self, state: Self::Message) -> ShouldRender {
+        if Self::is_denied(&state) {
+            // Come back here after logging in
+            auth::remember_current_route();
+
+            self.route_dispatcher
+                .send(RouteRequest::ChangeRoute(AppRoute::Login.into()));
+        }
+
+        self.state = Some(state);
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        match &self.state {
+            Some(state) if state.user().is_some() => html! { <>{ self.props.children.clone() }</> },
+            Some(state) if Self::is_denied(state) => html! {},
+            _ => html! { <p>{ "Loading..." }</p> },
+        }
+    }
+}
+
+impl Guard {
+    /// Whether the user is known to be logged out (as opposed to the state still being probed)
+    fn is_denied(state: &AuthState) -> bool {
+        match state {
+            AuthState::Probing(_) | AuthState::LoggingIn(_) => false,
+            state => state.user().is_none(),
+        }
+    }
+}
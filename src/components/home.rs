@@ -1,5 +1,13 @@
-use super::app::{AppRoute, AppRouterButton};
-use crate::constants::{self, css::BOXED};
+use super::{
+    app::{AppRoute, AppRouterButton},
+    core::expiring::Expiring,
+    login::AuthState,
+};
+use crate::{
+    constants::{self, css::BOXED},
+    services::auth::AuthAgent,
+};
+use std::rc::Rc;
 use yew::prelude::*;
 // use yewtil::{Pure, PureComponent};
 
@@ -9,20 +17,27 @@ use yew::prelude::*;
 // pub type Home = Pure<PureHome>;
 pub type Home = PureHome;
 
-pub struct PureHome;
+pub struct PureHome {
+    auth_state: Rc<AuthState>,
+    _auth_bridge: Box<dyn Bridge<AuthAgent>>,
+}
 
 // impl PureComponent for Home {
 
 impl Component for Home {
-    type Message = ();
+    type Message = Rc<AuthState>;
     type Properties = ();
 
-    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
-        Self
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            auth_state: Rc::new(AuthState::Initial),
+            _auth_bridge: AuthAgent::subscribe(link.callback(|state| state)),
+        }
     }
 
-    fn update(&mut self, _: Self::Message) -> ShouldRender {
-        false
+    fn update(&mut self, auth_state: Self::Message) -> ShouldRender {
+        self.auth_state = auth_state;
+        true
     }
 
     fn change(&mut self, _: Self::Properties) -> ShouldRender {
@@ -30,6 +45,11 @@ impl Component for Home {
     }
 
     fn view(&self) -> Html {
+        // Logged-in users land on the units expiring soon
+        if self.auth_state.user().is_some() {
+            return html! { <Expiring /> };
+        }
+
         html! {
             <>
 
@@ -45,7 +65,8 @@ impl Component for Home {
                 { "To navigate to the inventories page, press the button below:"}
             </p>
 
-            <AppRouterButton route=AppRoute::Inventories>{ "Inventories" }</AppRouterButton>
+            <AppRouterButton route=AppRoute::Inventories>{ "Inventories" }</AppRouterButton> { " " }
+            <AppRouterButton route=AppRoute::Settings>{ "Settings" }</AppRouterButton>
 
             <div style=BOXED>
                 <h3>{constants::license::license_notice_title()}</h3>
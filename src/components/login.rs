@@ -1,39 +1,38 @@
-use crate::services::auth::{AuthAgent, AuthAgentRequest};
-use sfi_core::core::users::{UserIdentifier, UserInfo, UserLogin, UserSignup};
+use crate::{
+    components::app::{AppAnchor, AppRoute},
+    services::auth::{self, AuthAgent, AuthAgentRequest},
+};
+use sfi_core::core::users::{UserIdentifier, UserInfo, UserLogin};
 use std::rc::Rc;
 use yew::{prelude::*, services::fetch::FetchTask};
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
 
-/// The root component of sfi-web
+/// The login page, which asks for a TOTP code as well if needed
 pub struct LoginComponent {
     link: ComponentLink<Self>,
     state: Rc<AuthState>,
     auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    route_dispatcher: RouteAgentDispatcher,
     form: LoginForm,
 }
 
+#[derive(Default)]
 struct LoginForm {
     password: String,
     name: String,
-}
-
-impl LoginForm {
-    fn new() -> Self {
-        Self {
-            password: String::new(),
-            name: String::new(),
-        }
-    }
+    totp: String,
 }
 
 pub enum Msg {
     StartLogin,
-    StartSignup,
-    StartLogout,
+    SubmitTotp,
+    CancelLogin,
 
     NewAuthState(Rc<AuthState>),
 
     ChangePassword(String),
     ChangeName(String),
+    ChangeTotp(String),
 }
 
 #[derive(Debug)]
@@ -48,26 +47,73 @@ pub enum AuthState {
     LoggingIn(FetchTask),
     LoggedIn(UserInfo),
 
+    /// sfi-server cannot be reached, so the user last logged in on this device is assumed
+    Offline(UserInfo),
+
+    /// The password was correct, but sfi-server needs a TOTP code as well
+    ///
+    /// The login itself stays with the auth agent, so the password isn't shared with subscribers.
+    AwaitingTotp {
+        /// Whether the previous code was wrong
+        rejected: bool,
+    },
+
+    // Account changes of a logged in user
+    Updating(FetchTask, UserInfo),
+    UpdateFailed(UserInfo, anyhow::Error),
+
     // Error
     Error(anyhow::Error),
 }
 
+impl AuthState {
+    /// Returns the logged in user, including while their account is being changed
+    pub fn user(&self) -> Option<&UserInfo> {
+        match self {
+            AuthState::LoggedIn(user_info)
+            | AuthState::Offline(user_info)
+            | AuthState::Updating(_, user_info)
+            | AuthState::UpdateFailed(user_info, _) => Some(user_info),
+            AuthState::Probing(_)
+            | AuthState::LoggingOut(_)
+            | AuthState::Initial
+            | AuthState::LoggingIn(_)
+            | AuthState::AwaitingTotp { .. }
+            | AuthState::Error(_) => None,
+        }
+    }
+
+    /// Whether a request to sfi-server is in progress
+    pub fn is_busy(&self) -> bool {
+        match self {
+            AuthState::LoggingOut(_)
+            | AuthState::LoggingIn(_)
+            | AuthState::Probing(_)
+            | AuthState::Updating(..) => true,
+            AuthState::Initial
+            | AuthState::LoggedIn(_)
+            | AuthState::Offline(_)
+            | AuthState::AwaitingTotp { .. }
+            | AuthState::UpdateFailed(..)
+            | AuthState::Error(_) => false,
+        }
+    }
+}
+
 impl Component for LoginComponent {
     type Message = Msg;
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
         // Initiate a bridge to the auth agent
-        let auth_bridge = AuthAgent::bridge(link.callback(Msg::NewAuthState));
-
-        // Request the current authentication status
-        // auth_bridge.send(AuthAgentRequest::GetAuthStatus);
+        let auth_bridge = AuthAgent::subscribe(link.callback(Msg::NewAuthState));
 
         Self {
             state: Rc::new(AuthState::Initial),
             link,
             auth_bridge,
-            form: LoginForm::new(),
+            route_dispatcher: RouteAgentDispatcher::new(),
+            form: LoginForm::default(),
         }
     }
 
@@ -75,22 +121,31 @@ impl Component for LoginComponent {
         match msg {
             // Handle auth requests by the user
             Msg::StartLogin => self.auth_bridge.send(AuthAgentRequest::Login(UserLogin {
-                identifier: UserIdentifier::Name(self.form.name.clone()),
+                identifier: UserIdentifier::Name(self.form.name.trim().to_string()),
                 password: self.form.password.clone(),
                 totp: None,
             })),
-            Msg::StartSignup => self.auth_bridge.send(AuthAgentRequest::Signup(UserSignup {
-                password: self.form.password.clone(),
-                name: self.form.name.clone(),
-            })),
-            Msg::StartLogout => self.auth_bridge.send(AuthAgentRequest::Logout),
+            Msg::SubmitTotp => {
+                let code = std::mem::take(&mut self.form.totp);
+                self.auth_bridge.send(AuthAgentRequest::SubmitTotp(code));
+            }
+            Msg::CancelLogin => self.auth_bridge.send(AuthAgentRequest::CancelLogin),
 
             // Handle form inputs
             Msg::ChangePassword(password) => self.form.password = password,
             Msg::ChangeName(name) => self.form.name = name,
+            Msg::ChangeTotp(code) => self.form.totp = code,
 
             // Handle auth agent callbacks
-            Msg::NewAuthState(state) => self.state = state,
+            Msg::NewAuthState(state) => {
+                // Go back to where the user came from once logged in
+                if state.user().is_some() {
+                    self.route_dispatcher
+                        .send(RouteRequest::ChangeRoute(auth::take_return_route()));
+                }
+
+                self.state = state;
+            }
         }
 
         true
@@ -102,115 +157,126 @@ impl Component for LoginComponent {
 
     fn view(&self) -> Html {
         html! {
-            <>
+            <div>
+
+            <h2>{ "Log in" }</h2>
 
             { self.view_form() }
             { self.view_state() }
 
-            </>
+            <p>
+                { "No account yet? " }
+                <AppAnchor route=AppRoute::Register>{ "Register" }</AppAnchor>
+            </p>
+
+            </div>
         }
     }
 }
 
 impl LoginComponent {
     fn view_form(&self) -> Html {
-        let busy = self.is_busy();
+        let busy = self.state.is_busy();
+
+        if let AuthState::AwaitingTotp { .. } = self.state.as_ref() {
+            return html! {
+                <>
+
+                // The code of the authenticator app (or a recovery code)
+                <input
+                    type="text"
+                    placeholder="authentication code"
+                    autocomplete="one-time-code"
+                    value=self.form.totp.clone()
+                    oninput=self.link.callback(|i: InputData| Msg::ChangeTotp(i.value))
+                />
+
+                // Space
+                // TODO use CSS instead
+                { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::SubmitTotp)
+                    disabled=self.form.totp.trim().is_empty()
+                >
+                    {"Verify"}
+                </button>
+
+                // Space
+                // TODO use CSS instead
+                { " " }
+
+                <button onclick=self.link.callback(|_| Msg::CancelLogin)>
+                    {"Cancel"}
+                </button>
+
+                </>
+            };
+        }
 
-        match self.state.as_ref() {
-            AuthState::LoggedIn(_) | AuthState::LoggingOut(_) => {
-                html! {
+        html! {
+            <>
 
-                    <button
-                        onclick=self.link.callback(|_| Msg::StartLogout)
-                        disabled=busy
-                    >
-                        {"Log out"}
-                    </button>
+            <input
+                type="text"
+                placeholder="user name"
+                autocomplete="username"
+                disabled=busy
+                value=self.form.name.clone()
+                oninput=self.link.callback(|i: InputData| Msg::ChangeName(i.value))
+            />
+
+            // Space
+            // TODO use CSS instead
+            { " " }
+
+            <input
+                type="password"
+                placeholder="password"
+                autocomplete="current-password"
+                disabled=busy
+                value=self.form.password.clone()
+                oninput=self.link.callback(|i: InputData| Msg::ChangePassword(i.value))
+            />
+
+            // TODO use CSS instead
+            <br />
+            <br />
+
+            <button
+                onclick=self.link.callback(|_| Msg::StartLogin)
+                disabled=busy || self.form.name.trim().is_empty() || self.form.password.is_empty()
+            >
+                {"Login"}
+            </button>
 
-                }
-            }
-            _ => {
-                html! {
-                    <>
-
-                    // The name of the new user
-                    <input
-                        type="text"
-                        placeholder="user name"
-                        disabled=busy
-                        oninput=self.link.callback(|i: InputData| Msg::ChangeName(i.value))
-                    />
-
-                    // Space
-                    // TODO use CSS instead
-                    { " " }
-
-                    // The input fields for new cards
-                    <input
-                        type="password"
-                        placeholder="password"
-                        disabled=busy
-                        oninput=self.link.callback(|i: InputData| Msg::ChangePassword(i.value))
-                    />
-
-                    // // Space
-                    // // TODO use CSS instead
-                    // { " " }
-
-                    // // The input fields for new cards
-                    // <input
-                    //     type="text"
-                    //     placeholder="UUID"
-                    //     oninput=self.link.callback(|i: InputData| Msg::ChangeUuid(i.value))
-                    // />
-
-                    // TODO use CSS instead
-                    <br />
-                    <br />
-
-                    <button
-                        onclick=self.link.callback(|_| Msg::StartLogin)
-                        disabled=busy
-                    >
-                        {"Login"}
-                    </button>
-
-                    // Space
-                    // TODO use CSS instead
-                    { " " }
-
-                    <button
-                        onclick=self.link.callback(|_| Msg::StartSignup)
-                        disabled=busy
-                    >
-                        {"Sign up"}
-                    </button>
-
-                    </>
-                }
-            }
+            </>
         }
     }
 
     fn view_state(&self) -> Html {
         match self.state.as_ref() {
             AuthState::Probing(_) => html! {<p>{ "Fetching auth state..." }</p>},
-            AuthState::Initial => {
-                html! {<p>{ "Not logged in" }</p>}
-            }
             AuthState::LoggingIn(_) => html! {<p>{ "Logging in..." }</p>},
-            AuthState::LoggedIn(user) => {
-                html! {<p>{ format!("Logged in as {} ({})", user.name, user.uuid) }</p>}
+            AuthState::AwaitingTotp {
+                rejected: false, ..
+            } => html! {
+                <p>{ "Please enter the code of your authenticator app, or one of your recovery codes" }</p>
+            },
+            AuthState::AwaitingTotp { rejected: true, .. } => {
+                html! {<p>{ "This code is invalid, please try again" }</p>}
+            }
+            AuthState::Error(error) => {
+                html! {<p class="sfi-error">{ "Couldn't log in: " }{ error }</p>}
             }
-            AuthState::Error(error) => html! {<p>{ "Couldn't log in: " }{ error }</p>},
             AuthState::LoggingOut(_) => html! {<p>{ "Logging out..." }</p>},
-        }
-    }
-
-    fn is_busy(&self) -> bool {
-        match self.state.as_ref() {
-            AuthState::LoggingOut(_) | AuthState::LoggingIn(_) | AuthState::Probing(_) => true,
-            AuthState::Initial | AuthState::LoggedIn(_) | AuthState::Error(_) => false,
+            AuthState::Offline(_) => {
+                html! {<p>{ "sfi-server can't be reached, you are working offline" }</p>}
+            }
+            AuthState::Initial
+            | AuthState::LoggedIn(_)
+            | AuthState::Updating(..)
+            | AuthState::UpdateFailed(..) => html! {},
         }
     }
 }
@@ -0,0 +1,40 @@
+use yew::prelude::*;
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{components::app::AppRoute, services::auth};
+
+/// Leads to the login page, which returns to the current page after logging in
+pub struct LoginButton {
+    link: ComponentLink<Self>,
+    route_dispatcher: RouteAgentDispatcher,
+}
+
+impl Component for LoginButton {
+    type Message = ();
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            route_dispatcher: RouteAgentDispatcher::new(),
+        }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        auth::remember_current_route();
+
+        self.route_dispatcher
+            .send(RouteRequest::ChangeRoute(AppRoute::Login.into()));
+        false
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <button onclick=self.link.callback(|_| ())>{ "Log in" }</button>
+        }
+    }
+}
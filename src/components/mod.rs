@@ -1,6 +1,12 @@
+pub mod account_settings;
 pub mod app;
 pub mod core;
 pub mod debug_tools;
+pub mod guard;
 pub mod home;
 pub mod login;
+pub mod login_button;
+pub mod register;
+pub mod settings;
 pub mod toolbar;
+pub mod totp_settings;
@@ -0,0 +1,187 @@
+use std::rc::Rc;
+
+use sfi_core::core::users::UserSignup;
+use yew::prelude::*;
+use yew_router::{agent::RouteRequest, prelude::RouteAgentDispatcher};
+
+use crate::{
+    components::{
+        app::{AppAnchor, AppRoute},
+        login::AuthState,
+    },
+    services::{
+        auth::{self, AuthAgent, AuthAgentRequest},
+        validation::{self, PasswordStrength},
+    },
+};
+
+/// The registration page, which checks the name and password before signing up
+pub struct Register {
+    link: ComponentLink<Self>,
+    state: Rc<AuthState>,
+    auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    route_dispatcher: RouteAgentDispatcher,
+
+    name: String,
+    password: String,
+    repeated_password: String,
+}
+
+pub enum Msg {
+    NewAuthState(Rc<AuthState>),
+    UpdateName(String),
+    UpdatePassword(String),
+    UpdateRepeatedPassword(String),
+    Signup,
+}
+
+impl Component for Register {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            state: Rc::new(AuthState::Initial),
+            auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            route_dispatcher: RouteAgentDispatcher::new(),
+            name: String::new(),
+            password: String::new(),
+            repeated_password: String::new(),
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewAuthState(state) => {
+                // New users are logged in right away
+                if state.user().is_some() {
+                    self.route_dispatcher
+                        .send(RouteRequest::ChangeRoute(auth::take_return_route()));
+                }
+
+                self.state = state;
+            }
+            Msg::UpdateName(name) => self.name = name,
+            Msg::UpdatePassword(password) => self.password = password,
+            Msg::UpdateRepeatedPassword(password) => self.repeated_password = password,
+            Msg::Signup => {
+                if self.is_valid() {
+                    self.auth_bridge.send(AuthAgentRequest::Signup(UserSignup {
+                        name: self.name.trim().to_string(),
+                        password: self.password.clone(),
+                    }))
+                }
+            }
+        }
+
+        true
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let busy = self.state.is_busy();
+
+        html! {
+            <div>
+
+            <h2>{ "Register" }</h2>
+
+            <input
+                type="text"
+                placeholder="user name"
+                autocomplete="username"
+                disabled=busy
+                value=self.name.clone()
+                oninput=self.link.callback(|i: InputData| Msg::UpdateName(i.value))
+            />
+
+            { match (self.name.is_empty(), validation::validate_name(self.name.trim())) {
+                (false, Err(reason)) => html! { <p class="sfi-error">{ reason }</p> },
+                _ => html! { <br /> },
+            } }
+
+            <input
+                type="password"
+                placeholder="password"
+                autocomplete="new-password"
+                disabled=busy
+                value=self.password.clone()
+                oninput=self.link.callback(|i: InputData| Msg::UpdatePassword(i.value))
+            />
+
+            // Space
+            // TODO use CSS instead
+            { " " }
+
+            <input
+                type="password"
+                placeholder="repeat password"
+                autocomplete="new-password"
+                disabled=busy
+                value=self.repeated_password.clone()
+                oninput=self.link.callback(|i: InputData| Msg::UpdateRepeatedPassword(i.value))
+            />
+
+            { self.view_password_feedback() }
+
+            <button
+                onclick=self.link.callback(|_| Msg::Signup)
+                disabled=busy || !self.is_valid()
+            >
+                {"Sign up"}
+            </button>
+
+            { match self.state.as_ref() {
+                AuthState::LoggingIn(_) => html! { <p>{ "Signing up..." }</p> },
+                AuthState::Error(error) => html! { <p class="sfi-error">{ "Couldn't sign up: " }{ error }</p> },
+                _ => html! {},
+            } }
+
+            <p>
+                { "Already registered? " }
+                <AppAnchor route=AppRoute::Login>{ "Log in" }</AppAnchor>
+            </p>
+
+            </div>
+        }
+    }
+}
+
+impl Register {
+    fn is_valid(&self) -> bool {
+        validation::validate_name(self.name.trim()).is_ok()
+            && PasswordStrength::of(&self.password).is_acceptable()
+            && self.password == self.repeated_password
+    }
+
+    fn view_password_feedback(&self) -> Html {
+        if self.password.is_empty() {
+            return html! { <><br /><br /></> };
+        }
+
+        let strength = PasswordStrength::of(&self.password);
+        let class = if strength.is_acceptable() {
+            ""
+        } else {
+            "sfi-error"
+        };
+
+        html! {
+            <>
+
+            <p class=class>{ "Password strength: " }{ strength.describe() }</p>
+
+            { if !self.repeated_password.is_empty() && self.password != self.repeated_password {
+                html! { <p class="sfi-error">{ "The passwords don't match" }</p> }
+            } else {
+                html! {}
+            } }
+
+            </>
+        }
+    }
+}
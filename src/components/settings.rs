@@ -1,17 +1,223 @@
-use yew::prelude::*;
+use crate::services::{
+    catalogue::{CatalogueAgent, CatalogueRequest, CatalogueResponse},
+    config::{self, ConfigError},
+};
+use yew::{
+    prelude::*,
+    services::{
+        reader::{FileChunk, ReaderTask},
+        ReaderService,
+    },
+};
 
-pub struct Settings;
+/// How many bytes of a catalogue file are read (and imported) at once
+const CATALOGUE_CHUNK_SIZE: usize = 256 * 1024;
+
+pub struct Settings {
+    link: ComponentLink<Self>,
+    api_base_url: String,
+    effective_api_base_url: String,
+    api_base_url_error: Option<ConfigError>,
+
+    catalogue_bridge: Box<dyn Bridge<CatalogueAgent>>,
+    catalogue_size: Option<u32>,
+    catalogue_status: Option<String>,
+    catalogue_server_lookup: bool,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+    catalogue_import: Option<CatalogueImport>,
+}
+
+/// A catalogue file which is being imported, one chunk of lines at a time
+#[derive(Default)]
+struct CatalogueImport {
+    name: String,
+
+    /// What was read after the last complete line
+    rest: Vec<u8>,
+
+    /// How many chunks were sent to the catalogue agent, but not imported yet
+    importing: usize,
+
+    /// Whether the whole file has been read
+    read: bool,
+
+    imported: usize,
+    skipped: usize,
+}
+
+pub enum Msg {
+    UpdateApiBaseUrl(String),
+    Save,
+    Reset,
+    PickCatalogueFile(ChangeData),
+    CatalogueChunk(Option<FileChunk>),
+    ClearCatalogue,
+    ToggleServerLookup,
+    CatalogueResponse(CatalogueResponse),
+}
 
 impl Component for Settings {
-    type Message = ();
+    type Message = Msg;
     type Properties = ();
 
-    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
-        Self {}
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut catalogue_bridge = CatalogueAgent::bridge(link.callback(Msg::CatalogueResponse));
+        catalogue_bridge.send(CatalogueRequest::GetSize);
+
+        Self {
+            api_base_url: config::user_api_base_url().unwrap_or_default(),
+            effective_api_base_url: config::api_base_url(),
+            api_base_url_error: None,
+            catalogue_bridge,
+            catalogue_size: None,
+            catalogue_status: None,
+            catalogue_server_lookup: config::catalogue_server_lookup(),
+            reader: ReaderService::new(),
+            reader_task: None,
+            catalogue_import: None,
+            link,
+        }
     }
 
-    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
-        true
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::UpdateApiBaseUrl(url) => {
+                self.api_base_url = url;
+                false
+            }
+            Msg::Save => {
+                self.api_base_url_error =
+                    config::set_user_api_base_url(Some(self.api_base_url.clone())).err();
+                self.effective_api_base_url = config::api_base_url();
+                true
+            }
+            Msg::Reset => {
+                self.api_base_url_error = config::set_user_api_base_url(None).err();
+                if self.api_base_url_error.is_none() {
+                    self.api_base_url = String::new();
+                }
+
+                self.effective_api_base_url = config::api_base_url();
+                true
+            }
+            Msg::PickCatalogueFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::CatalogueChunk);
+
+                    match self
+                        .reader
+                        .read_file_by_chunks(file, callback, CATALOGUE_CHUNK_SIZE)
+                    {
+                        Ok(task) => {
+                            self.reader_task = Some(task);
+                            self.catalogue_status = Some("Reading the file...".to_string());
+                        }
+                        Err(error) => self.catalogue_status = Some(error.to_string()),
+                    }
+                }
+                true
+            }
+            Msg::PickCatalogueFile(_) => false,
+            Msg::CatalogueChunk(Some(FileChunk::Started { name, .. })) => {
+                self.catalogue_status = Some(format!("Importing {}...", name));
+                self.catalogue_import = Some(CatalogueImport {
+                    name,
+                    ..CatalogueImport::default()
+                });
+                true
+            }
+            Msg::CatalogueChunk(Some(FileChunk::DataChunk { data, progress })) => {
+                let lines: Option<Vec<u8>> = match &mut self.catalogue_import {
+                    Some(import) => {
+                        self.catalogue_status = Some(format!(
+                            "Importing {}... ({:.0} %)",
+                            import.name,
+                            progress * 100.0
+                        ));
+
+                        // Only import complete lines, the rest follows with the next chunk
+                        import.rest.extend(data);
+                        import
+                            .rest
+                            .iter()
+                            .rposition(|byte| *byte == b'\n')
+                            .map(|end| import.rest.drain(..=end).collect())
+                    }
+                    None => return false,
+                };
+
+                if let Some(lines) = lines {
+                    self.import_lines(lines);
+                }
+                true
+            }
+            Msg::CatalogueChunk(Some(FileChunk::Finished)) => {
+                self.reader_task = None;
+
+                let rest = match &mut self.catalogue_import {
+                    Some(import) => {
+                        import.read = true;
+                        std::mem::take(&mut import.rest)
+                    }
+                    None => return false,
+                };
+
+                if !rest.is_empty() {
+                    self.import_lines(rest);
+                }
+
+                self.finish_import();
+                true
+            }
+            Msg::CatalogueChunk(None) => {
+                self.reader_task = None;
+                self.catalogue_import = None;
+                self.catalogue_status = Some("Cannot read the file".to_string());
+                true
+            }
+            Msg::ClearCatalogue => {
+                self.catalogue_bridge.send(CatalogueRequest::Clear);
+                false
+            }
+            Msg::ToggleServerLookup => {
+                match config::set_catalogue_server_lookup(!self.catalogue_server_lookup) {
+                    Ok(()) => self.catalogue_server_lookup = !self.catalogue_server_lookup,
+                    Err(error) => self.catalogue_status = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::CatalogueResponse(response) => match response {
+                CatalogueResponse::Size(size) => {
+                    self.catalogue_size = Some(size);
+                    true
+                }
+                CatalogueResponse::Imported { imported, skipped } => {
+                    if let Some(import) = &mut self.catalogue_import {
+                        import.importing = import.importing.saturating_sub(1);
+                        import.imported += imported;
+                        import.skipped += skipped;
+                    }
+
+                    self.finish_import();
+                    true
+                }
+                CatalogueResponse::Cleared => {
+                    self.catalogue_status = Some("Removed all products".to_string());
+                    self.catalogue_bridge.send(CatalogueRequest::GetSize);
+                    true
+                }
+                CatalogueResponse::Error(error) => {
+                    // Stop importing, the following chunks would most likely fail as well
+                    self.reader_task = None;
+                    self.catalogue_import = None;
+
+                    self.catalogue_status = Some(error);
+                    true
+                }
+                CatalogueResponse::Product(..) => false,
+            },
+        }
     }
 
     fn change(&mut self, _: Self::Properties) -> ShouldRender {
@@ -22,9 +228,114 @@ impl Component for Settings {
         html! {
             <>
 
+            <h1>{ "Settings" }</h1>
+
+            <h3>{ "Server" }</h3>
+
+            <p>
+                { "Currently using " }
+                <code>{ &self.effective_api_base_url }</code>
+            </p>
+
+            // The base URL input
+            <input
+                type="url"
+                placeholder="https://sfi.example.com"
+                value={self.api_base_url.to_owned()}
+                oninput=self.link.callback(|i: InputData| Msg::UpdateApiBaseUrl(i.value))
+            /> { " " }
+
+            // Save button
+            <button onclick=self.link.callback(|_| Msg::Save)>
+                { "Save" }
+            </button>  { " " }
+
+            // Reset button
+            <button onclick=self.link.callback(|_| Msg::Reset)>
+                { "Use default" }
+            </button>
+
+            { match &self.api_base_url_error {
+                Some(error) => html! { <p class="sfi-error">{ "Couldn't save the server: " }{ error }</p> },
+                None => html! {},
+            } }
+
+            <h3>{ "Product catalogue" }</h3>
+
+            <p>
+                { match self.catalogue_size {
+                    Some(size) => format!("The catalogue on this device contains {} products.", size),
+                    None => "Loading the catalogue...".to_string(),
+                } }
+            </p>
+
+            <p>
+                { "Import products from a file with one product per line: " }
+                <code>{ "EAN, name, brand, category, shelf life in days" }</code>
+                { ", separated by tabs." }
+            </p>
+
+            // The catalogue file input
+            <input
+                type="file"
+                accept=".tsv,.txt,text/tab-separated-values,text/plain"
+                onchange=self.link.callback(Msg::PickCatalogueFile)
+            /> { " " }
 
+            <button onclick=self.link.callback(|_| Msg::ClearCatalogue)>
+                { "Clear catalogue" }
+            </button>
+
+            <p>{ self.catalogue_status.clone().unwrap_or_default() }</p>
+
+            <label>
+                <input
+                    type="checkbox"
+                    checked=self.catalogue_server_lookup
+                    onclick=self.link.callback(|_| Msg::ToggleServerLookup)
+                />
+                { " Look up unknown products on the server" }
+            </label>
 
             </>
         }
     }
 }
+
+impl Settings {
+    /// Sends complete lines of the catalogue file being imported to the catalogue agent
+    fn import_lines(&mut self, lines: Vec<u8>) {
+        let import = match &mut self.catalogue_import {
+            Some(import) => import,
+            None => return,
+        };
+
+        // The lines are complete, so no character was split between chunks
+        match String::from_utf8(lines) {
+            Ok(tsv) => {
+                import.importing += 1;
+                self.catalogue_bridge.send(CatalogueRequest::Import(tsv));
+            }
+            Err(_) => {
+                self.catalogue_status = Some(format!("{} is not a text file", import.name));
+                self.catalogue_import = None;
+                self.reader_task = None;
+            }
+        }
+    }
+
+    /// Reports the result once the whole file has been read and imported
+    fn finish_import(&mut self) {
+        match &self.catalogue_import {
+            Some(import) if import.read && import.importing == 0 => {
+                self.catalogue_status = Some(format!(
+                    "Imported {} products ({} lines skipped)",
+                    import.imported, import.skipped
+                ));
+                self.catalogue_import = None;
+                self.catalogue_bridge.send(CatalogueRequest::GetSize);
+            }
+            _ => {}
+        }
+    }
+}
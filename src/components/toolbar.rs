@@ -1,16 +1,46 @@
+use std::rc::Rc;
+
 use yew::prelude::*;
 
-pub struct Toolbar;
+use crate::{
+    components::{
+        app::{AppAnchor, AppRoute},
+        login::AuthState,
+        login_button::LoginButton,
+    },
+    services::auth::{AuthAgent, AuthAgentRequest},
+};
+
+/// The header of every page, showing who is logged in
+pub struct Toolbar {
+    link: ComponentLink<Self>,
+    state: Rc<AuthState>,
+    auth_bridge: Box<dyn Bridge<AuthAgent>>,
+}
+
+pub enum Msg {
+    NewAuthState(Rc<AuthState>),
+    Logout,
+}
 
 impl Component for Toolbar {
-    type Message = ();
+    type Message = Msg;
     type Properties = ();
 
-    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
-        Self {}
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            state: Rc::new(AuthState::Initial),
+            auth_bridge: AuthAgent::subscribe(link.callback(Msg::NewAuthState)),
+            link,
+        }
     }
 
-    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewAuthState(state) => self.state = state,
+            Msg::Logout => self.auth_bridge.send(AuthAgentRequest::Logout),
+        }
+
         true
     }
 
@@ -19,12 +49,40 @@ impl Component for Toolbar {
     }
 
     fn view(&self) -> Html {
-        html! {
-            <>
+        let content = match (self.state.as_ref(), self.state.user()) {
+            (_, Some(user)) => html! {
+                <>
+
+                <AppAnchor route=AppRoute::AccountSettings>{ &user.name }</AppAnchor>
 
+                { match self.state.as_ref() {
+                    AuthState::Offline(_) => html! { " (offline)" },
+                    _ => html! {},
+                } }
 
+                // Space
+                // TODO use CSS instead
+                { " " }
 
-            </>
+                <button
+                    onclick=self.link.callback(|_| Msg::Logout)
+                    disabled=self.state.is_busy()
+                >
+                    {"Log out"}
+                </button>
+
+                </>
+            },
+            (AuthState::Probing(_), None) => html! { "Fetching auth state..." },
+            (AuthState::LoggingOut(_), None) => html! { "Logging out..." },
+            (_, None) => html! { <LoginButton /> },
+        };
+
+        html! {
+            <header>
+                <AppAnchor route=AppRoute::Home>{ "Home" }</AppAnchor> { " | " }
+                { content }
+            </header>
         }
     }
 }
@@ -0,0 +1,297 @@
+use sfi_core::core::users::StatusNotice;
+use yew::{
+    prelude::*,
+    services::{fetch::FetchTask, DialogService},
+};
+
+use crate::{
+    components::core::qr_code::QrCode,
+    services::{
+        api::{ApiClient, ApiError},
+        totp::{self, RecoveryCodes, TotpCode, TotpEnrolment, TotpStatus},
+    },
+};
+
+/// Lets the logged in user enable or disable two-factor authentication
+pub struct TotpSettings {
+    link: ComponentLink<Self>,
+
+    /// Whether TOTP is enabled, once loaded
+    enabled: Option<bool>,
+
+    /// The secret being set up, until its first code was verified
+    enrolment: Option<TotpEnrolment>,
+
+    /// The recovery codes, which are only shown once after verifying the enrolment
+    recovery_codes: Option<Vec<String>>,
+
+    code: String,
+    error: Option<String>,
+
+    // Avoid dropping requests
+    fetch_task: Option<FetchTask>,
+}
+
+pub enum Msg {
+    Loaded(Result<TotpStatus, ApiError>),
+    StartEnrolment,
+    Enrolled(Result<TotpEnrolment, ApiError>),
+    UpdateCode(String),
+    Verify,
+    Verified(Result<RecoveryCodes, ApiError>),
+    Disable,
+    Disabled(Result<StatusNotice, ApiError>),
+}
+
+impl Component for TotpSettings {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut settings = Self {
+            link,
+            enabled: None,
+            enrolment: None,
+            recovery_codes: None,
+            code: String::new(),
+            error: None,
+            fetch_task: None,
+        };
+
+        let callback = settings.link.callback(Msg::Loaded);
+        let task = ApiClient::configured().totp_status(callback);
+        settings.start(task);
+
+        settings
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Loaded(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(status) => self.enabled = Some(status.enabled),
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::StartEnrolment => {
+                let callback = self.link.callback(Msg::Enrolled);
+                let task = ApiClient::configured().enrol_totp(callback);
+
+                self.recovery_codes = None;
+                self.start(task)
+            }
+            Msg::Enrolled(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(enrolment) => {
+                        self.enrolment = Some(enrolment);
+                        self.error = None;
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::UpdateCode(code) => {
+                self.code = code;
+                true
+            }
+            Msg::Verify => {
+                let code = TotpCode {
+                    code: totp::normalize_code(&self.code),
+                };
+
+                let callback = self.link.callback(Msg::Verified);
+                let task = ApiClient::configured().verify_totp(&code, callback);
+
+                self.start(task)
+            }
+            Msg::Verified(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(codes) => {
+                        self.enabled = Some(true);
+                        self.enrolment = None;
+                        self.recovery_codes = Some(codes.recovery_codes);
+                        self.error = None;
+                    }
+                    Err(ApiError::Status { code: 401, .. }) => {
+                        self.error = Some("This code is invalid, please try again".to_string())
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+
+                self.code.clear();
+                true
+            }
+            Msg::Disable => {
+                if !DialogService::confirm(
+                    "Disable two-factor authentication?\nYour recovery codes will stop working.",
+                ) {
+                    return false;
+                }
+
+                let code = TotpCode {
+                    code: totp::normalize_code(&self.code),
+                };
+
+                let callback = self.link.callback(Msg::Disabled);
+                let task = ApiClient::configured().disable_totp(&code, callback);
+
+                self.start(task)
+            }
+            Msg::Disabled(result) => {
+                self.fetch_task = None;
+
+                match result {
+                    Ok(_) => {
+                        self.enabled = Some(false);
+                        self.recovery_codes = None;
+                        self.error = None;
+                    }
+                    Err(ApiError::Status { code: 401, .. }) => {
+                        self.error = Some("This code is invalid, please try again".to_string())
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+
+                self.code.clear();
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <h3>{ "Two-factor authentication" }</h3>
+
+                { self.view_status() }
+
+                { self.view_recovery_codes() }
+
+                { match &self.error {
+                    Some(error) => html! { <p class="sfi-error">{ error }</p> },
+                    None => html! {},
+                } }
+            </div>
+        }
+    }
+}
+
+impl TotpSettings {
+    /// Keeps a request alive, or shows why it couldn't be sent
+    fn start(&mut self, task: Result<FetchTask, ApiError>) -> ShouldRender {
+        match task {
+            Ok(task) => self.fetch_task = Some(task),
+            Err(error) => self.error = Some(error.to_string()),
+        }
+        true
+    }
+
+    fn view_status(&self) -> Html {
+        let is_busy = self.fetch_task.is_some();
+
+        if let Some(enrolment) = &self.enrolment {
+            return html! {
+                <>
+
+                <p>{ "Scan this code with your authenticator app, then enter the code it shows:" }</p>
+
+                <QrCode data=enrolment.otpauth_url.clone() />
+
+                <p>
+                    { "Or enter this key manually: " }
+                    <code>{ &enrolment.secret }</code>
+                </p>
+
+                { self.view_code_input() } { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::Verify)
+                    disabled=is_busy || self.code.trim().is_empty()
+                >
+                    { "Verify" }
+                </button>
+
+                </>
+            };
+        }
+
+        match self.enabled {
+            None if self.error.is_none() => html! { <p>{ "Loading..." }</p> },
+            None => html! {},
+            Some(false) => html! {
+                <>
+
+                <p>{ "Protect your account with codes from an authenticator app in addition to your password." }</p>
+
+                <button onclick=self.link.callback(|_| Msg::StartEnrolment) disabled=is_busy>
+                    { "Set up two-factor authentication" }
+                </button>
+
+                </>
+            },
+            Some(true) => html! {
+                <>
+
+                <p>{ "Two-factor authentication is enabled. Enter a current code to disable it." }</p>
+
+                { self.view_code_input() } { " " }
+
+                <button
+                    onclick=self.link.callback(|_| Msg::Disable)
+                    disabled=is_busy || self.code.trim().is_empty()
+                >
+                    { "Disable" }
+                </button>
+
+                </>
+            },
+        }
+    }
+
+    fn view_code_input(&self) -> Html {
+        html! {
+            <input
+                type="text"
+                placeholder="authentication code"
+                autocomplete="one-time-code"
+                disabled=self.fetch_task.is_some()
+                value=self.code.clone()
+                oninput=self.link.callback(|i: InputData| Msg::UpdateCode(i.value))
+            />
+        }
+    }
+
+    fn view_recovery_codes(&self) -> Html {
+        let codes = match &self.recovery_codes {
+            Some(codes) => codes,
+            None => return html! {},
+        };
+
+        html! {
+            <>
+
+            <p>
+                { "Two-factor authentication is now enabled. " }
+                <strong>{ "Store these recovery codes in a safe place," }</strong>
+                { " each of them can be used once instead of a code if you lose your authenticator:" }
+            </p>
+
+            <ul>
+                { for codes.iter().map(|code| html! { <li><code>{ code }</code></li> }) }
+            </ul>
+
+            </>
+        }
+    }
+}
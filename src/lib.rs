@@ -6,7 +6,7 @@ pub(crate) mod services;
 pub(crate) mod types;
 
 use components::app;
-use services::{auth::AuthAgent, data::DataAgent};
+use services::{auth::AuthAgent, catalogue::CatalogueAgent, data::DataAgent};
 use wasm_bindgen::prelude::*;
 use yew::{web_sys::console, Dispatched};
 
@@ -25,6 +25,7 @@ pub fn main() -> Result<(), JsValue> {
     // Create the singleton instances of the agents
     Box::leak(Box::new(DataAgent::dispatcher()));
     Box::leak(Box::new(AuthAgent::dispatcher()));
+    Box::leak(Box::new(CatalogueAgent::dispatcher()));
 
     // Start the yew app
     yew::start_app::<app::App>();
@@ -0,0 +1,518 @@
+use super::{
+    auth::{AccountDeletion, NameChange, PasswordChange},
+    config,
+    events::LoggedEvent,
+    invitations::{Invitation, NewInvitation},
+    sync::{PullResponse, PushResponse},
+    totp::{RecoveryCodes, TotpCode, TotpEnrolment, TotpStatus},
+};
+use crate::types::Product;
+use serde::{de::DeserializeOwned, Serialize};
+use sfi_core::core::users::{StatusNotice, UserIdentifier, UserInfo, UserLogin, UserSignup};
+use std::fmt;
+use uuid::Uuid;
+use yew::{
+    callback::Callback,
+    format::{Json, Nothing, Text},
+    services::{
+        fetch::{
+            FetchOptions, FetchTask, Method, Request as FetchRequest, Response as FetchResponse,
+        },
+        FetchService,
+    },
+    web_sys::RequestCredentials,
+};
+
+/// The callback type used for the typed responses of sfi-server
+pub type ApiCallback<T> = Callback<Result<T, ApiError>>;
+
+/// Describes why a request to sfi-server failed
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// The request couldn't be sent, or no response was received (e.g. while offline)
+    Network(String),
+
+    /// sfi-server responded with an unsuccessful HTTP status code
+    Status { code: u16, message: Option<String> },
+
+    /// The response of sfi-server couldn't be deserialized
+    Deserialize(String),
+}
+
+/// A typed client for the REST API of sfi-server
+///
+/// Every request to sfi-server should be made using this client.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    base_url: String,
+}
+
+impl ApiClient {
+    /// Creates a client for the sfi-server configured for this device
+    pub fn configured() -> Self {
+        Self::new(config::api_base_url())
+    }
+
+    /// Creates a client for the sfi-server at the given base URL (e.g. a local mock server)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Builds the full URL of an endpoint, e.g. `url("authentication/status")`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/api/v1/{}", self.base_url, path)
+    }
+
+    // Authentication
+
+    pub fn auth_status(&self, callback: ApiCallback<UserInfo>) -> Result<FetchTask, ApiError> {
+        self.get("authentication/status", callback)
+    }
+
+    pub fn login(
+        &self,
+        login_info: &UserLogin,
+        callback: ApiCallback<UserInfo>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("authentication/login", login_info, callback)
+    }
+
+    pub fn signup(
+        &self,
+        signup_info: &UserSignup,
+        callback: ApiCallback<UserInfo>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("authentication/signup", signup_info, callback)
+    }
+
+    pub fn logout(&self, callback: ApiCallback<StatusNotice>) -> Result<FetchTask, ApiError> {
+        self.get("authentication/logout", callback)
+    }
+
+    // Users
+
+    /// Looks up the public information of a user (e.g. to show the name of a member)
+    pub fn user(
+        &self,
+        user_uuid: Uuid,
+        callback: ApiCallback<UserInfo>,
+    ) -> Result<FetchTask, ApiError> {
+        self.get(&format!("users/{}", user_uuid), callback)
+    }
+
+    /// Finds a user by their name, e.g. to share an inventory with them
+    pub fn find_user(
+        &self,
+        identifier: &UserIdentifier,
+        callback: ApiCallback<UserInfo>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("users/lookup", identifier, callback)
+    }
+
+    // Account
+
+    pub fn change_name(
+        &self,
+        change: &NameChange,
+        callback: ApiCallback<UserInfo>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("users/me/name", change, callback)
+    }
+
+    pub fn change_password(
+        &self,
+        change: &PasswordChange,
+        callback: ApiCallback<StatusNotice>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("users/me/password", change, callback)
+    }
+
+    /// Deletes the account of the current user, which requires their password
+    pub fn delete_account(
+        &self,
+        deletion: &AccountDeletion,
+        callback: ApiCallback<StatusNotice>,
+    ) -> Result<FetchTask, ApiError> {
+        self.fetch(
+            Method::DELETE,
+            "users/me",
+            Some(Json(deletion).into()),
+            callback,
+        )
+    }
+
+    // Two-factor authentication
+
+    pub fn totp_status(&self, callback: ApiCallback<TotpStatus>) -> Result<FetchTask, ApiError> {
+        self.get("users/me/totp", callback)
+    }
+
+    /// Creates a new TOTP secret, which replaces any unverified one
+    pub fn enrol_totp(&self, callback: ApiCallback<TotpEnrolment>) -> Result<FetchTask, ApiError> {
+        self.post("users/me/totp", &(), callback)
+    }
+
+    /// Activates the TOTP secret created last, if the code matches it
+    pub fn verify_totp(
+        &self,
+        code: &TotpCode,
+        callback: ApiCallback<RecoveryCodes>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("users/me/totp/verify", code, callback)
+    }
+
+    pub fn disable_totp(
+        &self,
+        code: &TotpCode,
+        callback: ApiCallback<StatusNotice>,
+    ) -> Result<FetchTask, ApiError> {
+        self.fetch(
+            Method::DELETE,
+            "users/me/totp",
+            Some(Json(code).into()),
+            callback,
+        )
+    }
+
+    // Invitations
+
+    /// Invites someone to an inventory (only admins may do this)
+    pub fn create_invitation(
+        &self,
+        inventory_uuid: Uuid,
+        invitation: &NewInvitation,
+        callback: ApiCallback<Invitation>,
+    ) -> Result<FetchTask, ApiError> {
+        let path = format!("inventories/{}/invitations", inventory_uuid);
+        self.post(&path, invitation, callback)
+    }
+
+    /// Lists the outstanding invitations to an inventory
+    pub fn invitations(
+        &self,
+        inventory_uuid: Uuid,
+        callback: ApiCallback<Vec<Invitation>>,
+    ) -> Result<FetchTask, ApiError> {
+        self.get(
+            &format!("inventories/{}/invitations", inventory_uuid),
+            callback,
+        )
+    }
+
+    pub fn revoke_invitation(
+        &self,
+        inventory_uuid: Uuid,
+        token: &str,
+        callback: ApiCallback<StatusNotice>,
+    ) -> Result<FetchTask, ApiError> {
+        let path = format!(
+            "inventories/{}/invitations/{}",
+            inventory_uuid,
+            encode_component(token)
+        );
+        self.delete(&path, callback)
+    }
+
+    /// Looks up an invitation, so that the recipient can see what they're joining
+    pub fn invitation(
+        &self,
+        token: &str,
+        callback: ApiCallback<Invitation>,
+    ) -> Result<FetchTask, ApiError> {
+        self.get(
+            &format!("invitations/{}", encode_component(token)),
+            callback,
+        )
+    }
+
+    /// Adds the current user to the inventory of an invitation
+    pub fn accept_invitation(
+        &self,
+        token: &str,
+        callback: ApiCallback<StatusNotice>,
+    ) -> Result<FetchTask, ApiError> {
+        let path = format!("invitations/{}/accept", encode_component(token));
+        self.post(&path, &(), callback)
+    }
+
+    // Product catalogue
+
+    /// Looks up a product by its EAN, resolving to `None` if sfi-server doesn't know it
+    pub fn product(
+        &self,
+        ean: &str,
+        callback: ApiCallback<Option<Product>>,
+    ) -> Result<FetchTask, ApiError> {
+        let callback = callback.reform(|result| match result {
+            Ok(product) => Ok(Some(product)),
+            Err(ApiError::Status { code: 404, .. }) => Ok(None),
+            Err(error) => Err(error),
+        });
+
+        self.get(&format!("catalogue/{}", ean), callback)
+    }
+
+    // Synchronisation
+
+    /// Sends local events to sfi-server
+    pub fn push_events(
+        &self,
+        events: &[LoggedEvent],
+        callback: ApiCallback<PushResponse>,
+    ) -> Result<FetchTask, ApiError> {
+        self.post("sync/events", &events, callback)
+    }
+
+    /// Fetches the remote events recorded after the given cursor
+    pub fn pull_events(
+        &self,
+        cursor: &Option<String>,
+        callback: ApiCallback<PullResponse>,
+    ) -> Result<FetchTask, ApiError> {
+        self.get(&sync_events_path(cursor), callback)
+    }
+
+    // Request plumbing
+
+    fn get<T>(&self, path: &str, callback: ApiCallback<T>) -> Result<FetchTask, ApiError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        self.fetch(Method::GET, path, None, callback)
+    }
+
+    fn delete<T>(&self, path: &str, callback: ApiCallback<T>) -> Result<FetchTask, ApiError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        self.fetch(Method::DELETE, path, None, callback)
+    }
+
+    fn post<B, T>(
+        &self,
+        path: &str,
+        body: &B,
+        callback: ApiCallback<T>,
+    ) -> Result<FetchTask, ApiError>
+    where
+        B: Serialize,
+        T: DeserializeOwned + 'static,
+    {
+        self.fetch(Method::POST, path, Some(Json(body).into()), callback)
+    }
+
+    /// Sends a request, with a JSON body if one is given
+    fn fetch<T>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Text>,
+        callback: ApiCallback<T>,
+    ) -> Result<FetchTask, ApiError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let mut builder = FetchRequest::builder().method(method).uri(self.url(path));
+
+        // Requests without a body have no content to describe
+        if body.is_some() {
+            builder = builder.header("Content-Type", "application/json");
+        }
+
+        let request = builder
+            .body(body.unwrap_or_else(|| Nothing.into()))
+            .map_err(|error| ApiError::Network(error.to_string()))?;
+
+        let options = FetchOptions {
+            // sfi-server may be hosted on a different origin than the web-app
+            credentials: Some(RequestCredentials::Include),
+            ..FetchOptions::default()
+        };
+
+        let callback = callback.reform(|response: FetchResponse<Text>| parse_response(response));
+
+        FetchService::fetch_with_options(request, options, callback)
+            .map_err(|error| ApiError::Network(error.to_string()))
+    }
+}
+
+/// Builds the path of the synchronisation endpoint, optionally starting after a cursor
+fn sync_events_path(cursor: &Option<String>) -> String {
+    match cursor {
+        Some(cursor) => format!("sync/events?since={}", encode_component(cursor)),
+        None => "sync/events".to_string(),
+    }
+}
+
+/// Percent-encodes a value for use as a path segment or in a query string
+///
+/// Only the unreserved characters of RFC 3986 are kept as they are.
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Turns a raw response of sfi-server into a typed result
+fn parse_response<T>(response: FetchResponse<Text>) -> Result<T, ApiError>
+where
+    T: DeserializeOwned,
+{
+    let status = response.status();
+
+    // yew reports requests which never received a response as an error body
+    let body = match response.into_body() {
+        Ok(body) => body,
+        Err(error) => return Err(ApiError::Network(error.to_string())),
+    };
+
+    if !status.is_success() {
+        return Err(ApiError::Status {
+            code: status.as_u16(),
+            message: Some(body).filter(|body| !body.is_empty()),
+        });
+    }
+
+    serde_json::from_str(&body).map_err(|error| ApiError::Deserialize(error.to_string()))
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(reason) => write!(f, "Cannot reach the server ({})", reason),
+            ApiError::Status {
+                code,
+                message: Some(message),
+            } => write!(f, "The server responded with {}: {}", code, message),
+            ApiError::Status {
+                code,
+                message: None,
+            } => write!(f, "The server responded with {}", code),
+            ApiError::Deserialize(reason) => {
+                write!(
+                    f,
+                    "Cannot understand the response of the server ({})",
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Answer {
+        value: u32,
+    }
+
+    fn response(status: u16, body: Text) -> FetchResponse<Text> {
+        FetchResponse::builder()
+            .status(status)
+            .body(body)
+            .expect("Cannot build response")
+    }
+
+    #[test]
+    fn builds_urls_without_duplicate_slashes() {
+        let client = ApiClient::new("https://sfi.example/");
+        assert_eq!(
+            client.url("authentication/status"),
+            "https://sfi.example/api/v1/authentication/status"
+        );
+    }
+
+    #[test]
+    fn builds_sync_paths() {
+        assert_eq!(sync_events_path(&None), "sync/events");
+        assert_eq!(
+            sync_events_path(&Some("42".to_string())),
+            "sync/events?since=42"
+        );
+    }
+
+    #[test]
+    fn encodes_the_sync_cursor() {
+        assert_eq!(
+            sync_events_path(&Some("2021-05-01T12:00:00+02:00&x=1".to_string())),
+            "sync/events?since=2021-05-01T12%3A00%3A00%2B02%3A00%26x%3D1"
+        );
+    }
+
+    #[test]
+    fn encodes_non_ascii_characters() {
+        assert_eq!(encode_component("a b/ä"), "a%20b%2F%C3%A4");
+        assert_eq!(encode_component("Az09-._~"), "Az09-._~");
+    }
+
+    #[test]
+    fn parses_successful_responses() {
+        let result: Result<Answer, ApiError> =
+            parse_response(response(200, Ok(r#"{"value":42}"#.to_string())));
+        assert_eq!(result.unwrap(), Answer { value: 42 });
+    }
+
+    #[test]
+    fn maps_error_statuses() {
+        let result: Result<Answer, ApiError> =
+            parse_response(response(403, Ok("Forbidden".to_string())));
+
+        match result {
+            Err(ApiError::Status { code: 403, message }) => {
+                assert_eq!(message, Some("Forbidden".to_string()))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn omits_empty_error_messages() {
+        let result: Result<Answer, ApiError> = parse_response(response(500, Ok(String::new())));
+
+        match result {
+            Err(ApiError::Status {
+                code: 500,
+                message: None,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn maps_missing_responses_to_network_errors() {
+        let result: Result<Answer, ApiError> =
+            parse_response(response(200, Err(anyhow::anyhow!("Failed to fetch"))));
+
+        match result {
+            Err(ApiError::Network(_)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn maps_invalid_bodies_to_deserialize_errors() {
+        let result: Result<Answer, ApiError> =
+            parse_response(response(200, Ok("not json".to_string())));
+
+        match result {
+            Err(ApiError::Deserialize(_)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -1,36 +1,95 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sfi_core::core::users::{StatusNotice, UserInfo, UserLogin, UserSignup};
 use std::{collections::HashSet, rc::Rc};
 use yew::{
-    format::{Json, Nothing},
-    services::{
-        fetch::{FetchOptions, Request as FetchRequest, Response as FetchResponse},
-        FetchService,
-    },
-    web_sys::RequestCredentials,
+    format::{Json, Text},
+    services::{fetch::FetchTask, storage::Area, StorageService},
     worker::*,
+    Callback,
 };
+use yew_router::route::Route;
 
-use crate::components::login::AuthState;
+use super::{
+    api::{ApiClient, ApiError},
+    tabs::TabChannel,
+    totp::{self, TOTP_REQUIRED_STATUS},
+};
+use crate::components::{app::AppRoute, login::AuthState};
+
+/// The sessionStorage key of the route to return to after logging in
+const RETURN_ROUTE_KEY: &'static str = "sfi.auth.return_route";
+
+/// The localStorage key of the user last logged in on this device
+const KNOWN_USER_KEY: &'static str = "sfi.auth.user";
+
+/// The name of the channel over which the auth agents of all tabs talk to each other
+const TAB_CHANNEL_NAME: &'static str = "sfi.auth";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum AuthAgentRequest {
+    /// Sends every change of the auth state (starting with the current one) until disconnected
+    Subscribe,
     GetAuthStatus,
     Login(UserLogin),
+
+    /// Completes a login which is awaiting a TOTP (or recovery) code
+    SubmitTotp(String),
+    CancelLogin,
     Signup(UserSignup),
     Logout,
+    ChangeName(NameChange),
+    ChangePassword(PasswordChange),
+    DeleteAccount(AccountDeletion),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NameChange {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasswordChange {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountDeletion {
+    pub password: String,
+}
+
+/// What the auth agent of one tab tells those of the other tabs
+///
+/// All tabs share the same session, so they can simply adopt the new state.
+#[derive(Serialize, Deserialize)]
+pub enum TabMessage {
+    LoggedIn(UserInfo),
+    LoggedOut,
 }
 
 pub enum Msg {
     LoggedIn(UserInfo),
     LoggedOut,
     LoginError(anyhow::Error),
+    /// The login needs a TOTP code, and whether the previous code was rejected
+    TotpRequired(bool),
+    UpdateError(UserInfo, anyhow::Error),
+    Unreachable(ApiError),
+    OtherTab(TabMessage),
 }
 
 pub struct AuthAgent {
     link: AgentLink<AuthAgent>,
     subscribers: HashSet<HandlerId>,
+    tab_channel: Option<TabChannel>,
+
+    /// The user the other tabs were last told about (or told this tab about)
+    shared_user: Option<UserInfo>,
+
+    /// The login in progress, which is completed with a TOTP code if needed
+    ///
+    /// Kept out of the auth state, since it contains the password.
+    pending_login: Option<UserLogin>,
 
     // Avoid dropping requests
     auth_state: Rc<AuthState>,
@@ -43,83 +102,134 @@ impl Agent for AuthAgent {
     type Output = Rc<AuthState>;
 
     fn create(link: AgentLink<Self>) -> Self {
-        link.send_input(AuthAgentRequest::GetAuthStatus);
+        // Without a channel, every tab keeps its own state until it is reloaded
+        let tab_channel = TabChannel::open(TAB_CHANNEL_NAME, link.callback(Msg::OtherTab));
 
-        Self {
+        let mut agent = Self {
             link,
             subscribers: HashSet::new(),
+            tab_channel,
+            shared_user: None,
+            pending_login: None,
             auth_state: Rc::new(AuthState::Initial),
-        }
+        };
+
+        // Probe right away, so no subscriber mistakes the initial state for being logged out
+        agent.auth_state = Rc::new(agent.probe_state());
+        agent
     }
 
     fn update(&mut self, msg: Self::Message) {
+        // Only a login awaiting a TOTP code still needs the password
+        if !matches!(msg, Msg::TotpRequired(_)) {
+            self.pending_login = None;
+        }
+
         // Inform subscribers about internal changes from fetch callbacks
         let output = Rc::new(match msg {
-            Msg::LoggedIn(user_info) => AuthState::LoggedIn(user_info),
+            Msg::LoggedIn(user_info) => {
+                remember_user(Some(&user_info));
+                self.share(Some(&user_info));
+                AuthState::LoggedIn(user_info)
+            }
             Msg::LoginError(error) => AuthState::Error(error),
-            Msg::LoggedOut => AuthState::Initial,
-        });
+            Msg::TotpRequired(rejected) => AuthState::AwaitingTotp { rejected },
+            Msg::LoggedOut => {
+                remember_user(None);
+                self.share(None);
+                AuthState::Initial
+            }
+            Msg::UpdateError(user_info, error) => AuthState::UpdateFailed(user_info, error),
 
-        for sub in self.subscribers.iter() {
-            self.link.respond(*sub, output.clone());
-        }
+            // Keep the data of the last user readable while offline
+            Msg::Unreachable(error) => match known_user() {
+                Some(user_info) => {
+                    log::warn!("Cannot reach sfi-server, continuing offline: {}", error);
+                    AuthState::Offline(user_info)
+                }
+                None => AuthState::Initial,
+            },
+
+            // Adopt the state of the other tab without telling it back
+            Msg::OtherTab(TabMessage::LoggedIn(user_info)) => {
+                remember_user(Some(&user_info));
+                self.shared_user = Some(user_info.clone());
+                AuthState::LoggedIn(user_info)
+            }
+            Msg::OtherTab(TabMessage::LoggedOut) => {
+                remember_user(None);
+                self.shared_user = None;
+                AuthState::Initial
+            }
+        });
 
-        // Store the new AuthState in self
-        self.auth_state = output;
+        self.broadcast(output);
     }
 
-    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
         // Handle authentication  requests from components and other agents
         match msg {
+            AuthAgentRequest::Subscribe => {
+                self.subscribers.insert(id);
+
+                // Let new subscribers know the current state right away
+                self.link.respond(id, self.auth_state.clone());
+            }
             AuthAgentRequest::GetAuthStatus => {
                 let output = Rc::new(self.probe_state());
-
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, output.clone());
-                }
-
-                // Store the new AuthState in self
-                self.auth_state = output;
+                self.broadcast(output);
             }
             AuthAgentRequest::Login(login_info) => {
                 let output = Rc::new(self.login(login_info));
+                self.broadcast(output);
+            }
+            AuthAgentRequest::SubmitTotp(code) => {
+                let login_info = match (self.auth_state.as_ref(), &self.pending_login) {
+                    (AuthState::AwaitingTotp { .. }, Some(login)) => UserLogin {
+                        totp: Some(totp::normalize_code(&code)),
+                        ..login.clone()
+                    },
+                    _ => {
+                        log::warn!("Cannot submit a TOTP code without a pending login");
+                        return;
+                    }
+                };
 
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, output.clone());
+                let output = Rc::new(self.login(login_info));
+                self.broadcast(output);
+            }
+            AuthAgentRequest::CancelLogin => {
+                if let AuthState::AwaitingTotp { .. } = self.auth_state.as_ref() {
+                    self.pending_login = None;
+                    self.broadcast(Rc::new(AuthState::Initial));
                 }
-
-                // Store the new AuthState in self
-                self.auth_state = output;
             }
             AuthAgentRequest::Signup(signup_info) => {
                 let output = Rc::new(self.signup(signup_info));
-
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, output.clone());
-                }
-
-                // Store the new AuthState in self
-                self.auth_state = output;
+                self.broadcast(output);
             }
             AuthAgentRequest::Logout => {
                 let output = Rc::new(self.logout());
-
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, output.clone());
+                self.broadcast(output);
+            }
+            AuthAgentRequest::ChangeName(change) => {
+                if let Some(user_info) = self.current_user() {
+                    let output = Rc::new(self.change_name(change, user_info));
+                    self.broadcast(output);
+                }
+            }
+            AuthAgentRequest::ChangePassword(change) => {
+                if let Some(user_info) = self.current_user() {
+                    let output = Rc::new(self.change_password(change, user_info));
+                    self.broadcast(output);
+                }
+            }
+            AuthAgentRequest::DeleteAccount(deletion) => {
+                if let Some(user_info) = self.current_user() {
+                    let output = Rc::new(self.delete_account(deletion, user_info));
+                    self.broadcast(output);
                 }
-
-                // Store the new AuthState in self
-                self.auth_state = output;
             }
-        }
-    }
-
-    fn connected(&mut self, id: HandlerId) {
-        // FIelD `1` oF STrucT `yeW::AGENT::hANnlERiD` Is PRivATE
-        // PRiVATE fIELd rUsTC e0616
-        // if id.1 {}
-        if format!("{:?}", &id).contains("true") {
-            self.subscribers.insert(id);
         }
     }
 
@@ -129,125 +239,316 @@ impl Agent for AuthAgent {
 }
 
 impl AuthAgent {
-    fn login(&mut self, login_info: UserLogin) -> AuthState {
-        let request = FetchRequest::post("http://localhost:8080/api/v1/authentication/login")
-            .header("Content-Type", "application/json")
-            .body(Json(&login_info))
-            .expect("Failed to build request (login).");
-
-        let options = FetchOptions {
-            credentials: Some(RequestCredentials::SameOrigin),
-            ..FetchOptions::default()
-        };
+    /// Opens a bridge to the auth agent, which receives every change of the auth state
+    pub fn subscribe(callback: Callback<Rc<AuthState>>) -> Box<dyn Bridge<AuthAgent>> {
+        let mut bridge = AuthAgent::bridge(callback);
+        bridge.send(AuthAgentRequest::Subscribe);
+        bridge
+    }
 
+    fn login(&mut self, login_info: UserLogin) -> AuthState {
+        let with_totp = login_info.totp.is_some();
         let callback = self
             .link
-            .callback(|response: FetchResponse<Json<Result<UserInfo>>>| {
-                let Json(data) = response.into_body();
-
-                match data {
-                    Ok(user) => Msg::LoggedIn(user),
-                    Err(error) => Msg::LoginError(error),
-                }
-            });
+            .callback(move |result| login_outcome(result, with_totp));
 
-        let task = FetchService::fetch_with_options(request, options, callback);
+        let task = ApiClient::configured().login(&login_info, callback);
 
         // Store the task so it isn't canceled immediately
         match task {
-            Ok(fetch_task) => AuthState::LoggingIn(fetch_task),
-            Err(error) => AuthState::Error(error),
+            Ok(fetch_task) => {
+                // Another code may be needed, so don't keep the current one
+                self.pending_login = Some(UserLogin {
+                    totp: None,
+                    ..login_info
+                });
+                AuthState::LoggingIn(fetch_task)
+            }
+            Err(error) => {
+                self.pending_login = None;
+                AuthState::Error(error.into())
+            }
         }
     }
 
     fn signup(&mut self, signup_info: UserSignup) -> AuthState {
-        let request = FetchRequest::post("http://localhost:8080/api/v1/authentication/signup")
-            .header("Content-Type", "application/json")
-            .body(Json(&signup_info))
-            .expect("Failed to build request (signup).");
-
-        let options = FetchOptions {
-            credentials: Some(RequestCredentials::SameOrigin),
-            ..FetchOptions::default()
-        };
-
         let callback = self
             .link
-            .callback(|response: FetchResponse<Json<Result<UserInfo>>>| {
-                let Json(data) = response.into_body();
-
-                match data {
-                    Ok(user) => Msg::LoggedIn(user),
-                    Err(error) => Msg::LoginError(error),
-                }
+            .callback(|result: Result<UserInfo, ApiError>| match result {
+                Ok(user) => Msg::LoggedIn(user),
+                Err(error) => Msg::LoginError(error.into()),
             });
 
-        let task = FetchService::fetch_with_options(request, options, callback);
+        let task = ApiClient::configured().signup(&signup_info, callback);
 
         // Store the task so it isn't canceled immediately
         match task {
             Ok(fetch_task) => AuthState::LoggingIn(fetch_task),
-            Err(error) => AuthState::Error(error),
+            Err(error) => AuthState::Error(error.into()),
         }
     }
 
     fn logout(&mut self) -> AuthState {
-        let request = FetchRequest::get("http://localhost:8080/api/v1/authentication/logout")
-            .body(Nothing)
-            .expect("Failed to build request (logout).");
-
-        let options = FetchOptions {
-            credentials: Some(RequestCredentials::SameOrigin),
-            ..FetchOptions::default()
-        };
-
         let callback = self
             .link
-            .callback(|response: FetchResponse<Json<Result<StatusNotice>>>| {
-                let Json(data) = response.into_body();
-
-                match data {
-                    Ok(_) => Msg::LoggedOut,
-                    Err(error) => Msg::LoginError(error),
-                }
+            .callback(|result: Result<StatusNotice, ApiError>| match result {
+                Ok(_) => Msg::LoggedOut,
+                Err(error) => Msg::LoginError(error.into()),
             });
 
-        let task = FetchService::fetch_with_options(request, options, callback);
+        let task = ApiClient::configured().logout(callback);
 
         // Store the task so it isn't canceled immediately
         match task {
             Ok(fetch_task) => AuthState::LoggingOut(fetch_task),
-            Err(error) => AuthState::Error(error),
+            Err(error) => AuthState::Error(error.into()),
         }
     }
 
-    fn probe_state(&self) -> AuthState {
-        let request = FetchRequest::get("http://localhost:8080/api/v1/authentication/status")
-            .body(Nothing)
-            .expect("Failed to build request (probe).");
+    /// Returns the user whose account may be changed, unless another request is in progress
+    fn current_user(&self) -> Option<UserInfo> {
+        match self.auth_state.as_ref() {
+            AuthState::LoggedIn(user_info) | AuthState::UpdateFailed(user_info, _) => {
+                Some(user_info.clone())
+            }
+            _ => {
+                log::warn!("Cannot change the account while not logged in or busy");
+                None
+            }
+        }
+    }
 
-        let options = FetchOptions {
-            credentials: Some(RequestCredentials::SameOrigin),
-            ..FetchOptions::default()
+    /// Tells the other tabs about the logged in user, unless they already know about them
+    fn share(&mut self, user_info: Option<&UserInfo>) {
+        let is_known = match (&self.shared_user, user_info) {
+            (Some(shared), Some(user_info)) => {
+                shared.uuid == user_info.uuid && shared.name == user_info.name
+            }
+            (None, None) => true,
+            _ => false,
         };
 
+        if is_known {
+            return;
+        }
+
+        self.shared_user = user_info.cloned();
+
+        if let Some(tab_channel) = &self.tab_channel {
+            tab_channel.post(&match user_info {
+                Some(user_info) => TabMessage::LoggedIn(user_info.clone()),
+                None => TabMessage::LoggedOut,
+            });
+        }
+    }
+
+    /// Informs all subscribers about a new AuthState and stores it in self
+    fn broadcast(&mut self, output: Rc<AuthState>) {
+        for sub in self.subscribers.iter() {
+            self.link.respond(*sub, output.clone());
+        }
+
+        self.auth_state = output;
+    }
+
+    fn change_name(&mut self, change: NameChange, user_info: UserInfo) -> AuthState {
+        let previous = user_info.clone();
         let callback = self
             .link
-            .callback(|response: FetchResponse<Json<Result<UserInfo>>>| {
-                let Json(data) = response.into_body();
+            .callback(move |result: Result<UserInfo, ApiError>| match result {
+                Ok(user) => Msg::LoggedIn(user),
+                Err(error) => Msg::UpdateError(previous.clone(), error.into()),
+            });
 
-                match data {
-                    Ok(user) => Msg::LoggedIn(user),
-                    Err(_) => Msg::LoggedOut,
-                }
+        let task = ApiClient::configured().change_name(&change, callback);
+        Self::updating(task, user_info)
+    }
+
+    fn change_password(&mut self, change: PasswordChange, user_info: UserInfo) -> AuthState {
+        let previous = user_info.clone();
+        let callback =
+            self.link
+                .callback(move |result: Result<StatusNotice, ApiError>| match result {
+                    Ok(_) => Msg::LoggedIn(previous.clone()),
+                    Err(error) => Msg::UpdateError(previous.clone(), error.into()),
+                });
+
+        let task = ApiClient::configured().change_password(&change, callback);
+        Self::updating(task, user_info)
+    }
+
+    fn delete_account(&mut self, deletion: AccountDeletion, user_info: UserInfo) -> AuthState {
+        let previous = user_info.clone();
+        let callback =
+            self.link
+                .callback(move |result: Result<StatusNotice, ApiError>| match result {
+                    Ok(_) => Msg::LoggedOut,
+                    Err(error) => Msg::UpdateError(previous.clone(), error.into()),
+                });
+
+        let task = ApiClient::configured().delete_account(&deletion, callback);
+        Self::updating(task, user_info)
+    }
+
+    fn updating(task: Result<FetchTask, ApiError>, user_info: UserInfo) -> AuthState {
+        // Store the task so it isn't canceled immediately, and keep the user logged in on errors
+        match task {
+            Ok(fetch_task) => AuthState::Updating(fetch_task, user_info),
+            Err(error) => AuthState::UpdateFailed(user_info, error.into()),
+        }
+    }
+
+    fn probe_state(&self) -> AuthState {
+        let callback = self
+            .link
+            .callback(|result: Result<UserInfo, ApiError>| match result {
+                Ok(user) => Msg::LoggedIn(user),
+
+                // Only sfi-server itself can tell that the session is gone
+                Err(ApiError::Status { code: 401, .. })
+                | Err(ApiError::Status { code: 403, .. }) => Msg::LoggedOut,
+                Err(error) => Msg::Unreachable(error),
             });
 
-        let task = FetchService::fetch_with_options(request, options, callback);
+        let task = ApiClient::configured().auth_status(callback);
 
         // Store the task so it isn't canceled immediately
         match task {
             Ok(fetch_task) => AuthState::Probing(fetch_task),
-            Err(error) => AuthState::Error(error),
+            Err(error) => AuthState::Error(error.into()),
+        }
+    }
+}
+
+/// Maps the response to a login to the resulting message, depending on whether a TOTP code was sent
+fn login_outcome(result: Result<UserInfo, ApiError>, with_totp: bool) -> Msg {
+    match result {
+        Ok(user) => Msg::LoggedIn(user),
+
+        // The password was correct, but a second factor is needed
+        Err(ApiError::Status { code, .. }) if code == TOTP_REQUIRED_STATUS && !with_totp => {
+            Msg::TotpRequired(false)
+        }
+
+        // Let the user try another code
+        Err(ApiError::Status { code: 401, .. }) if with_totp => Msg::TotpRequired(true),
+
+        Err(error) => Msg::LoginError(error.into()),
+    }
+}
+
+/// Remembers the logged in user (or forgets them after logging out) for offline use
+fn remember_user(user_info: Option<&UserInfo>) {
+    if let Ok(mut storage) = StorageService::new(Area::Local) {
+        match user_info {
+            Some(user_info) => storage.store(KNOWN_USER_KEY, Json(user_info)),
+            None => storage.remove(KNOWN_USER_KEY),
+        }
+    }
+}
+
+/// Returns the user last logged in on this device, if they haven't logged out since
+pub fn known_user() -> Option<UserInfo> {
+    let storage = StorageService::new(Area::Local).ok()?;
+
+    match storage.restore(KNOWN_USER_KEY) {
+        Json(Ok(user_info)) => Some(user_info),
+        _ => None,
+    }
+}
+
+/// Remembers the route (e.g. `/invite/...`) to return to once the user has logged in
+pub fn remember_return_route(route: &str) {
+    if let Ok(mut storage) = StorageService::new(Area::Session) {
+        storage.store(RETURN_ROUTE_KEY, Ok(route.to_string()));
+    }
+}
+
+/// Remembers the page currently shown, e.g. before sending the user to the login page
+pub fn remember_current_route() {
+    let location = yew::utils::window().location();
+    let path = location.pathname().unwrap_or_default();
+    let search = location.search().unwrap_or_default();
+
+    remember_return_route(&format!("{}{}", path, search));
+}
+
+/// Returns (and forgets) the route to return to after logging in, which defaults to home
+pub fn take_return_route() -> Route {
+    let remembered = StorageService::new(Area::Session)
+        .ok()
+        .and_then(|mut storage| {
+            let route: Text = storage.restore(RETURN_ROUTE_KEY);
+            storage.remove(RETURN_ROUTE_KEY);
+            route.ok()
+        });
+
+    match remembered {
+        // Never return to the login pages themselves
+        Some(route)
+            if route.starts_with('/')
+                && !route.starts_with("/login")
+                && !route.starts_with("/register") =>
+        {
+            Route::new_no_state(&route)
+        }
+        _ => AppRoute::Home.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> Result<UserInfo, ApiError> {
+        Err(ApiError::Status {
+            code,
+            message: None,
+        })
+    }
+
+    #[test]
+    fn asks_for_a_totp_code_after_the_password() {
+        match login_outcome(status(TOTP_REQUIRED_STATUS), false) {
+            Msg::TotpRequired(false) => {}
+            _ => panic!("Expected a TOTP code to be required"),
+        }
+    }
+
+    #[test]
+    fn asks_again_after_a_wrong_totp_code() {
+        match login_outcome(status(401), true) {
+            Msg::TotpRequired(true) => {}
+            _ => panic!("Expected the TOTP code to be rejected"),
+        }
+    }
+
+    #[test]
+    fn fails_on_a_wrong_password() {
+        match login_outcome(status(401), false) {
+            Msg::LoginError(_) => {}
+            _ => panic!("Expected the login to fail"),
+        }
+    }
+
+    #[test]
+    fn fails_if_a_totp_code_is_required_again() {
+        match login_outcome(status(TOTP_REQUIRED_STATUS), true) {
+            Msg::LoginError(_) => {}
+            _ => panic!("Expected the login to fail"),
+        }
+    }
+
+    #[test]
+    fn fails_on_other_errors() {
+        match login_outcome(status(500), true) {
+            Msg::LoginError(_) => {}
+            _ => panic!("Expected the login to fail"),
+        }
+
+        match login_outcome(Err(ApiError::Network("offline".to_string())), false) {
+            Msg::LoginError(_) => {}
+            _ => panic!("Expected the login to fail"),
         }
     }
 }
@@ -0,0 +1,416 @@
+use super::ean;
+use image::{imageops::FilterType, DynamicImage, GenericImageView, GrayImage};
+use std::fmt;
+
+/// Photos are scaled down to this width before scanning, which is plenty for a barcode
+const MAX_SCAN_WIDTH: u32 = 1600;
+
+/// The number of rows scanned in every orientation
+const SCAN_LINES: u32 = 32;
+
+/// Rows with less contrast than this (between the darkest and brightest pixel) are skipped
+const MIN_CONTRAST: u8 = 48;
+
+/// The maximum summed deviation (in modules) for a digit to be recognised
+const MAX_DIGIT_ERROR: f32 = 2.5;
+
+/// The bar and space widths (in modules) of the L-coded digits 0-9
+///
+/// G-coded digits use the same widths in reverse, R-coded digits the same widths
+/// with bars and spaces swapped.
+const L_PATTERNS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+/// The parity (L = 0, G = 1) of the left digits of an EAN-13 for each implied first digit
+const FIRST_DIGIT_PARITIES: [u8; 10] = [
+    0b000000, 0b001011, 0b001101, 0b001110, 0b010011, 0b011001, 0b011100, 0b010101, 0b010110,
+    0b011010,
+];
+
+/// The indices of the start, middle and end guard bars and spaces of an EAN-13
+const EAN13_GUARDS: [usize; 11] = [0, 1, 2, 27, 28, 29, 30, 31, 56, 57, 58];
+
+/// The indices of the start, middle and end guard bars and spaces of an EAN-8
+const EAN8_GUARDS: [usize; 11] = [0, 1, 2, 19, 20, 21, 22, 23, 40, 41, 42];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarcodeFormat {
+    Ean13,
+    Ean8,
+    UpcA,
+}
+
+/// A barcode found in an image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Barcode {
+    pub format: BarcodeFormat,
+
+    /// The digits of the code, including the check digit
+    pub code: String,
+}
+
+#[derive(Debug)]
+pub enum BarcodeError {
+    /// The file could not be read as an image
+    UnreadableImage(String),
+
+    /// The image doesn't contain a readable EAN-13, EAN-8 or UPC-A barcode
+    NotFound,
+}
+
+impl fmt::Display for BarcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BarcodeError::UnreadableImage(reason) => write!(f, "Cannot read the image: {}", reason),
+            BarcodeError::NotFound => write!(f, "No barcode found in the image"),
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+/// Finds an EAN-13, EAN-8 or UPC-A barcode in an image file (e.g. a JPEG or PNG photo)
+pub fn decode(bytes: &[u8]) -> Result<Barcode, BarcodeError> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|error| BarcodeError::UnreadableImage(error.to_string()))?;
+
+    let image = if image.width() > MAX_SCAN_WIDTH {
+        image.resize(MAX_SCAN_WIDTH, u32::MAX, FilterType::Triangle)
+    } else {
+        image
+    };
+
+    scan_rotations(image.to_luma8()).ok_or(BarcodeError::NotFound)
+}
+
+/// Scans the photo as is first, then rotated for barcodes printed sideways
+///
+/// Every row is read in both directions, which covers the 180° and 270° rotations as well.
+fn scan_rotations(gray: GrayImage) -> Option<Barcode> {
+    scan(&gray).or_else(|| scan(&DynamicImage::ImageLuma8(gray).rotate90().to_luma8()))
+}
+
+/// Scans rows of the image, starting in the middle and moving outwards
+fn scan(image: &GrayImage) -> Option<Barcode> {
+    let (width, height) = image.dimensions();
+    let middle = SCAN_LINES / 2;
+
+    (0..SCAN_LINES)
+        .map(|step| {
+            // 0, +1, -1, +2, -2, ...
+            let offset = step.div_ceil(2);
+            if step % 2 == 0 {
+                middle + offset
+            } else {
+                middle.saturating_sub(offset)
+            }
+        })
+        .filter(|line| *line < SCAN_LINES)
+        .find_map(|line| {
+            let y = height * (2 * line + 1) / (2 * SCAN_LINES);
+            let row: Vec<u8> = (0..width).map(|x| image.get_pixel(x, y)[0]).collect();
+
+            decode_row(&row)
+        })
+}
+
+/// Tries to read a barcode from a single row of pixels (in both directions)
+fn decode_row(row: &[u8]) -> Option<Barcode> {
+    let mut runs = to_runs(row)?;
+
+    find_barcode(&runs).or_else(|| {
+        // Reading the runs backwards restores the order of a barcode which is upside down.
+        // Read forwards, its right half consists of G-coded digits only, which never
+        // form a valid EAN-13 or EAN-8, so the first attempt cannot misread it.
+        runs.reverse();
+        find_barcode(&runs)
+    })
+}
+
+/// Binarises a row and returns the lengths of its alternating light and dark runs
+fn to_runs(row: &[u8]) -> Option<Vec<(bool, u32)>> {
+    let min = *row.iter().min()?;
+    let max = *row.iter().max()?;
+
+    if max - min < MIN_CONTRAST {
+        return None;
+    }
+
+    let threshold = ((min as u16 + max as u16) / 2) as u8;
+    let mut runs: Vec<(bool, u32)> = vec![];
+
+    for &pixel in row {
+        let dark = pixel < threshold;
+
+        match runs.last_mut() {
+            Some((last_dark, length)) if *last_dark == dark => *length += 1,
+            _ => runs.push((dark, 1)),
+        }
+    }
+
+    Some(runs)
+}
+
+/// Looks for a barcode starting at any dark run
+fn find_barcode(runs: &[(bool, u32)]) -> Option<Barcode> {
+    let widths: Vec<f32> = runs.iter().map(|(_, length)| *length as f32).collect();
+
+    (0..runs.len())
+        .filter(|start| runs[*start].0)
+        .find_map(|start| {
+            decode_ean13(&widths[start..])
+                .or_else(|| decode_ean8(&widths[start..]))
+                .filter(|barcode| ean::has_valid_check_digit(&barcode.code))
+        })
+}
+
+/// Decodes an EAN-13 (or UPC-A) from the widths of its 59 bars and spaces
+fn decode_ean13(widths: &[f32]) -> Option<Barcode> {
+    if widths.len() < 59 || !has_guards(&widths[..59], 95.0, &EAN13_GUARDS) {
+        return None;
+    }
+
+    let mut digits = String::with_capacity(13);
+    let mut parities = 0u8;
+
+    for index in 0..6 {
+        let (digit, is_g) = match_left_digit(&widths[3 + 4 * index..7 + 4 * index])?;
+        digits.push(digit);
+
+        if is_g {
+            parities |= 1 << (5 - index);
+        }
+    }
+
+    for index in 0..6 {
+        digits.push(match_right_digit(&widths[32 + 4 * index..36 + 4 * index])?);
+    }
+
+    let first = FIRST_DIGIT_PARITIES
+        .iter()
+        .position(|pattern| *pattern == parities)?;
+
+    // A UPC-A is an EAN-13 with a leading zero
+    if first == 0 {
+        Some(Barcode {
+            format: BarcodeFormat::UpcA,
+            code: digits,
+        })
+    } else {
+        Some(Barcode {
+            format: BarcodeFormat::Ean13,
+            code: format!("{}{}", first, digits),
+        })
+    }
+}
+
+/// Decodes an EAN-8 from the widths of its 43 bars and spaces
+fn decode_ean8(widths: &[f32]) -> Option<Barcode> {
+    if widths.len() < 43 || !has_guards(&widths[..43], 67.0, &EAN8_GUARDS) {
+        return None;
+    }
+
+    let mut digits = String::with_capacity(8);
+
+    for index in 0..4 {
+        match match_left_digit(&widths[3 + 4 * index..7 + 4 * index])? {
+            (digit, false) => digits.push(digit),
+            // EAN-8 only uses L-coded digits on the left
+            (_, true) => return None,
+        }
+    }
+
+    for index in 0..4 {
+        digits.push(match_right_digit(&widths[24 + 4 * index..28 + 4 * index])?);
+    }
+
+    Some(Barcode {
+        format: BarcodeFormat::Ean8,
+        code: digits,
+    })
+}
+
+/// Checks whether the guard bars (one module wide each) are where they should be
+fn has_guards(widths: &[f32], modules: f32, guards: &[usize]) -> bool {
+    let module = widths.iter().sum::<f32>() / modules;
+
+    guards
+        .iter()
+        .all(|index| (0.5 * module..=1.8 * module).contains(&widths[*index]))
+}
+
+/// Matches four widths against the L and G patterns, returns the digit and whether it's G-coded
+fn match_left_digit(widths: &[f32]) -> Option<(char, bool)> {
+    let mut best: Option<(f32, usize, bool)> = None;
+
+    for (digit, pattern) in L_PATTERNS.iter().enumerate() {
+        let mut reversed = *pattern;
+        reversed.reverse();
+
+        for &(error, is_g) in &[
+            (pattern_error(widths, pattern), false),
+            (pattern_error(widths, &reversed), true),
+        ] {
+            if best.is_none_or(|(best_error, _, _)| error < best_error) {
+                best = Some((error, digit, is_g));
+            }
+        }
+    }
+
+    best.filter(|(error, _, _)| *error < MAX_DIGIT_ERROR)
+        .map(|(_, digit, is_g)| (to_char(digit), is_g))
+}
+
+/// Matches four widths against the R patterns
+fn match_right_digit(widths: &[f32]) -> Option<char> {
+    L_PATTERNS
+        .iter()
+        .map(|pattern| pattern_error(widths, pattern))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("Errors are never NaN"))
+        .filter(|(_, error)| *error < MAX_DIGIT_ERROR)
+        .map(|(digit, _)| to_char(digit))
+}
+
+/// The summed deviation of the widths from the pattern, after scaling the widths to 7 modules
+fn pattern_error(widths: &[f32], pattern: &[u8; 4]) -> f32 {
+    let total: f32 = widths.iter().sum();
+
+    widths
+        .iter()
+        .zip(pattern.iter())
+        .map(|(width, expected)| (width * 7.0 / total - *expected as f32).abs())
+        .sum()
+}
+
+fn to_char(digit: usize) -> char {
+    std::char::from_digit(digit as u32, 10).expect("Digits are always below 10")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws a barcode as a row of pixels, three pixels per module, with quiet zones
+    fn draw(code: &str) -> Vec<u8> {
+        let digits: Vec<usize> = code
+            .chars()
+            .map(|c| c.to_digit(10).expect("Not a digit") as usize)
+            .collect();
+
+        let (parities, left, right) = match digits.len() {
+            8 => (0, &digits[..4], &digits[4..]),
+            12 => (0, &digits[..6], &digits[6..]),
+            13 => (FIRST_DIGIT_PARITIES[digits[0]], &digits[1..7], &digits[7..]),
+            length => panic!("Cannot draw {} digits", length),
+        };
+
+        let mut modules = vec![false; 10];
+        modules.extend(&[true, false, true]);
+
+        for (index, digit) in left.iter().enumerate() {
+            let mut widths = L_PATTERNS[*digit];
+
+            if parities & (1 << (left.len() - 1 - index)) != 0 {
+                widths.reverse();
+            }
+
+            push_digit(&mut modules, &widths, false);
+        }
+
+        modules.extend(&[false, true, false, true, false]);
+
+        for digit in right {
+            push_digit(&mut modules, &L_PATTERNS[*digit], true);
+        }
+
+        modules.extend(&[true, false, true]);
+        modules.extend(vec![false; 10]);
+
+        modules
+            .iter()
+            .flat_map(|dark| std::iter::repeat_n(if *dark { 20 } else { 230 }, 3))
+            .collect()
+    }
+
+    /// Appends the modules of a digit, whose first run is dark for R-coded digits
+    fn push_digit(modules: &mut Vec<bool>, widths: &[u8; 4], starts_dark: bool) {
+        for (index, width) in widths.iter().enumerate() {
+            let dark = starts_dark == (index % 2 == 0);
+            modules.extend(std::iter::repeat_n(dark, *width as usize));
+        }
+    }
+
+    fn reversed(row: Vec<u8>) -> Vec<u8> {
+        row.into_iter().rev().collect()
+    }
+
+    fn barcode(format: BarcodeFormat, code: &str) -> Option<Barcode> {
+        Some(Barcode {
+            format,
+            code: code.to_string(),
+        })
+    }
+
+    #[test]
+    fn decodes_ean13() {
+        let expected = barcode(BarcodeFormat::Ean13, "4006381333931");
+
+        assert_eq!(decode_row(&draw("4006381333931")), expected);
+        assert_eq!(decode_row(&reversed(draw("4006381333931"))), expected);
+    }
+
+    #[test]
+    fn decodes_ean8() {
+        let expected = barcode(BarcodeFormat::Ean8, "96385074");
+
+        assert_eq!(decode_row(&draw("96385074")), expected);
+        assert_eq!(decode_row(&reversed(draw("96385074"))), expected);
+    }
+
+    #[test]
+    fn decodes_upc_a() {
+        let expected = barcode(BarcodeFormat::UpcA, "036000291452");
+
+        assert_eq!(decode_row(&draw("036000291452")), expected);
+        assert_eq!(decode_row(&reversed(draw("036000291452"))), expected);
+    }
+
+    #[test]
+    fn decodes_every_rotation() {
+        let row = draw("4006381333931");
+        let image = GrayImage::from_fn(row.len() as u32, 40, |x, _| image::Luma([row[x as usize]]));
+        let image = DynamicImage::ImageLuma8(image);
+        let expected = barcode(BarcodeFormat::Ean13, "4006381333931");
+
+        for rotated in &[
+            image.clone(),
+            image.rotate90(),
+            image.rotate180(),
+            image.rotate270(),
+        ] {
+            assert_eq!(scan_rotations(rotated.to_luma8()), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_check_digit() {
+        assert_eq!(decode_row(&draw("4006381333932")), None);
+        assert_eq!(decode_row(&reversed(draw("96385075"))), None);
+    }
+
+    #[test]
+    fn rejects_rows_without_contrast() {
+        assert_eq!(decode_row(&[128; 300]), None);
+    }
+}
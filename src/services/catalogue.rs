@@ -0,0 +1,286 @@
+use super::{
+    api::{ApiClient, ApiError},
+    config, ean,
+    idb::{self, IdbError},
+};
+use crate::types::Product;
+use web_sys::IdbDatabase;
+use yew::{services::fetch::FetchTask, worker::*};
+
+const DATABASE_NAME: &'static str = "sfi-catalogue";
+const DATABASE_VERSION: u32 = 1;
+const PRODUCTS_STORE: &'static str = "products";
+
+/// Resolves EANs to products using a local database, falling back to sfi-server
+pub struct CatalogueAgent {
+    link: AgentLink<CatalogueAgent>,
+    db: Option<IdbDatabase>,
+
+    /// Why the database couldn't be opened (e.g. in private browsing mode)
+    open_error: Option<IdbError>,
+
+    /// Requests received before the database was opened
+    pending: Vec<(HandlerId, CatalogueRequest)>,
+
+    // Avoid dropping requests
+    fetch_tasks: Vec<FetchTask>,
+}
+
+#[derive(Debug)]
+pub enum CatalogueRequest {
+    /// Looks up the product with the given EAN
+    Lookup(String),
+
+    /// Imports products from tab-separated values (see the README for the format)
+    ///
+    /// Large files are sent in chunks of complete lines, each of which is imported on its own.
+    Import(String),
+
+    /// Removes every product from the local catalogue
+    Clear,
+
+    /// Requests the number of products in the local catalogue
+    GetSize,
+}
+
+#[derive(Debug, Clone)]
+pub enum CatalogueResponse {
+    /// The product with the given (normalised) EAN, if known
+    Product(String, Option<Product>),
+    Imported {
+        imported: usize,
+        skipped: usize,
+    },
+    Cleared,
+    Size(u32),
+    Error(String),
+}
+
+pub enum Msg {
+    Opened(Result<IdbDatabase, IdbError>),
+    Found(HandlerId, String, Result<Option<String>, IdbError>),
+    Fetched(HandlerId, String, Result<Option<Product>, ApiError>),
+    Respond(HandlerId, CatalogueResponse),
+    Failed(HandlerId, String),
+}
+
+impl Agent for CatalogueAgent {
+    type Reach = Context<Self>;
+    type Message = Msg;
+    type Input = CatalogueRequest;
+    type Output = CatalogueResponse;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        idb::open(
+            DATABASE_NAME,
+            DATABASE_VERSION,
+            &[PRODUCTS_STORE],
+            link.callback(Msg::Opened),
+        );
+
+        Self {
+            link,
+            db: None,
+            open_error: None,
+            pending: vec![],
+            fetch_tasks: vec![],
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            Msg::Opened(result) => {
+                match result {
+                    Ok(db) => self.db = Some(db),
+                    Err(error) => {
+                        log::error!("Cannot open the product catalogue: {}", error);
+                        self.open_error = Some(error);
+                    }
+                }
+
+                for (id, request) in std::mem::take(&mut self.pending) {
+                    self.handle_input(request, id);
+                }
+            }
+            Msg::Found(id, ean, Ok(Some(json))) => match serde_json::from_str(&json) {
+                Ok(product) => self
+                    .link
+                    .respond(id, CatalogueResponse::Product(ean, Some(product))),
+                Err(error) => {
+                    log::warn!("Cannot read product {} from the catalogue: {}", ean, error);
+                    self.fetch(id, ean);
+                }
+            },
+            Msg::Found(id, ean, Ok(None)) => self.fetch(id, ean),
+            Msg::Found(id, ean, Err(error)) => {
+                log::warn!("Cannot look up {} in the catalogue: {}", ean, error);
+                self.fetch(id, ean);
+            }
+            Msg::Fetched(id, ean, result) => {
+                self.fetch_tasks.retain(|task| task.is_active());
+
+                let product = match result {
+                    Ok(product) => product,
+                    Err(error) => {
+                        log::warn!("Cannot look up {} on the server: {}", ean, error);
+                        None
+                    }
+                };
+
+                // Remember products from sfi-server for offline use
+                if let (Some(product), Some(db)) = (&product, &self.db) {
+                    let json = serde_json::to_string(product).expect("Cannot serialize product");
+                    idb::put_all(
+                        db,
+                        PRODUCTS_STORE,
+                        vec![(ean.clone(), json)],
+                        yew::Callback::from(|result: Result<(), IdbError>| {
+                            if let Err(error) = result {
+                                log::warn!("Cannot cache product: {}", error);
+                            }
+                        }),
+                    );
+                }
+
+                self.link
+                    .respond(id, CatalogueResponse::Product(ean, product));
+            }
+            Msg::Respond(id, response) => self.link.respond(id, response),
+            Msg::Failed(id, error) => self.link.respond(id, CatalogueResponse::Error(error)),
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        if self.db.is_none() {
+            let error = match &self.open_error {
+                Some(error) => error.to_string(),
+                None => return self.pending.push((id, msg)),
+            };
+
+            // Lookups still work using sfi-server
+            return match msg {
+                CatalogueRequest::Lookup(ean) => self.fetch(id, ean),
+                _ => self.link.respond(id, CatalogueResponse::Error(error)),
+            };
+        }
+
+        let db = self.db.as_ref().expect("The database is open");
+
+        match msg {
+            CatalogueRequest::Lookup(input) => {
+                let ean = match ean::normalize(&input) {
+                    Ok(ean) => ean,
+                    // Invalid EANs aren't in the catalogue
+                    Err(_) => {
+                        return self
+                            .link
+                            .respond(id, CatalogueResponse::Product(input, None))
+                    }
+                };
+
+                let found_ean = ean.clone();
+                let callback = self
+                    .link
+                    .callback(move |result| Msg::Found(id, found_ean.clone(), result));
+
+                idb::get(db, PRODUCTS_STORE, &ean, callback);
+            }
+            CatalogueRequest::Import(tsv) => {
+                let (products, skipped) = parse_tsv(&tsv);
+                let imported = products.len();
+
+                let entries = products
+                    .into_iter()
+                    .map(|product| {
+                        let json =
+                            serde_json::to_string(&product).expect("Cannot serialize product");
+                        (product.ean, json)
+                    })
+                    .collect();
+
+                let callback =
+                    self.link
+                        .callback(move |result: Result<(), IdbError>| match result {
+                            Ok(()) => {
+                                Msg::Respond(id, CatalogueResponse::Imported { imported, skipped })
+                            }
+                            Err(error) => Msg::Failed(id, error.to_string()),
+                        });
+
+                idb::put_all(db, PRODUCTS_STORE, entries, callback);
+            }
+            CatalogueRequest::Clear => {
+                let callback =
+                    self.link
+                        .callback(move |result: Result<(), IdbError>| match result {
+                            Ok(()) => Msg::Respond(id, CatalogueResponse::Cleared),
+                            Err(error) => Msg::Failed(id, error.to_string()),
+                        });
+
+                idb::clear(db, PRODUCTS_STORE, callback);
+            }
+            CatalogueRequest::GetSize => {
+                let callback =
+                    self.link
+                        .callback(move |result: Result<u32, IdbError>| match result {
+                            Ok(size) => Msg::Respond(id, CatalogueResponse::Size(size)),
+                            Err(error) => Msg::Failed(id, error.to_string()),
+                        });
+
+                idb::count(db, PRODUCTS_STORE, callback);
+            }
+        }
+    }
+}
+
+impl CatalogueAgent {
+    /// Asks sfi-server about a product (if enabled in the settings)
+    fn fetch(&mut self, id: HandlerId, ean: String) {
+        if !config::catalogue_server_lookup() {
+            return self.link.respond(id, CatalogueResponse::Product(ean, None));
+        }
+
+        let fetched_ean = ean.clone();
+        let callback = self
+            .link
+            .callback(move |result| Msg::Fetched(id, fetched_ean.clone(), result));
+
+        match ApiClient::configured().product(&ean, callback) {
+            Ok(task) => self.fetch_tasks.push(task),
+            Err(error) => {
+                log::warn!("Cannot look up {} on the server: {}", ean, error);
+                self.link.respond(id, CatalogueResponse::Product(ean, None));
+            }
+        }
+    }
+}
+
+/// Parses products from lines of `ean, name, brand, category, shelf life in days`, separated by tabs
+///
+/// Only the EAN and the name are required. Returns the products and the number of skipped lines.
+fn parse_tsv(tsv: &str) -> (Vec<Product>, usize) {
+    let mut products = vec![];
+    let mut skipped = 0;
+
+    for line in tsv.lines().filter(|line| !line.trim().is_empty()) {
+        let mut columns = line.split('\t').map(str::trim);
+        let optional = |column: Option<&str>| column.filter(|c| !c.is_empty()).map(String::from);
+
+        let ean = columns.next().map(ean::normalize);
+        let name = optional(columns.next());
+
+        match (ean, name) {
+            (Some(Ok(ean)), Some(name)) => products.push(Product {
+                ean,
+                name,
+                brand: optional(columns.next()),
+                category: optional(columns.next()),
+                shelf_life_days: columns.next().and_then(|days| days.parse().ok()),
+            }),
+            // Header lines and invalid EANs end up here
+            _ => skipped += 1,
+        }
+    }
+
+    (products, skipped)
+}
@@ -0,0 +1,151 @@
+use super::validation;
+use std::fmt;
+use yew::{
+    format::Text,
+    services::{storage::Area, StorageService},
+    utils,
+};
+
+/// The base URL of sfi-server used when nothing else is configured
+const DEFAULT_API_BASE_URL: &'static str = "http://localhost:8080";
+
+/// The base URL of sfi-server set at build time (e.g. `SFI_API_BASE_URL=https://... trunk build`)
+const BUILD_TIME_API_BASE_URL: Option<&'static str> = option_env!("SFI_API_BASE_URL");
+
+/// The name of the `<meta>` tag in `index.html` which may contain the base URL of sfi-server
+const API_BASE_URL_META_NAME: &'static str = "sfi-api-base-url";
+
+/// The localStorage key of the base URL of sfi-server set by the user
+const API_BASE_URL_KEY: &'static str = "sfi.config.api_base_url";
+
+/// The number of days within which units are considered to be expiring soon by default
+const DEFAULT_EXPIRY_WINDOW_DAYS: i64 = 7;
+
+/// The localStorage key of the expiry window set by the user
+const EXPIRY_WINDOW_KEY: &'static str = "sfi.config.expiry_window_days";
+
+/// The localStorage key of whether unknown EANs are looked up on sfi-server
+const CATALOGUE_SERVER_LOOKUP_KEY: &'static str = "sfi.config.catalogue_server_lookup";
+
+/// Why a setting couldn't be saved
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// localStorage cannot be used (e.g. because it's disabled), so nothing can be saved
+    Storage,
+
+    /// The base URL of sfi-server isn't valid, for the given reason
+    InvalidUrl(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Storage => write!(f, "This browser doesn't allow storing settings"),
+            ConfigError::InvalidUrl(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Returns the base URL of sfi-server (without a trailing slash)
+///
+/// The value set by the user in the settings takes precedence over the `<meta>` tag
+/// in `index.html`, which in turn takes precedence over the build-time configuration.
+pub fn api_base_url() -> String {
+    let url = user_api_base_url()
+        .or_else(meta_api_base_url)
+        .or_else(|| BUILD_TIME_API_BASE_URL.map(String::from))
+        .unwrap_or_else(|| DEFAULT_API_BASE_URL.to_string());
+
+    url.trim_end_matches('/').to_string()
+}
+
+/// Returns the base URL of sfi-server set by the user, if any
+pub fn user_api_base_url() -> Option<String> {
+    let storage = StorageService::new(Area::Local).ok()?;
+    let url: Text = storage.restore(API_BASE_URL_KEY);
+
+    url.ok().filter(|url| !url.trim().is_empty())
+}
+
+/// Sets (or with `None`, resets) the base URL of sfi-server used by this device
+pub fn set_user_api_base_url(url: Option<String>) -> Result<(), ConfigError> {
+    let url = url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+
+    if let Some(url) = &url {
+        validation::validate_url(url).map_err(ConfigError::InvalidUrl)?;
+    }
+
+    let mut storage = local_storage()?;
+
+    match url {
+        Some(url) => {
+            let url: Text = Ok(url);
+            storage.store(API_BASE_URL_KEY, url)
+        }
+        None => storage.remove(API_BASE_URL_KEY),
+    }
+
+    Ok(())
+}
+
+/// Returns the base URL of sfi-server as declared by `<meta name="sfi-api-base-url">`
+fn meta_api_base_url() -> Option<String> {
+    let selector = format!("meta[name=\"{}\"]", API_BASE_URL_META_NAME);
+
+    utils::document()
+        .query_selector(&selector)
+        .ok()
+        .flatten()
+        .and_then(|meta| meta.get_attribute("content"))
+        .filter(|url| !url.trim().is_empty())
+}
+
+/// Returns the number of days within which units are listed as expiring soon
+pub fn expiry_window_days() -> i64 {
+    StorageService::new(Area::Local)
+        .ok()
+        .and_then(|storage| {
+            let days: Text = storage.restore(EXPIRY_WINDOW_KEY);
+            days.ok()
+        })
+        .and_then(|days| days.parse().ok())
+        .filter(|days: &i64| *days >= 0)
+        .unwrap_or(DEFAULT_EXPIRY_WINDOW_DAYS)
+}
+
+/// Sets the number of days within which units are listed as expiring soon
+pub fn set_expiry_window_days(days: i64) -> Result<(), ConfigError> {
+    let days: Text = Ok(days.to_string());
+    local_storage()?.store(EXPIRY_WINDOW_KEY, days);
+
+    Ok(())
+}
+
+/// Returns whether EANs missing from the local catalogue are looked up on sfi-server
+pub fn catalogue_server_lookup() -> bool {
+    StorageService::new(Area::Local)
+        .ok()
+        .and_then(|storage| {
+            let enabled: Text = storage.restore(CATALOGUE_SERVER_LOOKUP_KEY);
+            enabled.ok()
+        })
+        .map_or(true, |enabled| enabled != "false")
+}
+
+/// Sets whether EANs missing from the local catalogue are looked up on sfi-server
+pub fn set_catalogue_server_lookup(enabled: bool) -> Result<(), ConfigError> {
+    let enabled: Text = Ok(enabled.to_string());
+    local_storage()?.store(CATALOGUE_SERVER_LOOKUP_KEY, enabled);
+
+    Ok(())
+}
+
+/// Gets a reference to localStorage, logging why it cannot be used
+fn local_storage() -> Result<StorageService, ConfigError> {
+    StorageService::new(Area::Local).map_err(|error| {
+        log::error!("Cannot use localStorage: {}", error);
+        ConfigError::Storage
+    })
+}
@@ -1,24 +1,155 @@
-use crate::components::login::AuthState;
+use crate::{
+    components::login::AuthState,
+    types::{
+        Action, ConsumptionKind, ExpiringUnit, Membership, Role, SearchQuery, SearchResult,
+        UnitDetails,
+    },
+};
 
-use super::auth::{AuthAgent, AuthAgentRequest};
-use anyhow::anyhow;
+use super::{
+    api::{ApiClient, ApiError},
+    auth::{self, AuthAgent},
+    ean::{self, EanError},
+    events::{Conflict, Entity, EventLog, InventoryRecord, ItemRecord, LoggedEvent, UnitRecord},
+    storage::{EventStorage, StorageError},
+    sync::{PullResponse, PushResponse, SyncState, SYNC_INTERVAL},
+    tabs::TabChannel,
+};
 use serde::{Deserialize, Serialize};
-use sfi_core::core::{Inventory, Item};
+use sfi_core::core::{Inventory, Item, Unit};
 use std::{
-    collections::HashSet,
-    ops::DerefMut,
+    collections::{HashMap, HashSet},
+    fmt,
     rc::Rc,
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 use uuid::Uuid;
 use yew::{
-    format::Json,
-    services::{storage::Area, StorageService},
+    format::{Json, Text},
+    services::{
+        fetch::FetchTask, interval::IntervalTask, storage::Area, IntervalService, StorageService,
+    },
     worker::*,
+    Callback,
 };
 
-const EVENT_STORE_KEY: &'static str = "sfi.events.store";
-const SIMPLE_STORE_KEY: &'static str = "sfi.simple_data.store";
+const SYNC_STATE_KEY: &'static str = "sfi.sync.state";
+
+/// The user last logged in on this device, whose permissions apply while offline
+
+/// The name of the channel over which the data agents of all tabs talk to each other
+const TAB_CHANNEL_NAME: &'static str = "sfi.data";
+
+/// Identifies a request sent through a [`DataBridge`], and is repeated in the reply to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(usize);
+
+impl RequestId {
+    /// The ID of requests sent by dispatchers, which are never replied to
+    const DISPATCHED: RequestId = RequestId(0);
+}
+
+/// What the data agent receives from components
+#[derive(Debug)]
+pub enum DataAgentInput {
+    /// A request, which is only answered to the component which sent it
+    Request(RequestId, DataAgentRequest),
+
+    /// Sends notifications about changes of the topic until unsubscribed (or disconnected)
+    Subscribe(Topic),
+    Unsubscribe(Topic),
+}
+
+impl From<DataAgentRequest> for DataAgentInput {
+    /// Wraps requests of dispatchers, which cannot receive replies anyway
+    fn from(request: DataAgentRequest) -> Self {
+        DataAgentInput::Request(RequestId::DISPATCHED, request)
+    }
+}
+
+/// What the data agent sends to components
+#[derive(Debug)]
+pub enum DataAgentOutput {
+    /// The reply to the request with this ID
+    Reply(RequestId, DataAgentResponse),
+    Notification(DataNotification),
+}
+
+/// The data components can subscribe to, instead of asking for it again and again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    /// The accessible inventories, including their items and units
+    Inventories,
+
+    /// An inventory, including its items and units
+    Inventory(Uuid),
+
+    /// The concurrent edits which need to be resolved by the user
+    Conflicts,
+}
+
+/// A change of the data, sent to the subscribers of the affected topic
+#[derive(Debug, Clone)]
+pub enum DataNotification {
+    /// Every inventory has been replaced, e.g. by a synchronisation
+    Inventories(Vec<Arc<RwLock<Inventory>>>),
+
+    CreatedInventory(Arc<RwLock<Inventory>>),
+
+    /// The inventory has been edited, or replaced so previously obtained references are stale
+    UpdatedInventory(Arc<RwLock<Inventory>>),
+    DeletedInventory(Uuid),
+
+    CreatedItem(Arc<RwLock<Item>>),
+    UpdatedItem(Arc<RwLock<Item>>),
+    DeletedItem {
+        inventory_uuid: Uuid,
+        item_uuid: Uuid,
+    },
+
+    /// A unit has been created, edited or (partially) consumed
+    ChangedUnit(Unit, UnitDetails),
+    DeletedUnit {
+        item_uuid: Uuid,
+        unit_uuid: Uuid,
+    },
+
+    Conflicts(Vec<Conflict>),
+}
+
+/// A bridge to the data agent, which numbers the requests sent through it
+pub struct DataBridge {
+    bridge: Box<dyn Bridge<DataAgent>>,
+    next_id: usize,
+}
+
+impl DataBridge {
+    pub fn new(callback: Callback<DataAgentOutput>) -> Self {
+        Self {
+            bridge: DataAgent::bridge(callback),
+
+            // Skip the ID of dispatched requests
+            next_id: 1,
+        }
+    }
+
+    /// Sends a request, whose reply will carry the returned ID
+    pub fn send(&mut self, request: DataAgentRequest) -> RequestId {
+        let id = RequestId(self.next_id);
+        self.next_id += 1;
+
+        self.bridge.send(DataAgentInput::Request(id, request));
+        id
+    }
+
+    pub fn subscribe(&mut self, topic: Topic) {
+        self.bridge.send(DataAgentInput::Subscribe(topic));
+    }
+
+    pub fn unsubscribe(&mut self, topic: Topic) {
+        self.bridge.send(DataAgentInput::Unsubscribe(topic));
+    }
+}
 
 #[derive(Debug)]
 pub enum DataAgentRequest {
@@ -42,10 +173,144 @@ pub enum DataAgentRequest {
         name: String,
         ean: Option<String>,
     },
-    CreateItem(Uuid, String, Option<String>),
+    CreateItem {
+        inventory_uuid: Uuid,
+        name: String,
+        ean: Option<String>,
+
+        /// Create the item even if another item of the inventory has the same EAN
+        allow_duplicate: bool,
+    },
     DeleteAllData,
     GetItem(Uuid, Uuid),
     DeleteItem(Arc<RwLock<Item>>),
+
+    /// Moves every unit of the source item into the target item, then deletes the source item
+    MergeItems {
+        source: Arc<RwLock<Item>>,
+        target: Arc<RwLock<Item>>,
+    },
+
+    GetUnit(Uuid, Uuid, Uuid),
+    GetUnitDetails(Uuid, Uuid),
+    CreateUnit(Uuid, Uuid, String, UnitDetails),
+    UpdateUnit {
+        target: Arc<RwLock<Unit>>,
+        name: String,
+        details: UnitDetails,
+    },
+    DeleteUnit(Arc<RwLock<Unit>>),
+
+    /// Records that some (`None` for all that remains) of a unit was eaten or thrown away
+    ConsumeUnit {
+        target: Arc<RwLock<Unit>>,
+        amount: Option<f64>,
+        kind: ConsumptionKind,
+    },
+
+    /// Requests every accessible unit expiring within the given number of days
+    GetExpiringUnits(i64),
+
+    Search(SearchQuery),
+
+    /// Requests the role of the current user in every accessible inventory
+    GetRoles,
+
+    /// Synchronises with sfi-server right away (e.g. after joining an inventory)
+    Sync,
+
+    GetConflicts,
+    ResolveConflict {
+        conflict: Conflict,
+        resolution: Resolution,
+    },
+}
+
+impl DataAgentRequest {
+    /// Whether the request changes any data, which needs to be saved
+    fn is_change(&self) -> bool {
+        match self {
+            DataAgentRequest::MakeDebugInventory
+            | DataAgentRequest::CreateInventory(_)
+            | DataAgentRequest::UpdateInventory { .. }
+            | DataAgentRequest::DeleteInventory(_)
+            | DataAgentRequest::UpdateItem { .. }
+            | DataAgentRequest::CreateItem { .. }
+            | DataAgentRequest::DeleteAllData
+            | DataAgentRequest::DeleteItem(_)
+            | DataAgentRequest::MergeItems { .. }
+            | DataAgentRequest::CreateUnit(..)
+            | DataAgentRequest::UpdateUnit { .. }
+            | DataAgentRequest::DeleteUnit(_)
+            | DataAgentRequest::ConsumeUnit { .. }
+            | DataAgentRequest::ResolveConflict { .. } => true,
+            DataAgentRequest::GetInventories
+            | DataAgentRequest::GetInventory(_)
+            | DataAgentRequest::GetItem(..)
+            | DataAgentRequest::GetUnit(..)
+            | DataAgentRequest::GetUnitDetails(..)
+            | DataAgentRequest::GetExpiringUnits(_)
+            | DataAgentRequest::Search(_)
+            | DataAgentRequest::GetRoles
+            | DataAgentRequest::Sync
+            | DataAgentRequest::GetConflicts => false,
+        }
+    }
+}
+
+/// How a conflict between concurrent updates should be resolved
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    /// Use the given version, e.g. assembled field-by-field from the conflicting versions
+    Pick(Entity),
+
+    /// Keep the first version, and turn every other version into a new entity
+    KeepBoth,
+}
+
+/// Why the data agent couldn't handle a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataError {
+    /// The inventory doesn't exist (anymore), e.g. because a stale route was opened
+    UnknownInventory(Uuid),
+    UnknownItem(Uuid),
+    UnknownUnit(Uuid),
+
+    /// An entity cannot be locked, because an earlier panic happened while it was locked
+    Poisoned,
+
+    /// The current user isn't allowed to do what was requested
+    PermissionDenied(Action),
+
+    /// Only logged in users can do what was requested (e.g. create an inventory)
+    NotLoggedIn,
+
+    /// The version chosen to resolve a conflict is one of another entity
+    WrongEntity(Uuid),
+
+    /// Neither IndexedDB nor localStorage can be used, so nothing can be changed
+    Storage,
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::UnknownInventory(_) => write!(f, "This inventory doesn't exist (anymore)"),
+            DataError::UnknownItem(_) => write!(f, "This item doesn't exist (anymore)"),
+            DataError::UnknownUnit(_) => write!(f, "This unit doesn't exist (anymore)"),
+            DataError::Poisoned => write!(
+                f,
+                "The local data is in an inconsistent state, please reload the page"
+            ),
+            DataError::PermissionDenied(action) => write!(f, "{}", action),
+            DataError::NotLoggedIn => write!(f, "Please log in first"),
+            DataError::WrongEntity(_) => write!(f, "This version belongs to something else"),
+            DataError::Storage => write!(
+                f,
+                "This browser doesn't allow storing data, so changes cannot be saved"
+            ),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -53,167 +318,449 @@ pub enum DataAgentResponse {
     Inventories(Vec<Arc<RwLock<Inventory>>>),
     NewInventoryUuid(Uuid),
     Inventory(Arc<RwLock<Inventory>>),
-    InvalidInventoryUuid,
     UpdatedInventory(Arc<RwLock<Inventory>>),
     DeletedInventory(Uuid),
 
     NewItemUuid(Uuid),
     Item(Arc<RwLock<Item>>),
     UpdatedItem,
+
+    /// The EAN of an item to be created or updated is invalid
+    InvalidEan(EanError),
+
+    /// The inventory already contains this item with the same EAN, so nothing was created
+    DuplicateEan(Arc<RwLock<Item>>),
     DeletedItem(Uuid),
+
+    /// The units of another item were moved into the item with this UUID
+    MergedItems(Uuid),
+
+    NewUnitUuid(Uuid),
+    Unit(Arc<RwLock<Unit>>, UnitDetails),
+    UnitDetails(HashMap<Uuid, UnitDetails>),
+    UpdatedUnit,
+    DeletedUnit(Uuid),
+    ConsumedUnit(Uuid, UnitDetails),
+    ExpiringUnits(Vec<ExpiringUnit>),
+    SearchResults(Vec<SearchResult>),
+
+    /// The roles of the current user, by inventory UUID
+    Roles(HashMap<Uuid, Role>),
+
+    /// The request couldn't be handled, so nothing was changed (unless saving failed)
+    Error(DataError),
+
+    Conflicts(Vec<Conflict>),
+    ResolvedConflict(Uuid),
+}
+
+/// What the data agent of one tab tells those of the other tabs
+#[derive(Serialize, Deserialize)]
+pub enum TabMessage {
+    /// These events have been saved
+    Saved(Vec<LoggedEvent>),
+
+    /// All data has been deleted
+    Cleared,
 }
 
 pub enum Msg {
     NewAuthState(Rc<AuthState>),
+    OtherTab(TabMessage),
+
+    // Storage of the event log
+    Opened(Result<EventStorage, StorageError>),
+    Loaded(Result<EventLog, StorageError>),
+    Saved(Result<Vec<LoggedEvent>, StorageError>),
+
+    // Synchronisation with sfi-server
+    Sync,
+    Pushed(PushResponse),
+    Pulled(PullResponse),
+    SyncFailed(ApiError),
 }
 
 pub struct DataAgent {
     link: AgentLink<DataAgent>,
-    subscribers: HashSet<HandlerId>,
-    local_storage: StorageService,
+    subscriptions: HashMap<Topic, HashSet<HandlerId>>,
+    local_storage: Option<StorageService>,
     auth_state: Rc<AuthState>,
 
+    /// The user whose data is kept, which outlasts the authentication state while probing
+    user: Option<Uuid>,
+    event_log: EventLog,
+
+    /// Where the event log is saved, once opened
+    storage: Option<EventStorage>,
+
+    /// Whether the event log has been loaded, before which requests are queued
+    loaded: bool,
+
+    /// Whether the data of the previous user needs to be deleted once loaded
+    delete_on_load: bool,
+    pending: Vec<(HandlerId, RequestId, DataAgentRequest)>,
+
     inventories: Vec<Arc<RwLock<Inventory>>>,
+    unit_details: HashMap<Uuid, UnitDetails>,
     auth_bridge: Box<dyn Bridge<AuthAgent>>,
+    tab_channel: Option<TabChannel>,
+
+    // Synchronisation with sfi-server
+    sync_state: SyncState,
+    sync_interval: Option<IntervalTask>,
+    sync_task: Option<FetchTask>,
 }
 
 impl Agent for DataAgent {
     type Reach = Context<Self>;
     type Message = Msg;
-    type Input = DataAgentRequest;
-    type Output = DataAgentResponse;
+    type Input = DataAgentInput;
+    type Output = DataAgentOutput;
 
     fn create(link: AgentLink<Self>) -> Self {
-        // Get a reference to localStorage
-        let local_storage = StorageService::new(Area::Local).expect("Cannot use localStorage");
-
-        // Load the event store from localStorage
-        let store = {
-            if let Json(Ok(store)) = local_storage.restore(SIMPLE_STORE_KEY) {
-                // Load the event store from localStorage
-                store
+        // Get a reference to localStorage, without which the synchronisation progress
+        // only lasts for this session
+        let local_storage = match StorageService::new(Area::Local) {
+            Ok(local_storage) => Some(local_storage),
+            Err(error) => {
+                log::error!("Cannot use localStorage: {}", error);
+                None
+            }
+        };
+
+        // Load the event log in the background, the state is rebuilt once it is loaded
+        EventStorage::open(link.callback(Msg::Opened));
+
+        // Load the progress of the synchronisation with sfi-server
+        let sync_state = {
+            if let Some(Json(Ok(sync_state))) = local_storage
+                .as_ref()
+                .map(|storage| storage.restore(SYNC_STATE_KEY))
+            {
+                sync_state
             } else {
-                // If no such entry exists, create a new one
-                vec![]
+                SyncState::default()
             }
         };
 
+        // The stored data belongs to the user last logged in, until the auth agent tells otherwise
+        let user = auth::known_user().map(|user_info| user_info.uuid);
+
         // Initiate a bridge to the auth agent
-        let mut auth_bridge = AuthAgent::bridge(link.callback(Msg::NewAuthState));
+        let auth_bridge = AuthAgent::subscribe(link.callback(Msg::NewAuthState));
 
-        // Request the current authentication status
-        // auth_bridge.send(AuthAgentRequest::GetAuthStatus);
+        // Without a channel, changes of other tabs only show up after a reload
+        let tab_channel = TabChannel::open(TAB_CHANNEL_NAME, link.callback(Msg::OtherTab));
 
         Self {
-            subscribers: HashSet::new(),
-            inventories: store,
+            subscriptions: HashMap::new(),
+            inventories: vec![],
+            unit_details: HashMap::new(),
+            event_log: EventLog::default(),
+            storage: None,
+            loaded: false,
+            delete_on_load: false,
+            pending: vec![],
             local_storage,
             auth_state: Rc::new(AuthState::Initial),
+            user,
             auth_bridge,
+            tab_channel,
             link,
+            sync_state,
+            sync_interval: None,
+            sync_task: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         match msg {
-            Msg::NewAuthState(auth_state) => self.auth_state = auth_state,
-        };
-    }
+            Msg::NewAuthState(auth_state) => {
+                self.auth_state = auth_state;
 
-    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
-        match msg {
-            DataAgentRequest::GetInventories => {
-                // TODO remove these clones
+                // While probing or logging out, the previous user still applies
+                if !matches!(
+                    *self.auth_state,
+                    AuthState::Probing(_) | AuthState::LoggingOut
+                ) {
+                    let user = self.auth_state.user().map(|user_info| user_info.uuid);
 
-                for sub in self.subscribers.iter() {
-                    self.link.respond(
-                        *sub,
-                        DataAgentResponse::Inventories(self.inventories.clone()),
-                    )
-                }
-            }
-            DataAgentRequest::MakeDebugInventory => {
-                let res = if let AuthState::LoggedIn(user_info) = self.auth_state.as_ref() {
-                    let inv = Inventory::new("debug inv".to_string(), user_info.uuid);
-                    let uuid = inv.uuid;
-                    self.inventories.push(Arc::new(RwLock::new(inv)));
-                    uuid
-                } else {
-                    let inv = Inventory::new("debug inv".to_string(), Uuid::new_v4());
-                    let uuid = inv.uuid;
-                    self.inventories.push(Arc::new(RwLock::new(inv)));
-                    uuid
-                };
+                    if user != self.user {
+                        let previous = std::mem::replace(&mut self.user, user);
 
-                self.persist_data();
+                        // Nobody else may see or change the data of the previous user
+                        if previous.is_some() {
+                            self.delete_previous_user_data();
+                        } else if self.loaded {
+                            // The roles of the new user differ
+                            self.publish_all();
+                        }
+                    }
+                }
 
-                self.link
-                    .respond(id, DataAgentResponse::NewInventoryUuid(res));
+                // Only synchronise while logged in
+                if self.auth_state.user().is_some() {
+                    if self.sync_interval.is_none() {
+                        self.sync_interval = Some(IntervalService::spawn(
+                            SYNC_INTERVAL,
+                            self.link.callback(|_| Msg::Sync),
+                        ));
 
-                for sub in self.subscribers.iter() {
-                    self.link.respond(
-                        *sub,
-                        DataAgentResponse::Inventories(self.inventories.to_vec().clone()),
-                    )
+                        self.link.send_message(Msg::Sync);
+                    }
+                } else {
+                    self.sync_interval = None;
+                    self.sync_task = None;
                 }
             }
-            DataAgentRequest::CreateInventory(name) => {
-                if let AuthState::LoggedIn(user_info) = self.auth_state.as_ref() {
-                    let inv = Inventory::new(name, user_info.uuid);
-                    let uuid = inv.uuid;
-                    self.inventories.push(Arc::new(RwLock::new(inv)));
+            Msg::OtherTab(TabMessage::Saved(events)) => {
+                // Before loading, the saved events will be loaded anyway
+                if self.loaded && self.event_log.merge_local(events) > 0 {
+                    self.rebuild();
+                    self.publish_all();
+                }
+            }
+            Msg::OtherTab(TabMessage::Cleared) => {
+                self.clear_data();
+                self.publish_all();
+            }
+            Msg::Opened(Ok(storage)) => {
+                storage.load(self.link.callback(Msg::Loaded));
+                self.storage = Some(storage);
+            }
+            Msg::Opened(Err(error)) => {
+                log::error!(
+                    "Cannot store any data, changes only last for this session: {}",
+                    error
+                );
+                self.finish_loading();
+            }
+            Msg::Loaded(result) => {
+                match result {
+                    Ok(event_log) => self.event_log = event_log,
+                    Err(error) => log::error!("Cannot load the stored data: {}", error),
+                }
 
-                    self.persist_data();
+                // Save the data moved from localStorage right away
+                if self.event_log.has_changes() {
+                    if let Err(error) = self.save_events() {
+                        log::warn!("Cannot save the loaded events: {}", error);
+                    }
+                }
 
-                    self.link
-                        .respond(id, DataAgentResponse::NewInventoryUuid(uuid));
+                self.finish_loading();
+            }
+            Msg::Saved(Ok(events)) => {
+                self.event_log.mark_saved(&events);
 
-                    for sub in self.subscribers.iter() {
-                        self.link.respond(
-                            *sub,
-                            DataAgentResponse::Inventories(self.inventories.to_vec().clone()),
-                        )
-                    }
+                // Only move the stored progress past events which are saved as well,
+                // otherwise they would never be downloaded again
+                if !self.event_log.has_changes() {
+                    self.store_sync_state();
+                }
+
+                if !events.is_empty() {
+                    self.post_to_tabs(TabMessage::Saved(events));
                 }
             }
-            DataAgentRequest::DeleteAllData => {
-                self.inventories = vec![];
+            Msg::Saved(Err(error)) => {
+                // The events remain changed, so saving them is retried with the next change
+                log::warn!("Cannot save the changed events: {}", error);
+            }
+            Msg::Sync => {
+                // Don't start another synchronisation while one is running,
+                // or before the stored events are known
+                if self.sync_task.is_none() && self.sync_interval.is_some() && self.loaded {
+                    self.start_sync();
+                }
+            }
+            Msg::Pushed(response) => {
+                self.sync_task = None;
+
+                self.event_log.mark_synced(&response.accepted);
+
                 self.persist_data();
 
-                let res = (&self.inventories).to_vec();
+                // Continue with fetching the remote changes
+                self.start_pull();
+            }
+            Msg::Pulled(response) => {
+                self.sync_task = None;
+
+                let merged = self.event_log.merge(response.events);
+                self.sync_state.cursor = Some(response.cursor);
+
+                if merged > 0 {
+                    // The progress is stored once the downloaded events are saved
+                    if let Err(error) = self.save_events() {
+                        log::warn!("Cannot save the downloaded events: {}", error);
+                    }
 
-                for sub in self.subscribers.iter() {
-                    self.link
-                        .respond(*sub, DataAgentResponse::Inventories(res.clone()))
+                    // Rebuild the state and inform every subscriber about it,
+                    // including concurrent edits the user needs to know about
+                    self.rebuild();
+                    self.publish_all();
+                } else if !self.event_log.has_changes() {
+                    self.store_sync_state();
+                }
+
+                // Catch up in batches after having been offline for a while
+                if response.has_more {
+                    self.start_pull();
                 }
             }
-            DataAgentRequest::GetInventory(inv_uuid) => {
-                let res = if let Some(inventory) = self.find_inv(inv_uuid) {
-                    DataAgentResponse::Inventory(inventory.clone())
+            Msg::SyncFailed(error) => {
+                // Keep the local events; they will be pushed on the next attempt
+                self.sync_task = None;
+                log::warn!("Cannot synchronise with sfi-server: {}", error);
+            }
+        };
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        match msg {
+            DataAgentInput::Request(request_id, request) => {
+                if self.loaded {
+                    self.reply(id, request_id, request);
                 } else {
-                    DataAgentResponse::InvalidInventoryUuid
-                };
+                    self.pending.push((id, request_id, request));
+                }
+            }
+            DataAgentInput::Subscribe(topic) => {
+                self.subscriptions.entry(topic).or_default().insert(id);
+            }
+            DataAgentInput::Unsubscribe(topic) => {
+                if let Some(subscribers) = self.subscriptions.get_mut(&topic) {
+                    subscribers.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        for subscribers in self.subscriptions.values_mut() {
+            subscribers.remove(&id);
+        }
+    }
+}
+
+impl DataAgent {
+    /// Handles a request and replies to the requester (if needed)
+    fn reply(&mut self, id: HandlerId, request_id: RequestId, request: DataAgentRequest) {
+        let response = match self.handle_request(request) {
+            Ok(Some(response)) => response,
+            Ok(None) => return,
+
+            // Report failures to the requesting component instead of crashing the agent
+            Err(error) => {
+                log::warn!("Cannot handle a request: {}", error);
+                DataAgentResponse::Error(error)
+            }
+        };
+
+        // Dispatchers cannot receive the reply
+        if request_id != RequestId::DISPATCHED {
+            self.link
+                .respond(id, DataAgentOutput::Reply(request_id, response));
+        }
+    }
+
+    /// Rebuilds the state from the loaded event log and handles the queued requests
+    fn finish_loading(&mut self) {
+        self.loaded = true;
+
+        // The user changed while loading
+        if std::mem::take(&mut self.delete_on_load) {
+            self.delete_previous_user_data();
+        }
+
+        self.rebuild();
+        self.publish_all();
+
+        for (id, request_id, request) in std::mem::take(&mut self.pending) {
+            self.reply(id, request_id, request);
+        }
 
-                self.link.respond(id, res)
+        // Synchronise right away if the user logged in while loading
+        self.link.send_message(Msg::Sync);
+    }
+
+    /// Handles a request, returning the reply to the requester (if any)
+    fn handle_request(
+        &mut self,
+        msg: DataAgentRequest,
+    ) -> Result<Option<DataAgentResponse>, DataError> {
+        // Reject changes up front, rather than applying them without being able to save them
+        if msg.is_change() && self.storage.is_none() {
+            return Err(DataError::Storage);
+        }
+
+        let response = match msg {
+            DataAgentRequest::GetInventories => {
+                // TODO remove these clones
+                DataAgentResponse::Inventories(self.inventories.clone())
             }
-            DataAgentRequest::CreateItem(inventory_uuid, name, ean) => {
-                let res = {
-                    let item = Item::new(inventory_uuid, name, ean);
-                    let uuid = item.uuid;
+            DataAgentRequest::MakeDebugInventory => {
+                let owner = match self.auth_state.user() {
+                    Some(user_info) => user_info.uuid,
+                    None => Uuid::new_v4(),
+                };
 
-                    self.find_inv(inventory_uuid)
-                        .expect("No such inventory (cannot write)")
-                        .write()
-                        .expect("Cannot write inventory")
-                        .items
-                        .push(Arc::new(RwLock::new(item)));
+                let inv = Inventory::new("debug inv".to_string(), owner);
+                self.create_inventory(inv)?
+            }
+            DataAgentRequest::CreateInventory(name) => {
+                let owner = match self.auth_state.user() {
+                    Some(user_info) => user_info.uuid,
+                    None => return Err(DataError::NotLoggedIn),
+                };
 
-                    self.persist_data();
+                self.create_inventory(Inventory::new(name, owner))?
+            }
+            DataAgentRequest::DeleteAllData => {
+                self.delete_all_data();
 
-                    DataAgentResponse::NewItemUuid(uuid)
+                DataAgentResponse::Inventories(self.inventories.clone())
+            }
+            DataAgentRequest::GetInventory(inv_uuid) => {
+                DataAgentResponse::Inventory(self.find_inv(inv_uuid)?)
+            }
+            DataAgentRequest::CreateItem {
+                inventory_uuid,
+                name,
+                ean,
+                allow_duplicate,
+            } => {
+                let ean = match ean::normalize_optional(ean.as_deref()) {
+                    Ok(ean) => ean,
+                    Err(error) => return Ok(Some(DataAgentResponse::InvalidEan(error))),
                 };
 
-                self.link.respond(id, res)
+                self.check(inventory_uuid, Action::EditContents)?;
+
+                // Suggest adding a unit to the existing item instead
+                if !allow_duplicate {
+                    if let Some(ean) = ean.as_deref() {
+                        if let Some(existing) = self.find_item_by_ean(inventory_uuid, ean)? {
+                            return Ok(Some(DataAgentResponse::DuplicateEan(existing)));
+                        }
+                    }
+                }
+
+                let inventory = self.find_inv(inventory_uuid)?;
+
+                let item = Item::new(inventory_uuid, name, ean);
+                let uuid = item.uuid;
+
+                self.event_log.created(Entity::Item((&item).into()));
+
+                let item = Arc::new(RwLock::new(item));
+                write(&inventory)?.items.push(item.clone());
+
+                self.persist_data();
+                self.publish_change(inventory_uuid, DataNotification::CreatedItem(item));
+
+                DataAgentResponse::NewItemUuid(uuid)
             }
             DataAgentRequest::UpdateInventory {
                 target,
@@ -223,160 +770,968 @@ impl Agent for DataAgent {
                 writables,
                 readables,
             } => {
-                let res = if let Ok(mut inventory) = target.write() {
+                let target = self.current(&target)?;
+                let target_uuid = read(&target)?.uuid;
+
+                let membership = Membership {
+                    owner,
+                    admins: admins.clone(),
+                    writables: writables.clone(),
+                    readables: readables.clone(),
+                };
+
+                let required_actions = Self::required_actions(&*read(&target)?, &name, &membership);
+
+                for action in required_actions {
+                    self.check(target_uuid, action)?;
+                }
+
+                {
+                    let mut inventory = write(&target)?;
+
                     inventory.name = name;
                     inventory.owner = owner;
                     inventory.admins = admins;
                     inventory.writables = writables;
                     inventory.readables = readables;
 
-                    drop(inventory);
-
-                    self.persist_data();
+                    self.event_log
+                        .updated(Entity::Inventory((&*inventory).into()));
+                }
 
-                    DataAgentResponse::UpdatedInventory(target.clone())
-                } else {
-                    DataAgentResponse::InvalidInventoryUuid
-                };
+                self.persist_data();
+                self.publish_change(
+                    target_uuid,
+                    DataNotification::UpdatedInventory(target.clone()),
+                );
 
-                self.link.respond(id, res);
+                DataAgentResponse::UpdatedInventory(target)
             }
             DataAgentRequest::GetItem(inventory_uuid, item_uuid) => {
-                // let res = if let Some(item) = crate::find_item!(inventory_uuid, item_uuid) {
-                //     DataAgentResponse::Item(item.clone())
-                // } else {
-                //     DataAgentResponse::InvalidInventoryUuid
-                // };
-
-                let res = if let Some(item) = {
-                    self.inventories
-                        .iter()
-                        .find(|inv| {
-                            inv.read().expect("Cannot read inventory uuid").uuid == inventory_uuid
-                        })
-                        .expect("No such item")
-                        .read()
-                        .expect("Cannot read inventory")
-                        .items
-                        .iter()
-                        .find(|item| item.read().expect("Cannot read item").uuid == item_uuid)
-                } {
-                    DataAgentResponse::Item(item.clone())
-                } else {
-                    DataAgentResponse::InvalidInventoryUuid
-                };
-
-                self.link.respond(id, res)
+                DataAgentResponse::Item(self.find_item(inventory_uuid, item_uuid)?)
             }
             DataAgentRequest::UpdateItem { target, name, ean } => {
-                let res = if let Ok(mut item) = target.write() {
+                let ean = match ean::normalize_optional(ean.as_deref()) {
+                    Ok(ean) => ean,
+                    Err(error) => return Ok(Some(DataAgentResponse::InvalidEan(error))),
+                };
+
+                let target = self.current(&target)?;
+                self.check(read(&target)?.inventory_uuid, Action::EditContents)?;
+
+                {
+                    let mut item = write(&target)?;
+
                     item.name = name;
                     item.ean = ean;
 
-                    drop(item);
-
-                    self.persist_data();
+                    self.event_log.updated(Entity::Item((&*item).into()));
+                }
 
-                    DataAgentResponse::UpdatedItem
-                } else {
-                    // TODO Maybe replace this with InvalidItemUuid or something; notice: the error could still be the inventory UUID
-                    DataAgentResponse::InvalidInventoryUuid
-                };
+                self.persist_data();
+                self.publish_item_update(&target)?;
 
-                self.link.respond(id, res);
+                DataAgentResponse::UpdatedItem
             }
             DataAgentRequest::DeleteInventory(target) => {
-                let target_uuid = target
-                    .read()
-                    .expect("Cannot read inventory to be deleted")
-                    .uuid;
+                let target_uuid = read(&target)?.uuid;
 
-                let index = self
-                    .inventories
-                    .iter()
-                    .position(|i| i.read().expect("Cannot read inventory").uuid == target_uuid)
-                    .expect("No such inventory");
+                self.check(target_uuid, Action::DeleteInventory)?;
 
-                self.inventories.remove(index);
+                let inventory = self.find_inv(target_uuid)?;
+                self.inventories
+                    .retain(|other| !Arc::ptr_eq(other, &inventory));
 
+                self.record_inventory_deletion(&inventory)?;
                 self.persist_data();
+                self.publish_change(target_uuid, DataNotification::DeletedInventory(target_uuid));
 
-                let response = DataAgentResponse::DeletedInventory(target_uuid);
-                self.link.respond(id, response);
+                DataAgentResponse::DeletedInventory(target_uuid)
             }
             DataAgentRequest::DeleteItem(target) => {
-                let target = target.read().expect("Cannot read item to be deleted");
-
-                let mut inventory = self
-                    .inventories
-                    .iter()
-                    .find(|i| {
-                        i.read().expect("Cannot read inventory").uuid == target.inventory_uuid
-                    })
-                    .expect("Cannot get inventory as mutable")
-                    .write()
-                    .expect("Cannot write to inventory");
+                let (inventory_uuid, item_uuid) = {
+                    let target = read(&target)?;
+                    (target.inventory_uuid, target.uuid)
+                };
 
-                let item_index = inventory
-                    .items
-                    .iter()
-                    .position(|i| i.read().expect("Cannot read inventory").uuid == target.uuid)
-                    .expect("No such item");
+                self.check(inventory_uuid, Action::EditContents)?;
 
-                inventory.items.remove(item_index);
+                let item = self.find_item(inventory_uuid, item_uuid)?;
 
-                drop(inventory);
+                write(&self.find_inv(inventory_uuid)?)?
+                    .items
+                    .retain(|other| !Arc::ptr_eq(other, &item));
 
+                self.record_item_deletion(&item)?;
                 self.persist_data();
+                self.publish_change(
+                    inventory_uuid,
+                    DataNotification::DeletedItem {
+                        inventory_uuid,
+                        item_uuid,
+                    },
+                );
 
-                let response = DataAgentResponse::DeletedItem(target.uuid);
-                self.link.respond(id, response);
+                DataAgentResponse::DeletedItem(item_uuid)
             }
-        }
-    }
+            DataAgentRequest::MergeItems { source, target } => {
+                let source = self.current(&source)?;
+                let target = self.current(&target)?;
 
-    fn connected(&mut self, id: HandlerId) {
-        // FIelD `1` oF STrucT `yeW::AGENT::hANnlERiD` Is PRivATE
-        // PRiVATE fIELd rUsTC e0616
-        // if id.1 {}
-        if format!("{:?}", &id).contains("true") {
-            self.subscribers.insert(id);
-        }
-    }
+                self.check(read(&source)?.inventory_uuid, Action::EditContents)?;
+                self.check(read(&target)?.inventory_uuid, Action::EditContents)?;
 
-    fn disconnected(&mut self, id: HandlerId) {
-        self.subscribers.remove(&id);
-    }
-}
+                let (source_uuid, source_inventory_uuid) = {
+                    let source = read(&source)?;
+                    (source.uuid, source.inventory_uuid)
+                };
+                let target_uuid = read(&target)?.uuid;
 
-impl DataAgent {
-    fn persist_data(&mut self) -> () {
-        self.local_storage
-            .store(SIMPLE_STORE_KEY, Json(&self.inventories));
-    }
+                if source_uuid == target_uuid {
+                    return Ok(Some(DataAgentResponse::MergedItems(target_uuid)));
+                }
 
-    fn find_inv(&mut self, inv_uuid: Uuid) -> Option<&Arc<RwLock<Inventory>>> {
-        self.inventories
-            .iter()
-            .find(|inv| inv.read().expect("Cannot read inventory uuid").uuid == inv_uuid)
-    }
+                let units = std::mem::take(&mut write(&source)?.units);
 
-    // fn find_item(&mut self, inventory_uuid: Uuid, item_uuid: Uuid) -> Option<&Arc<RwLock<Item>>> {}
-}
+                for unit in units.iter() {
+                    let mut unit = write(unit)?;
+                    unit.item_uuid = target_uuid;
+
+                    let details = self.details_of(unit.uuid);
+                    self.event_log
+                        .updated(Entity::Unit(UnitRecord::new(&unit, details)));
+                }
 
-// #[macro_export]
-// macro_rules! find_item {
-//     ($inventory_uuid:ident,$item_uuid: ident) => {{
-//         let inv = self
-//             .inventories
-//             .iter()
-//             .find(|inv| inv.read().expect("Cannot read inventory uuid").uuid == inventory_uuid)
-//             .expect("No such item")
-//             .read()
-//             .expect("Cannot read inventory");
-
-//         inv.items
-//             .iter()
-//             .find(|item| item.read().expect("Cannot read item").uuid == item_uuid)
-//     }};
-// }
+                write(&target)?.units.extend(units);
+
+                // The source item is empty now and can be removed
+                write(&self.find_inv(source_inventory_uuid)?)?
+                    .items
+                    .retain(|item| !Arc::ptr_eq(item, &source));
+
+                self.record_item_deletion(&source)?;
+                self.persist_data();
+
+                self.publish_item_update(&target)?;
+                self.publish_change(
+                    source_inventory_uuid,
+                    DataNotification::DeletedItem {
+                        inventory_uuid: source_inventory_uuid,
+                        item_uuid: source_uuid,
+                    },
+                );
+
+                DataAgentResponse::MergedItems(target_uuid)
+            }
+            DataAgentRequest::GetUnit(inventory_uuid, item_uuid, unit_uuid) => {
+                let item = self.find_item(inventory_uuid, item_uuid)?;
+                let unit = Self::find_unit(&item, unit_uuid)?;
+                let details = self.details_of(unit_uuid);
+
+                DataAgentResponse::Unit(unit, details)
+            }
+            DataAgentRequest::GetUnitDetails(inventory_uuid, item_uuid) => {
+                let item = self.find_item(inventory_uuid, item_uuid)?;
+
+                let mut details = HashMap::new();
+
+                for unit in read(&item)?.units.iter() {
+                    let uuid = read(unit)?.uuid;
+                    details.insert(uuid, self.details_of(uuid));
+                }
+
+                DataAgentResponse::UnitDetails(details)
+            }
+            DataAgentRequest::CreateUnit(inventory_uuid, item_uuid, name, details) => {
+                self.check(inventory_uuid, Action::EditContents)?;
+
+                let item = self.find_item(inventory_uuid, item_uuid)?;
+
+                let unit = Unit::new(item_uuid, name);
+                let uuid = unit.uuid;
+
+                self.event_log
+                    .created(Entity::Unit(UnitRecord::new(&unit, details.clone())));
+                self.unit_details.insert(uuid, details.clone());
+
+                write(&item)?
+                    .units
+                    .push(Arc::new(RwLock::new(unit.clone())));
+
+                self.persist_data();
+                self.publish_change(inventory_uuid, DataNotification::ChangedUnit(unit, details));
+
+                DataAgentResponse::NewUnitUuid(uuid)
+            }
+            DataAgentRequest::UpdateUnit {
+                target,
+                name,
+                details,
+            } => {
+                let target = self.current(&target)?;
+                let inventory_uuid = self.check_unit_edit(&read(&target)?)?;
+
+                let unit = {
+                    let mut unit = write(&target)?;
+                    unit.name = name;
+
+                    self.event_log
+                        .updated(Entity::Unit(UnitRecord::new(&unit, details.clone())));
+                    self.unit_details.insert(unit.uuid, details.clone());
+
+                    unit.clone()
+                };
+
+                self.persist_data();
+                self.publish_change(inventory_uuid, DataNotification::ChangedUnit(unit, details));
+
+                DataAgentResponse::UpdatedUnit
+            }
+            DataAgentRequest::ConsumeUnit {
+                target,
+                amount,
+                kind,
+            } => {
+                let unit = read(&self.current(&target)?)?.clone();
+
+                let inventory_uuid = self.check_unit_edit(&unit)?;
+
+                let mut details = self.details_of(unit.uuid);
+                let unit_uuid = unit.uuid;
+
+                // Nothing changed (e.g. the unit was used up already), so nothing is recorded
+                if details.consume(amount, kind) == 0.0 {
+                    return Ok(Some(DataAgentResponse::ConsumedUnit(unit_uuid, details)));
+                }
+
+                self.event_log
+                    .updated(Entity::Unit(UnitRecord::new(&unit, details.clone())));
+                self.unit_details.insert(unit.uuid, details.clone());
+                self.persist_data();
+
+                let notification = DataNotification::ChangedUnit(unit, details.clone());
+                self.publish_change(inventory_uuid, notification);
+
+                DataAgentResponse::ConsumedUnit(unit_uuid, details)
+            }
+            DataAgentRequest::DeleteUnit(target) => {
+                let target = read(&target)?.clone();
+
+                let inventory_uuid = self.check_unit_edit(&target)?;
+
+                let item = self.find_item_of_unit(&target)?;
+                let unit = Self::find_unit(&item, target.uuid)?;
+
+                write(&item)?
+                    .units
+                    .retain(|other| !Arc::ptr_eq(other, &unit));
+
+                let details = self.unit_details.remove(&target.uuid).unwrap_or_default();
+                self.event_log
+                    .deleted(Entity::Unit(UnitRecord::new(&target, details)));
+                self.persist_data();
+                self.publish_change(
+                    inventory_uuid,
+                    DataNotification::DeletedUnit {
+                        item_uuid: target.item_uuid,
+                        unit_uuid: target.uuid,
+                    },
+                );
+
+                DataAgentResponse::DeletedUnit(target.uuid)
+            }
+            DataAgentRequest::GetExpiringUnits(days) => {
+                DataAgentResponse::ExpiringUnits(self.expiring_units(days)?)
+            }
+            DataAgentRequest::Search(query) => {
+                DataAgentResponse::SearchResults(self.search(&query)?)
+            }
+            DataAgentRequest::GetRoles => {
+                let mut roles = HashMap::new();
+
+                for inventory in self.inventories.iter() {
+                    let inventory = read(inventory)?;
+
+                    if let Some(role) = self.role_in(&inventory) {
+                        roles.insert(inventory.uuid, role);
+                    }
+                }
+
+                DataAgentResponse::Roles(roles)
+            }
+            DataAgentRequest::Sync => {
+                self.link.send_message(Msg::Sync);
+                return Ok(None);
+            }
+            DataAgentRequest::GetConflicts => {
+                DataAgentResponse::Conflicts(self.event_log.conflicts())
+            }
+            DataAgentRequest::ResolveConflict {
+                conflict,
+                resolution,
+            } => {
+                match resolution {
+                    Resolution::Pick(entity) => {
+                        if entity.uuid() != conflict.entity_uuid {
+                            return Err(DataError::WrongEntity(entity.uuid()));
+                        }
+
+                        self.check_resolution(&entity)?;
+                        self.event_log.resolved(entity, &conflict);
+                    }
+                    Resolution::KeepBoth => {
+                        for version in conflict.versions.iter() {
+                            self.check_resolution(&version.entity)?;
+                        }
+
+                        let mut versions = conflict.versions.iter();
+
+                        if let Some(first) = versions.next() {
+                            self.event_log.resolved(first.entity.clone(), &conflict);
+                        }
+
+                        // Every other version becomes a copy with a new identity
+                        for version in versions {
+                            self.record_copy(&version.entity)?;
+                        }
+                    }
+                }
+
+                // Rebuild the state from the event log, which now contains the resolution
+                self.rebuild();
+                self.persist_data();
+                self.publish_all();
+
+                DataAgentResponse::ResolvedConflict(conflict.entity_uuid)
+            }
+        };
+
+        Ok(Some(response))
+    }
+
+    /// Sends a notification to every subscriber of the topic
+    fn publish(&self, topic: Topic, notification: DataNotification) {
+        if let Some(subscribers) = self.subscriptions.get(&topic) {
+            for sub in subscribers.iter() {
+                self.link
+                    .respond(*sub, DataAgentOutput::Notification(notification.clone()));
+            }
+        }
+    }
+
+    /// Informs the subscribers of everything and of the affected inventory about a change
+    fn publish_change(&self, inventory_uuid: Uuid, notification: DataNotification) {
+        self.publish(Topic::Inventory(inventory_uuid), notification.clone());
+        self.publish(Topic::Inventories, notification);
+    }
+
+    fn publish_item_update(&self, item: &Arc<RwLock<Item>>) -> Result<(), DataError> {
+        let inventory_uuid = read(item)?.inventory_uuid;
+
+        self.publish_change(inventory_uuid, DataNotification::UpdatedItem(item.clone()));
+        Ok(())
+    }
+
+    /// Informs every subscriber after the whole state was rebuilt
+    fn publish_all(&self) {
+        self.publish(
+            Topic::Inventories,
+            DataNotification::Inventories(self.inventories.clone()),
+        );
+
+        for topic in self.subscriptions.keys() {
+            if let Topic::Inventory(uuid) = topic {
+                let notification = match self.find_inv(*uuid) {
+                    Ok(inventory) => DataNotification::UpdatedInventory(inventory),
+                    Err(_) => DataNotification::DeletedInventory(*uuid),
+                };
+
+                self.publish(*topic, notification);
+            }
+        }
+
+        self.publish(
+            Topic::Conflicts,
+            DataNotification::Conflicts(self.event_log.conflicts()),
+        );
+    }
+
+    fn create_inventory(&mut self, inventory: Inventory) -> Result<DataAgentResponse, DataError> {
+        let uuid = inventory.uuid;
+        self.event_log
+            .created(Entity::Inventory((&inventory).into()));
+
+        let inventory = Arc::new(RwLock::new(inventory));
+        self.inventories.push(inventory.clone());
+
+        self.persist_data();
+        self.publish_change(uuid, DataNotification::CreatedInventory(inventory));
+
+        Ok(DataAgentResponse::NewInventoryUuid(uuid))
+    }
+
+    /// Saves the changed events and pushes them to sfi-server as soon as possible
+    ///
+    /// Changes are only accepted while the storage is available, see `handle_request`.
+    fn persist_data(&mut self) {
+        if let Err(error) = self.save_events() {
+            log::warn!("Cannot save the changed events: {}", error);
+        }
+
+        self.link.send_message(Msg::Sync);
+    }
+
+    /// Saves the changed events, the other tabs are told about them once they are saved
+    fn save_events(&mut self) -> Result<(), DataError> {
+        match &mut self.storage {
+            Some(storage) => {
+                storage.save(&self.event_log, self.link.callback(Msg::Saved));
+                Ok(())
+            }
+            None => Err(DataError::Storage),
+        }
+    }
+
+    fn post_to_tabs(&self, message: TabMessage) {
+        if let Some(tab_channel) = &self.tab_channel {
+            tab_channel.post(&message);
+        }
+    }
+
+    /// Deletes all data, locally and in every other tab
+    fn delete_all_data(&mut self) {
+        self.clear_data();
+        self.store_sync_state();
+
+        if let Some(storage) = &mut self.storage {
+            storage.clear(Callback::from(|result: Result<(), StorageError>| {
+                if let Err(error) = result {
+                    log::warn!("Cannot delete the stored events: {}", error);
+                }
+            }));
+        }
+
+        self.post_to_tabs(TabMessage::Cleared);
+        self.publish_all();
+    }
+
+    /// Deletes the data of the user who logged out, or once the stored data is loaded
+    fn delete_previous_user_data(&mut self) {
+        // Stop merging events of the previous user
+        self.sync_task = None;
+
+        if !self.loaded {
+            self.delete_on_load = true;
+            return;
+        }
+
+        let unsynced = self.event_log.unsynced().len();
+        if unsynced > 0 {
+            log::warn!(
+                "Deleting {} events of the previous user which weren't synchronised",
+                unsynced
+            );
+        }
+
+        self.delete_all_data();
+    }
+
+    /// Forgets all data, including the progress of the synchronisation
+    fn clear_data(&mut self) {
+        self.inventories = vec![];
+        self.unit_details.clear();
+        self.event_log.clear();
+
+        // Download everything again on the next synchronisation
+        self.sync_state = SyncState::default();
+    }
+
+    fn store_sync_state(&mut self) {
+        // Without localStorage, the progress is only kept for this session
+        self.store(SYNC_STATE_KEY, Json(&self.sync_state).into())
+            .ok();
+    }
+
+    fn store(&mut self, key: &str, value: Text) -> Result<(), DataError> {
+        match &mut self.local_storage {
+            Some(local_storage) => Ok(local_storage.store(key, value)),
+            None => Err(DataError::Storage),
+        }
+    }
+
+    /// Determines the role of the current user in an inventory
+    fn role_in(&self, inventory: &Inventory) -> Option<Role> {
+        Membership::from(inventory).role_of(self.user?)
+    }
+
+    /// Checks whether the current user is allowed to perform an action on an inventory
+    fn check(&self, inventory_uuid: Uuid, action: Action) -> Result<(), DataError> {
+        let role = self.role_in(&*read(&self.find_inv(inventory_uuid)?)?);
+
+        if role.map_or(false, |role| role.allows(action)) {
+            Ok(())
+        } else {
+            Err(DataError::PermissionDenied(action))
+        }
+    }
+
+    /// Checks whether the current user may edit a unit, returning the UUID of its inventory
+    fn check_unit_edit(&self, unit: &Unit) -> Result<Uuid, DataError> {
+        let inventory_uuid = read(&self.find_item_of_unit(unit)?)?.inventory_uuid;
+
+        self.check(inventory_uuid, Action::EditContents)?;
+        Ok(inventory_uuid)
+    }
+
+    /// Checks whether the current user is allowed to apply a version of a conflicting entity
+    fn check_resolution(&self, entity: &Entity) -> Result<(), DataError> {
+        match entity {
+            Entity::Inventory(record) => {
+                let membership = Membership {
+                    owner: record.owner,
+                    admins: record.admins.clone(),
+                    writables: record.writables.clone(),
+                    readables: record.readables.clone(),
+                };
+                let inventory = self.find_inv(record.uuid)?;
+                let required_actions =
+                    Self::required_actions(&*read(&inventory)?, &record.name, &membership);
+
+                for action in required_actions {
+                    self.check(record.uuid, action)?;
+                }
+
+                Ok(())
+            }
+            Entity::Item(record) => self.check(record.inventory_uuid, Action::EditContents),
+            Entity::Unit(record) => {
+                let inventory_uuid =
+                    read(&self.find_item_anywhere(record.item_uuid)?)?.inventory_uuid;
+                self.check(inventory_uuid, Action::EditContents)
+            }
+        }
+    }
+
+    /// Lists what changing an inventory's name and members amounts to
+    fn required_actions(inventory: &Inventory, name: &str, membership: &Membership) -> Vec<Action> {
+        let current = Membership::from(inventory);
+        let mut actions = vec![];
+
+        if inventory.name != name {
+            actions.push(Action::EditInventory);
+        }
+
+        if current.owner != membership.owner {
+            actions.push(Action::TransferOwnership);
+        }
+
+        if current.admins != membership.admins
+            || current.writables != membership.writables
+            || current.readables != membership.readables
+        {
+            actions.push(Action::ManageMembers);
+        }
+
+        actions
+    }
+
+    /// Rebuilds the current state from the event log
+    fn rebuild(&mut self) {
+        let projection = self.event_log.project();
+
+        self.inventories = projection.inventories;
+        self.unit_details = projection.unit_details;
+    }
+
+    /// Records a copy of an entity with a new identity, together with copies of its contents
+    ///
+    /// The contents are those currently known, since they aren't part of the conflict.
+    fn record_copy(&mut self, entity: &Entity) -> Result<(), DataError> {
+        let copy_uuid = Uuid::new_v4();
+
+        match entity {
+            Entity::Inventory(record) => {
+                self.event_log.created(Entity::Inventory(InventoryRecord {
+                    uuid: copy_uuid,
+                    ..record.clone()
+                }));
+
+                // The original may have been deleted in the meantime
+                let items = match self.find_inv(record.uuid) {
+                    Ok(inventory) => read(&inventory)?.items.clone(),
+                    Err(_) => vec![],
+                };
+
+                for item in items {
+                    let item = read(&item)?.clone();
+                    let item_copy_uuid = Uuid::new_v4();
+
+                    self.event_log.created(Entity::Item(ItemRecord {
+                        uuid: item_copy_uuid,
+                        inventory_uuid: copy_uuid,
+                        ..(&item).into()
+                    }));
+                    self.record_unit_copies(&item, item_copy_uuid)?;
+                }
+            }
+            Entity::Item(record) => {
+                self.event_log.created(Entity::Item(ItemRecord {
+                    uuid: copy_uuid,
+                    ..record.clone()
+                }));
+
+                if let Ok(item) = self.find_item(record.inventory_uuid, record.uuid) {
+                    let item = read(&item)?.clone();
+                    self.record_unit_copies(&item, copy_uuid)?;
+                }
+            }
+            Entity::Unit(record) => {
+                self.event_log.created(Entity::Unit(UnitRecord {
+                    uuid: copy_uuid,
+                    ..record.clone()
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records copies of the units of an item, which belong to a copy of the item
+    fn record_unit_copies(&mut self, item: &Item, item_copy_uuid: Uuid) -> Result<(), DataError> {
+        for unit in item.units.iter() {
+            let unit = read(unit)?.clone();
+            let details = self.details_of(unit.uuid);
+
+            self.event_log.created(Entity::Unit(UnitRecord {
+                uuid: Uuid::new_v4(),
+                item_uuid: item_copy_uuid,
+                ..UnitRecord::new(&unit, details)
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn details_of(&self, unit_uuid: Uuid) -> UnitDetails {
+        self.unit_details
+            .get(&unit_uuid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Collects every unit expiring within the given number of days, sorted by date
+    fn expiring_units(&self, days: i64) -> Result<Vec<ExpiringUnit>, DataError> {
+        let mut expiring = vec![];
+
+        for inventory in self.inventories.iter() {
+            let inventory = read(inventory)?;
+
+            for item in inventory.items.iter() {
+                let item = read(item)?;
+
+                for unit in item.units.iter() {
+                    let unit = read(unit)?;
+                    let details = self.details_of(unit.uuid);
+
+                    // Units which were eaten or thrown away entirely cannot expire anymore
+                    if details.is_used_up() {
+                        continue;
+                    }
+
+                    match (details.expires, details.days_left()) {
+                        (Some(expires), Some(days_left)) if days_left <= days => {
+                            expiring.push(ExpiringUnit {
+                                inventory_uuid: inventory.uuid,
+                                inventory_name: inventory.name.clone(),
+                                item_uuid: item.uuid,
+                                item_name: item.name.clone(),
+                                unit: unit.clone(),
+                                details,
+                                expires,
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        expiring.sort_by_key(|expiring| expiring.expires);
+        Ok(expiring)
+    }
+
+    /// Finds the items (and units) matching the query across all inventories
+    fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>, DataError> {
+        let text = query.text.trim().to_lowercase();
+        let mut results = vec![];
+
+        for inventory in self.inventories.iter() {
+            let inventory = read(inventory)?;
+
+            if query.inventory.map_or(false, |uuid| uuid != inventory.uuid)
+                || query.owner.map_or(false, |uuid| uuid != inventory.owner)
+            {
+                continue;
+            }
+
+            for item in inventory.items.iter() {
+                let item = read(item)?;
+
+                let item_matches = item.name.to_lowercase().contains(&text)
+                    || item
+                        .ean
+                        .as_ref()
+                        .map_or(false, |ean| ean.to_lowercase().contains(&text));
+
+                let mut units = Vec::with_capacity(item.units.len());
+
+                for unit in item.units.iter() {
+                    let unit = read(unit)?.clone();
+                    let details = self.details_of(unit.uuid);
+                    units.push((unit, details));
+                }
+
+                let units: Vec<(Unit, UnitDetails)> = units
+                    .into_iter()
+                    .filter(|(_, details)| match query.expires_within {
+                        Some(days) => {
+                            !details.is_used_up()
+                                && details.days_left().map_or(false, |left| left <= days)
+                        }
+                        None => true,
+                    })
+                    .filter(|(unit, _)| item_matches || unit.name.to_lowercase().contains(&text))
+                    .collect();
+
+                // With an expiry filter, only items with matching units are of interest
+                let is_result = if query.expires_within.is_some() {
+                    !units.is_empty()
+                } else {
+                    item_matches || !units.is_empty()
+                };
+
+                if is_result {
+                    results.push(SearchResult {
+                        inventory_uuid: inventory.uuid,
+                        inventory_name: inventory.name.clone(),
+                        item: item.clone(),
+                        units,
+                    });
+                }
+            }
+        }
+
+        results.sort_by(|a, b| a.item.name.to_lowercase().cmp(&b.item.name.to_lowercase()));
+        Ok(results)
+    }
+
+    fn start_sync(&mut self) {
+        let unsynced = self.event_log.unsynced();
+
+        if unsynced.is_empty() {
+            self.start_pull();
+            return;
+        }
+
+        let callback = self
+            .link
+            .callback(|result: Result<PushResponse, ApiError>| match result {
+                Ok(response) => Msg::Pushed(response),
+                Err(error) => Msg::SyncFailed(error),
+            });
+
+        match ApiClient::configured().push_events(&unsynced, callback) {
+            Ok(task) => self.sync_task = Some(task),
+            Err(error) => self.link.send_message(Msg::SyncFailed(error)),
+        }
+    }
+
+    fn start_pull(&mut self) {
+        let callback = self
+            .link
+            .callback(|result: Result<PullResponse, ApiError>| match result {
+                Ok(response) => Msg::Pulled(response),
+                Err(error) => Msg::SyncFailed(error),
+            });
+
+        match ApiClient::configured().pull_events(&self.sync_state.cursor, callback) {
+            Ok(task) => self.sync_task = Some(task),
+            Err(error) => self.link.send_message(Msg::SyncFailed(error)),
+        }
+    }
+
+    /// Records the deletion of an inventory, including all of its items and units
+    fn record_inventory_deletion(
+        &mut self,
+        inventory: &Arc<RwLock<Inventory>>,
+    ) -> Result<(), DataError> {
+        let inventory = read(inventory)?;
+
+        for item in inventory.items.iter() {
+            self.record_item_deletion(item)?;
+        }
+
+        self.event_log
+            .deleted(Entity::Inventory((&*inventory).into()));
+        Ok(())
+    }
+
+    /// Records the deletion of an item, including all of its units
+    fn record_item_deletion(&mut self, item: &Arc<RwLock<Item>>) -> Result<(), DataError> {
+        let item = read(item)?;
+
+        for unit in item.units.iter() {
+            let unit = read(unit)?;
+            self.event_log.deleted(Entity::Unit((&*unit).into()));
+        }
+
+        self.event_log.deleted(Entity::Item((&*item).into()));
+        Ok(())
+    }
+
+    /// Looks up the current version of an entity referenced by a request
+    ///
+    /// Don't rely on the referenced entity itself, it may have been replaced by a synchronisation.
+    fn current<T: Current>(&self, stale: &Arc<RwLock<T>>) -> Result<Arc<RwLock<T>>, DataError> {
+        let key = read(stale)?.key();
+        T::find_current(self, key)
+    }
+
+    fn find_inv(&self, inv_uuid: Uuid) -> Result<Arc<RwLock<Inventory>>, DataError> {
+        for inventory in self.inventories.iter() {
+            if read(inventory)?.uuid == inv_uuid {
+                return Ok(inventory.clone());
+            }
+        }
+
+        Err(DataError::UnknownInventory(inv_uuid))
+    }
+
+    fn find_item(
+        &self,
+        inventory_uuid: Uuid,
+        item_uuid: Uuid,
+    ) -> Result<Arc<RwLock<Item>>, DataError> {
+        let inventory = self.find_inv(inventory_uuid)?;
+
+        for item in read(&inventory)?.items.iter() {
+            if read(item)?.uuid == item_uuid {
+                return Ok(item.clone());
+            }
+        }
+
+        Err(DataError::UnknownItem(item_uuid))
+    }
+
+    /// Finds an item of the inventory with the given (normalised) EAN
+    fn find_item_by_ean(
+        &self,
+        inventory_uuid: Uuid,
+        ean: &str,
+    ) -> Result<Option<Arc<RwLock<Item>>>, DataError> {
+        let inventory = self.find_inv(inventory_uuid)?;
+
+        for item in read(&inventory)?.items.iter() {
+            // Items from before EANs were normalised may use another form of the same EAN
+            let matches = read(item)?.ean.as_deref().map_or(false, |existing| {
+                existing == ean || ean::normalize(existing).map_or(false, |e| e == ean)
+            });
+
+            if matches {
+                return Ok(Some(item.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds the item a unit belongs to, regardless of its inventory
+    fn find_item_of_unit(&self, unit: &Unit) -> Result<Arc<RwLock<Item>>, DataError> {
+        self.find_item_anywhere(unit.item_uuid)
+    }
+
+    /// Looks up an item without knowing its inventory
+    fn find_item_anywhere(&self, item_uuid: Uuid) -> Result<Arc<RwLock<Item>>, DataError> {
+        for inventory in self.inventories.iter() {
+            for item in read(inventory)?.items.iter() {
+                if read(item)?.uuid == item_uuid {
+                    return Ok(item.clone());
+                }
+            }
+        }
+
+        Err(DataError::UnknownItem(item_uuid))
+    }
+
+    fn find_unit(
+        item: &Arc<RwLock<Item>>,
+        unit_uuid: Uuid,
+    ) -> Result<Arc<RwLock<Unit>>, DataError> {
+        for unit in read(item)?.units.iter() {
+            if read(unit)?.uuid == unit_uuid {
+                return Ok(unit.clone());
+            }
+        }
+
+        Err(DataError::UnknownUnit(unit_uuid))
+    }
+}
+
+/// An entity which can be looked up again in the current state of the data agent
+trait Current: Sized {
+    /// What the entity is looked up by, so that it doesn't need to stay locked meanwhile
+    type Key;
+
+    fn key(&self) -> Self::Key;
+    fn find_current(agent: &DataAgent, key: Self::Key) -> Result<Arc<RwLock<Self>>, DataError>;
+}
+
+impl Current for Inventory {
+    type Key = Uuid;
+
+    fn key(&self) -> Self::Key {
+        self.uuid
+    }
+
+    fn find_current(agent: &DataAgent, uuid: Uuid) -> Result<Arc<RwLock<Self>>, DataError> {
+        agent.find_inv(uuid)
+    }
+}
+
+impl Current for Item {
+    type Key = (Uuid, Uuid);
+
+    fn key(&self) -> Self::Key {
+        (self.inventory_uuid, self.uuid)
+    }
+
+    fn find_current(
+        agent: &DataAgent,
+        (inventory_uuid, uuid): (Uuid, Uuid),
+    ) -> Result<Arc<RwLock<Self>>, DataError> {
+        agent.find_item(inventory_uuid, uuid)
+    }
+}
+
+impl Current for Unit {
+    type Key = Unit;
+
+    fn key(&self) -> Self::Key {
+        self.clone()
+    }
+
+    fn find_current(agent: &DataAgent, unit: Unit) -> Result<Arc<RwLock<Self>>, DataError> {
+        let item = agent.find_item_of_unit(&unit)?;
+        DataAgent::find_unit(&item, unit.uuid)
+    }
+}
+
+/// Locks an entity for reading, which only fails if an earlier panic poisoned the lock
+fn read<T>(lock: &RwLock<T>) -> Result<RwLockReadGuard<'_, T>, DataError> {
+    lock.read().map_err(|_| DataError::Poisoned)
+}
+
+/// Locks an entity for writing, which only fails if an earlier panic poisoned the lock
+fn write<T>(lock: &RwLock<T>) -> Result<RwLockWriteGuard<'_, T>, DataError> {
+    lock.write().map_err(|_| DataError::Poisoned)
+}
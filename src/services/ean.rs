@@ -0,0 +1,198 @@
+use std::fmt;
+
+/// Why a string isn't a valid EAN
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EanError {
+    /// The EAN contains something other than digits (and whitespace)
+    InvalidCharacter(char),
+
+    /// The EAN is neither 8 (EAN-8), 12 (UPC-A) nor 13 (EAN-13) digits long
+    InvalidLength(usize),
+
+    /// The last digit doesn't match the GTIN check digit of the others
+    InvalidCheckDigit { expected: u32 },
+}
+
+impl fmt::Display for EanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EanError::InvalidCharacter(c) => {
+                write!(f, "An EAN may only contain digits, not '{}'", c)
+            }
+            EanError::InvalidLength(length) => write!(
+                f,
+                "An EAN must have 8 or 13 digits (or 12 for a UPC-A), not {}",
+                length
+            ),
+            EanError::InvalidCheckDigit { expected } => write!(
+                f,
+                "The check digit doesn't match (expected {}), is there a typo?",
+                expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EanError {}
+
+/// Validates an EAN and brings it into its canonical form
+///
+/// Whitespace is removed and UPC-A codes are converted to EAN-13 by prepending a zero.
+pub fn normalize(input: &str) -> Result<String, EanError> {
+    let code: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(c) = code.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(EanError::InvalidCharacter(c));
+    }
+
+    let code = match code.len() {
+        8 | 13 => code,
+        12 => format!("0{}", code),
+        length => return Err(EanError::InvalidLength(length)),
+    };
+
+    let (payload, check) = code.split_at(code.len() - 1);
+    let expected = check_digit(payload);
+
+    if check.parse::<u32>() != Ok(expected) {
+        return Err(EanError::InvalidCheckDigit { expected });
+    }
+
+    Ok(code)
+}
+
+/// Normalises an optional EAN, treating blank input as no EAN at all
+pub fn normalize_optional(input: Option<&str>) -> Result<Option<String>, EanError> {
+    match input.filter(|input| !input.trim().is_empty()) {
+        Some(input) => normalize(input).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Checks whether an EAN is valid and already in its canonical form
+pub fn is_valid(code: &str) -> bool {
+    normalize(code).as_deref() == Ok(code)
+}
+
+/// Verifies the GTIN check digit (the last digit) of a string of digits
+pub fn has_valid_check_digit(code: &str) -> bool {
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let (payload, check) = code.split_at(code.len() - 1);
+    check.parse::<u32>() == Ok(check_digit(payload))
+}
+
+/// Computes the GTIN check digit of the given digits
+///
+/// The weights alternate between 3 and 1, starting with 3 at the rightmost digit.
+fn check_digit(payload: &str) -> u32 {
+    let sum: u32 = payload
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { digit })
+        .sum();
+
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_valid_codes() {
+        assert_eq!(normalize("4006381333931"), Ok("4006381333931".to_string()));
+        assert_eq!(normalize("96385074"), Ok("96385074".to_string()));
+    }
+
+    #[test]
+    fn converts_upc_a_to_ean13() {
+        assert_eq!(normalize("036000291452"), Ok("0036000291452".to_string()));
+    }
+
+    #[test]
+    fn removes_embedded_whitespace() {
+        assert_eq!(
+            normalize(" 4 006381\t333931\n"),
+            Ok("4006381333931".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_check_digit() {
+        assert_eq!(
+            normalize("4006381333932"),
+            Err(EanError::InvalidCheckDigit { expected: 1 })
+        );
+        assert_eq!(
+            normalize("96385075"),
+            Err(EanError::InvalidCheckDigit { expected: 4 })
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        assert_eq!(normalize("1234567"), Err(EanError::InvalidLength(7)));
+        assert_eq!(
+            normalize("40063813339310"),
+            Err(EanError::InvalidLength(14))
+        );
+        assert_eq!(normalize(""), Err(EanError::InvalidLength(0)));
+    }
+
+    #[test]
+    fn rejects_other_characters() {
+        assert_eq!(
+            normalize("400638133393a"),
+            Err(EanError::InvalidCharacter('a'))
+        );
+    }
+
+    #[test]
+    fn treats_blank_input_as_no_ean() {
+        assert_eq!(normalize_optional(None), Ok(None));
+        assert_eq!(normalize_optional(Some("  ")), Ok(None));
+        assert_eq!(
+            normalize_optional(Some("96385074")),
+            Ok(Some("96385074".to_string()))
+        );
+    }
+
+    #[test]
+    fn accepts_only_canonical_codes() {
+        assert!(is_valid("4006381333931"));
+        assert!(is_valid("0036000291452"));
+
+        // Valid, but not in their canonical form
+        assert!(!is_valid("036000291452"));
+        assert!(!is_valid("4006381 333931"));
+
+        assert!(!is_valid("4006381333932"));
+        assert!(!is_valid(""));
+    }
+
+    #[test]
+    fn verifies_check_digits() {
+        assert!(has_valid_check_digit("4006381333931"));
+        assert!(has_valid_check_digit("036000291452"));
+        assert!(has_valid_check_digit("96385074"));
+
+        assert!(!has_valid_check_digit("4006381333932"));
+        assert!(!has_valid_check_digit("40063813339x1"));
+        assert!(!has_valid_check_digit(""));
+    }
+
+    #[test]
+    fn computes_check_digits() {
+        assert_eq!(check_digit("400638133393"), 1);
+        assert_eq!(check_digit("03600029145"), 2);
+        assert_eq!(check_digit("9638507"), 4);
+
+        // The sum is already a multiple of ten
+        assert_eq!(check_digit("0000000"), 0);
+    }
+}
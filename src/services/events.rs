@@ -0,0 +1,819 @@
+use super::ean;
+use crate::types::UnitDetails;
+use chrono::{DateTime, Utc};
+use libocc::{CrudOperation, Event, Projector};
+use serde::{Deserialize, Serialize};
+use sfi_core::core::{Inventory, Item, Unit};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock, RwLockReadGuard},
+};
+use uuid::Uuid;
+
+/// A flat, serializable snapshot of one of the entities managed by sfi
+///
+/// The nested `Inventory -> Item -> Unit` tree of sfi-core cannot be used as the payload of
+/// an event directly, since every update would then contain all of the children as well.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Entity {
+    Inventory(InventoryRecord),
+    Item(ItemRecord),
+    Unit(UnitRecord),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InventoryRecord {
+    pub uuid: Uuid,
+    pub name: String,
+    pub owner: Uuid,
+    pub admins: Vec<Uuid>,
+    pub writables: Vec<Uuid>,
+    pub readables: Vec<Uuid>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItemRecord {
+    pub uuid: Uuid,
+    pub inventory_uuid: Uuid,
+    pub name: String,
+    pub ean: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnitRecord {
+    pub uuid: Uuid,
+    pub item_uuid: Uuid,
+    pub name: String,
+
+    #[serde(default)]
+    pub details: UnitDetails,
+}
+
+/// A single entry of the event log
+///
+/// Every entry carries its own UUID, so that the same event can be recognized again
+/// (e.g. after it was exchanged with another device).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub uuid: Uuid,
+    pub recorded_at: DateTime<Utc>,
+    pub event: Event<'static, Entity>,
+
+    /// The previous event of the same entity, as known to the device recording this event
+    #[serde(default)]
+    pub parent: Option<Uuid>,
+
+    /// The conflicting events which are resolved by this event
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolves: Vec<Uuid>,
+
+    /// Whether sfi-server already knows about this event
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub synced: bool,
+
+    /// Where the event is in the log of this device, since the stored events are unordered
+    ///
+    /// Events stored before positions were introduced are at position 0.
+    #[serde(default)]
+    pub position: u64,
+}
+
+/// Concurrent updates of the same entity, which haven't been resolved yet
+///
+/// Two updates are concurrent if they were recorded on top of the same previous event,
+/// i.e. without either device knowing about the other update.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Conflict {
+    pub entity_uuid: Uuid,
+    pub versions: Vec<ConflictVersion>,
+}
+
+/// One of the versions of an entity involved in a conflict
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConflictVersion {
+    pub event_uuid: Uuid,
+    pub recorded_at: DateTime<Utc>,
+    pub entity: Entity,
+}
+
+/// The current state of the data, as rebuilt from the event log
+pub struct Projection {
+    pub inventories: Vec<Arc<RwLock<Inventory>>>,
+    pub unit_details: HashMap<Uuid, UnitDetails>,
+}
+
+/// The append-only log of every change made to the data of sfi
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct EventLog {
+    events: Vec<LoggedEvent>,
+
+    /// The events which were recorded or changed since they were last saved
+    #[serde(skip)]
+    changed: HashSet<Uuid>,
+}
+
+impl Entity {
+    /// Returns the UUID of the entity (regardless of its kind)
+    pub fn uuid(&self) -> Uuid {
+        match self {
+            Entity::Inventory(record) => record.uuid,
+            Entity::Item(record) => record.uuid,
+            Entity::Unit(record) => record.uuid,
+        }
+    }
+
+    /// Checks if two snapshots of an entity contain the same data
+    pub fn same_contents(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Entity::Inventory(a), Entity::Inventory(b)) => a == b,
+            (Entity::Item(a), Entity::Item(b)) => a == b,
+            (Entity::Unit(a), Entity::Unit(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// Entities are identified by their kind and UUID, not by their contents.
+// This is how the projector knows which entity an update or delete event refers to.
+impl PartialEq for Entity {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Entity::Inventory(a), Entity::Inventory(b)) => a.uuid == b.uuid,
+            (Entity::Item(a), Entity::Item(b)) => a.uuid == b.uuid,
+            (Entity::Unit(a), Entity::Unit(b)) => a.uuid == b.uuid,
+            _ => false,
+        }
+    }
+}
+
+impl From<&Inventory> for InventoryRecord {
+    fn from(inventory: &Inventory) -> Self {
+        Self {
+            uuid: inventory.uuid,
+            name: inventory.name.clone(),
+            owner: inventory.owner,
+            admins: inventory.admins.clone(),
+            writables: inventory.writables.clone(),
+            readables: inventory.readables.clone(),
+        }
+    }
+}
+
+impl From<&Item> for ItemRecord {
+    fn from(item: &Item) -> Self {
+        Self {
+            uuid: item.uuid,
+            inventory_uuid: item.inventory_uuid,
+            name: item.name.clone(),
+            ean: item.ean.clone(),
+        }
+    }
+}
+
+impl From<&Unit> for UnitRecord {
+    fn from(unit: &Unit) -> Self {
+        Self {
+            uuid: unit.uuid,
+            item_uuid: unit.item_uuid,
+            name: unit.name.clone(),
+            details: UnitDetails::default(),
+        }
+    }
+}
+
+impl InventoryRecord {
+    fn to_inventory(&self) -> Inventory {
+        let mut inventory = Inventory::new(self.name.clone(), self.owner);
+
+        inventory.uuid = self.uuid;
+        inventory.admins = self.admins.clone();
+        inventory.writables = self.writables.clone();
+        inventory.readables = self.readables.clone();
+
+        inventory
+    }
+}
+
+impl ItemRecord {
+    fn to_item(&self) -> Item {
+        let mut item = Item::new(self.inventory_uuid, self.name.clone(), self.ean.clone());
+        item.uuid = self.uuid;
+        item
+    }
+}
+
+impl UnitRecord {
+    pub fn new(unit: &Unit, details: UnitDetails) -> Self {
+        Self {
+            details,
+            ..UnitRecord::from(unit)
+        }
+    }
+
+    fn to_unit(&self) -> Unit {
+        let mut unit = Unit::new(self.item_uuid, self.name.clone());
+        unit.uuid = self.uuid;
+        unit
+    }
+}
+
+impl LoggedEvent {
+    fn new(event: Event<'static, Entity>, parent: Option<Uuid>, resolves: Vec<Uuid>) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            recorded_at: Utc::now(),
+            event,
+            parent,
+            resolves,
+            synced: false,
+            position: 0,
+        }
+    }
+
+    /// Returns the snapshot of the entity contained in this event
+    pub fn entity(&self) -> &Entity {
+        self.event.data.as_ref()
+    }
+
+    fn is_update(&self) -> bool {
+        matches!(self.event.operation, CrudOperation::Update)
+    }
+}
+
+impl EventLog {
+    /// Creates an event log from stored events, in any order
+    pub fn from_events(mut events: Vec<LoggedEvent>) -> Self {
+        // Events without a position were recorded before positions were introduced
+        events.sort_by_key(|logged| (logged.position, logged.recorded_at));
+
+        Self {
+            events,
+            changed: HashSet::new(),
+        }
+    }
+
+    /// Creates an event log from an existing (legacy) inventory tree
+    ///
+    /// Used to migrate data which was stored before the event log was introduced.
+    pub fn from_inventories(inventories: &[Arc<RwLock<Inventory>>]) -> Self {
+        let mut log = Self::default();
+
+        for inventory in inventories {
+            let inventory = match read_legacy(inventory, "inventory") {
+                Some(inventory) => inventory,
+                None => continue,
+            };
+            log.created(Entity::Inventory((&*inventory).into()));
+
+            for item in inventory.items.iter() {
+                let item = match read_legacy(item, "item") {
+                    Some(item) => item,
+                    None => continue,
+                };
+                log.created(Entity::Item((&*item).into()));
+
+                for unit in item
+                    .units
+                    .iter()
+                    .filter_map(|unit| read_legacy(unit, "unit"))
+                {
+                    log.created(Entity::Unit((&*unit).into()));
+                }
+            }
+        }
+
+        log
+    }
+
+    /// Records the creation of an entity
+    pub fn created(&mut self, entity: Entity) {
+        self.record(Event::create(Cow::Owned(entity)));
+    }
+
+    /// Records the update of an entity
+    pub fn updated(&mut self, entity: Entity) {
+        self.record(Event::update(Cow::Owned(entity)));
+    }
+
+    /// Records the deletion of an entity
+    pub fn deleted(&mut self, entity: Entity) {
+        self.record(Event::delete(Cow::Owned(entity)));
+    }
+
+    /// Records the update of an entity which resolves a conflict
+    pub fn resolved(&mut self, entity: Entity, conflict: &Conflict) {
+        let resolves = conflict
+            .versions
+            .iter()
+            .map(|version| version.event_uuid)
+            .collect();
+
+        let parent = self.latest_of(entity.uuid());
+        let event = Event::update(Cow::Owned(entity));
+
+        self.push(LoggedEvent::new(event, parent, resolves));
+    }
+
+    /// Discards every event of the log
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.changed.clear();
+    }
+
+    /// Returns the events which were recorded or changed since they were last saved
+    pub fn changes(&self) -> Vec<LoggedEvent> {
+        self.events
+            .iter()
+            .filter(|logged| self.changed.contains(&logged.uuid))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks if any events need to be saved
+    pub fn has_changes(&self) -> bool {
+        !self.changed.is_empty()
+    }
+
+    /// Marks every event as changed, e.g. to move the log to another storage
+    pub fn mark_all_changed(&mut self) {
+        self.changed = self.events.iter().map(|logged| logged.uuid).collect();
+    }
+
+    /// Marks the given events as saved, unless they have changed again in the meantime
+    pub fn mark_saved(&mut self, saved: &[LoggedEvent]) {
+        for logged in saved {
+            let unchanged = self
+                .events
+                .iter()
+                .any(|known| known.uuid == logged.uuid && known.synced == logged.synced);
+
+            if unchanged {
+                self.changed.remove(&logged.uuid);
+            }
+        }
+    }
+
+    fn record(&mut self, event: Event<'static, Entity>) {
+        let parent = self.latest_of(event.data.uuid());
+        self.push(LoggedEvent::new(event, parent, vec![]));
+    }
+
+    /// Appends an event to the log, behind every event known so far
+    fn push(&mut self, mut logged: LoggedEvent) {
+        logged.position = self.events.last().map_or(1, |last| last.position + 1);
+
+        self.changed.insert(logged.uuid);
+        self.events.push(logged);
+    }
+
+    /// Returns the UUID of the most recent event of an entity
+    fn latest_of(&self, entity_uuid: Uuid) -> Option<Uuid> {
+        self.events
+            .iter()
+            .rev()
+            .find(|logged| logged.entity().uuid() == entity_uuid)
+            .map(|logged| logged.uuid)
+    }
+
+    /// Finds the concurrent updates which haven't been resolved yet
+    ///
+    /// An update concurrent with the deletion of the same entity isn't a conflict,
+    /// since the entity stays deleted regardless of the order of both events.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts: Vec<Conflict> = vec![];
+
+        let resolved: Vec<Uuid> = self
+            .events
+            .iter()
+            .flat_map(|logged| logged.resolves.iter().cloned())
+            .collect();
+
+        for (index, logged) in self.events.iter().enumerate() {
+            // Events without a parent predate the conflict detection
+            let parent = match logged.parent {
+                Some(parent) if logged.is_update() => parent,
+                _ => continue,
+            };
+
+            // Every conflict is found by its earliest version
+            if conflicts.iter().any(|conflict| {
+                conflict
+                    .versions
+                    .iter()
+                    .any(|v| v.event_uuid == logged.uuid)
+            }) {
+                continue;
+            }
+
+            let siblings: Vec<&LoggedEvent> = self.events[index + 1..]
+                .iter()
+                .filter(|other| {
+                    other.is_update()
+                        && other.parent == Some(parent)
+                        && other.entity().uuid() == logged.entity().uuid()
+                        && !other.entity().same_contents(logged.entity())
+                })
+                .collect();
+
+            if siblings.is_empty()
+                || resolved.contains(&logged.uuid)
+                || siblings.iter().any(|other| resolved.contains(&other.uuid))
+            {
+                continue;
+            }
+
+            conflicts.push(Conflict {
+                entity_uuid: logged.entity().uuid(),
+                versions: std::iter::once(logged)
+                    .chain(siblings)
+                    .map(|version| ConflictVersion {
+                        event_uuid: version.uuid,
+                        recorded_at: version.recorded_at,
+                        entity: version.entity().clone(),
+                    })
+                    .collect(),
+            });
+        }
+
+        conflicts
+    }
+
+    /// Returns the events which haven't been pushed to sfi-server yet
+    pub fn unsynced(&self) -> Vec<LoggedEvent> {
+        self.events
+            .iter()
+            .filter(|logged| !logged.synced)
+            .cloned()
+            .collect()
+    }
+
+    /// Marks the given events as known to sfi-server
+    pub fn mark_synced(&mut self, uuids: &[Uuid]) {
+        for logged in self.events.iter_mut() {
+            if uuids.contains(&logged.uuid) && !logged.synced {
+                logged.synced = true;
+                self.changed.insert(logged.uuid);
+            }
+        }
+    }
+
+    /// Merges remote events into the log, returning the number of previously unknown events
+    ///
+    /// The remote events are appended in the order sfi-server sent them in. The clocks of
+    /// other devices cannot be relied on, so an event might be ordered before its parent otherwise.
+    pub fn merge(&mut self, remote: Vec<LoggedEvent>) -> usize {
+        let mut merged = 0;
+
+        for mut logged in remote {
+            if self.events.iter().any(|known| known.uuid == logged.uuid) {
+                continue;
+            }
+
+            // Don't let invalid EANs in, even if another client doesn't validate them.
+            // The event itself is kept, otherwise later events of the item had nothing to apply to.
+            if let Entity::Item(record) = logged.event.data.to_mut() {
+                if let Some(code) = record.ean.take() {
+                    record.ean = ean::normalize(&code).ok();
+
+                    if record.ean.is_none() {
+                        log::warn!("Dropping invalid EAN {} of event {}", code, logged.uuid);
+                    }
+                }
+            }
+
+            logged.synced = true;
+            self.push(logged);
+            merged += 1;
+        }
+
+        merged
+    }
+
+    /// Merges the events saved by another tab, returning the number of previously unknown events
+    ///
+    /// Unlike remote events, these keep their synchronisation state and position.
+    /// They have already been saved, so they aren't considered changed.
+    pub fn merge_local(&mut self, saved: Vec<LoggedEvent>) -> usize {
+        let mut merged = 0;
+
+        for logged in saved {
+            match self
+                .events
+                .iter_mut()
+                .find(|known| known.uuid == logged.uuid)
+            {
+                // The other tab may have pushed the event in the meantime
+                Some(known) => known.synced |= logged.synced,
+                None => {
+                    self.events.push(logged);
+                    merged += 1;
+                }
+            }
+        }
+
+        if merged > 0 {
+            self.events
+                .sort_by_key(|logged| (logged.position, logged.recorded_at));
+        }
+
+        merged
+    }
+
+    /// Replays the log and builds the inventory tree used by the rest of the app
+    pub fn project(&self) -> Projection {
+        let mut projector = Projector::new();
+
+        for logged in self.events.iter() {
+            if let Err(error) = projector.push(logged.event.clone()) {
+                log::warn!(
+                    "Skipping event {} during projection: {}",
+                    logged.uuid,
+                    error
+                );
+            }
+        }
+
+        // Sort the projected entities by their kind
+        let mut inventories: Vec<Inventory> = vec![];
+        let mut items: Vec<Item> = vec![];
+        let mut units: Vec<Unit> = vec![];
+        let mut unit_details = HashMap::new();
+
+        for entity in projector.get_projection().iter() {
+            match entity.as_ref() {
+                Entity::Inventory(record) => inventories.push(record.to_inventory()),
+                Entity::Item(record) => items.push(record.to_item()),
+                Entity::Unit(record) => {
+                    unit_details.insert(record.uuid, record.details.clone());
+                    units.push(record.to_unit());
+                }
+            }
+        }
+
+        // Attach the units to their items
+        for unit in units {
+            if let Some(item) = items.iter_mut().find(|item| item.uuid == unit.item_uuid) {
+                item.units.push(Arc::new(RwLock::new(unit)));
+            } else {
+                log::warn!("Dropping orphaned unit {}", unit.uuid);
+            }
+        }
+
+        // Attach the items to their inventories
+        for item in items {
+            if let Some(inventory) = inventories
+                .iter_mut()
+                .find(|inventory| inventory.uuid == item.inventory_uuid)
+            {
+                inventory.items.push(Arc::new(RwLock::new(item)));
+            } else {
+                log::warn!("Dropping orphaned item {}", item.uuid);
+            }
+        }
+
+        let inventories = inventories
+            .into_iter()
+            .map(|inventory| Arc::new(RwLock::new(inventory)))
+            .collect();
+
+        Projection {
+            inventories,
+            unit_details,
+        }
+    }
+}
+
+/// Locks an entity of a legacy inventory tree for reading, skipping it if a panic poisoned the lock
+fn read_legacy<'a, T>(lock: &'a RwLock<T>, kind: &str) -> Option<RwLockReadGuard<'a, T>> {
+    match lock.read() {
+        Ok(entity) => Some(entity),
+        Err(_) => {
+            log::warn!("Skipping a legacy {} which cannot be read", kind);
+            None
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(uuid: Uuid, name: &str, ean: Option<&str>) -> Entity {
+        Entity::Item(ItemRecord {
+            uuid,
+            inventory_uuid: Uuid::nil(),
+            name: name.to_string(),
+            ean: ean.map(String::from),
+        })
+    }
+
+    /// An update recorded by another device, on top of the given previous event
+    fn remote_update(entity: Entity, parent: Option<Uuid>) -> LoggedEvent {
+        LoggedEvent::new(Event::update(Cow::Owned(entity)), parent, vec![])
+    }
+
+    /// A creation saved by another tab, at the given position of its log
+    fn saved_creation(entity: Entity, position: u64) -> LoggedEvent {
+        LoggedEvent {
+            position,
+            ..LoggedEvent::new(Event::create(Cow::Owned(entity)), None, vec![])
+        }
+    }
+
+    fn uuids(log: &EventLog) -> Vec<Uuid> {
+        log.events.iter().map(|logged| logged.uuid).collect()
+    }
+
+    #[test]
+    fn reports_updates_of_the_same_parent_as_conflict() {
+        let uuid = Uuid::new_v4();
+        let mut log = EventLog::default();
+        log.created(item(uuid, "Milk", None));
+        let parent = log.latest_of(uuid);
+
+        let first = remote_update(item(uuid, "Oat milk", None), parent);
+        let second = remote_update(item(uuid, "Soy milk", None), parent);
+        log.merge(vec![first.clone(), second.clone()]);
+
+        let conflicts = log.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].entity_uuid, uuid);
+
+        let versions: Vec<Uuid> = conflicts[0]
+            .versions
+            .iter()
+            .map(|version| version.event_uuid)
+            .collect();
+        assert_eq!(versions, vec![first.uuid, second.uuid]);
+    }
+
+    #[test]
+    fn ignores_updates_of_the_same_parent_with_the_same_contents() {
+        let uuid = Uuid::new_v4();
+        let mut log = EventLog::default();
+        log.created(item(uuid, "Milk", None));
+        let parent = log.latest_of(uuid);
+
+        log.merge(vec![
+            remote_update(item(uuid, "Oat milk", None), parent),
+            remote_update(item(uuid, "Oat milk", None), parent),
+        ]);
+
+        assert!(log.conflicts().is_empty());
+    }
+
+    #[test]
+    fn stops_reporting_resolved_conflicts() {
+        let uuid = Uuid::new_v4();
+        let mut log = EventLog::default();
+        log.created(item(uuid, "Milk", None));
+        let parent = log.latest_of(uuid);
+
+        log.merge(vec![
+            remote_update(item(uuid, "Oat milk", None), parent),
+            remote_update(item(uuid, "Soy milk", None), parent),
+        ]);
+
+        let conflict = log.conflicts().remove(0);
+        log.resolved(item(uuid, "Oat milk", None), &conflict);
+
+        assert!(log.conflicts().is_empty());
+        assert_eq!(
+            log.events.last().map(|logged| logged.resolves.len()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn finds_no_conflicts_in_a_linear_history() {
+        let uuid = Uuid::new_v4();
+        let mut log = EventLog::default();
+        log.created(item(uuid, "Milk", None));
+        log.updated(item(uuid, "Oat milk", None));
+        log.updated(item(uuid, "Soy milk", None));
+
+        let parent = log.latest_of(uuid);
+        log.merge(vec![remote_update(item(uuid, "Rice milk", None), parent)]);
+
+        assert!(log.conflicts().is_empty());
+    }
+
+    #[test]
+    fn merges_remote_events_in_server_order() {
+        let mut log = EventLog::default();
+        log.created(item(Uuid::new_v4(), "Milk", None));
+
+        // The clock of the other device is behind
+        let mut first = saved_creation(item(Uuid::new_v4(), "Bread", None), 7);
+        let mut second = saved_creation(item(Uuid::new_v4(), "Butter", None), 3);
+        first.recorded_at = Utc::now();
+        second.recorded_at = first.recorded_at - chrono::Duration::hours(1);
+
+        assert_eq!(log.merge(vec![first.clone(), second.clone()]), 2);
+        assert_eq!(&uuids(&log)[1..], &[first.uuid, second.uuid]);
+
+        // Remote events are placed behind every known event, regardless of their position
+        let positions: Vec<u64> = log.events.iter().map(|logged| logged.position).collect();
+        assert_eq!(positions, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn merges_remote_events_only_once() {
+        let mut log = EventLog::default();
+        let remote = saved_creation(item(Uuid::new_v4(), "Milk", None), 1);
+
+        assert_eq!(log.merge(vec![remote.clone()]), 1);
+        assert_eq!(log.merge(vec![remote.clone(), remote]), 0);
+        assert_eq!(log.events.len(), 1);
+    }
+
+    #[test]
+    fn normalizes_the_eans_of_remote_events() {
+        let valid = Uuid::new_v4();
+        let invalid = Uuid::new_v4();
+        let mut log = EventLog::default();
+
+        log.merge(vec![
+            saved_creation(item(valid, "Milk", Some("036000291452")), 1),
+            saved_creation(item(invalid, "Bread", Some("4006381333932")), 2),
+        ]);
+
+        let eans: Vec<Option<String>> = log
+            .events
+            .iter()
+            .map(|logged| match logged.entity() {
+                Entity::Item(record) => record.ean.clone(),
+                _ => panic!("Only items were merged"),
+            })
+            .collect();
+
+        // The event with the invalid EAN is kept, only without the EAN
+        assert_eq!(eans, vec![Some("0036000291452".to_string()), None]);
+    }
+
+    #[test]
+    fn marks_remote_events_as_synced_and_changed() {
+        let mut log = EventLog::default();
+        let remote = saved_creation(item(Uuid::new_v4(), "Milk", None), 1);
+
+        log.merge(vec![remote.clone()]);
+
+        assert!(log.unsynced().is_empty());
+        assert_eq!(
+            log.changes()
+                .iter()
+                .map(|logged| logged.uuid)
+                .collect::<Vec<_>>(),
+            vec![remote.uuid]
+        );
+    }
+
+    #[test]
+    fn sorts_events_of_other_tabs_by_position() {
+        let mut log = EventLog::default();
+        log.created(item(Uuid::new_v4(), "Milk", None));
+        log.mark_saved(&log.changes());
+
+        let third = saved_creation(item(Uuid::new_v4(), "Bread", None), 3);
+        let second = saved_creation(item(Uuid::new_v4(), "Butter", None), 2);
+
+        assert_eq!(log.merge_local(vec![third.clone(), second.clone()]), 2);
+        assert_eq!(&uuids(&log)[1..], &[second.uuid, third.uuid]);
+
+        // The other tab has saved them already
+        assert!(!log.has_changes());
+    }
+
+    #[test]
+    fn keeps_the_sync_state_of_known_events_from_other_tabs() {
+        let mut log = EventLog::default();
+        log.created(item(Uuid::new_v4(), "Milk", None));
+
+        let mut pushed = log.events[0].clone();
+        pushed.synced = true;
+
+        assert_eq!(log.merge_local(vec![pushed]), 0);
+        assert_eq!(log.events.len(), 1);
+        assert!(log.unsynced().is_empty());
+    }
+
+    #[test]
+    fn keeps_events_changed_after_saving_them() {
+        let mut log = EventLog::default();
+        log.created(item(Uuid::new_v4(), "Milk", None));
+        let saving = log.changes();
+
+        // The event is pushed while it's being saved
+        log.mark_synced(&[saving[0].uuid]);
+        log.mark_saved(&saving);
+        assert!(log.has_changes());
+
+        log.mark_saved(&log.changes());
+        assert!(!log.has_changes());
+    }
+}
@@ -0,0 +1,267 @@
+//! A small callback-based wrapper around IndexedDB
+//!
+//! Values are stored as JSON strings, keys are strings as well.
+
+use std::{cell::RefCell, fmt, rc::Rc};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{
+    DomException, Event, EventTarget, IdbDatabase, IdbRequest, IdbTransaction, IdbTransactionMode,
+};
+use yew::Callback;
+
+/// The callback type used for the results of IndexedDB operations
+pub type IdbCallback<T> = Callback<Result<T, IdbError>>;
+
+/// Describes why an IndexedDB operation failed
+#[derive(Debug, Clone)]
+pub struct IdbError(pub String);
+
+impl fmt::Display for IdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IndexedDB error: {}", self.0)
+    }
+}
+
+impl std::error::Error for IdbError {}
+
+impl From<JsValue> for IdbError {
+    fn from(value: JsValue) -> Self {
+        let message = value
+            .dyn_ref::<DomException>()
+            .map(|exception| exception.message())
+            .or_else(|| value.as_string())
+            .unwrap_or_else(|| format!("{:?}", value));
+
+        Self(message)
+    }
+}
+
+/// Opens (and if necessary creates or upgrades) a database with the given object stores
+pub fn open(
+    name: &str,
+    version: u32,
+    stores: &'static [&'static str],
+    callback: IdbCallback<IdbDatabase>,
+) {
+    let request = match factory()
+        .and_then(|factory| factory.open_with_u32(name, version).map_err(IdbError::from))
+    {
+        Ok(request) => request,
+        Err(error) => return callback.emit(Err(error)),
+    };
+
+    // Create missing object stores when the database is new or outdated
+    let upgrade_request = request.clone();
+    once(
+        &request,
+        &["upgradeneeded", "success", "error"],
+        move |event| {
+            // The database is up to date, so the listener only needs to be removed
+            if event.type_() != "upgradeneeded" {
+                return;
+            }
+
+            match upgrade_request
+                .result()
+                .and_then(|db| db.dyn_into::<IdbDatabase>().map_err(JsValue::from))
+            {
+                Ok(db) => {
+                    for store in stores {
+                        if !db.object_store_names().contains(store) {
+                            if let Err(error) = db.create_object_store(store) {
+                                log::error!("Cannot create object store {}: {:?}", store, error);
+                            }
+                        }
+                    }
+                }
+                Err(error) => log::error!("Cannot upgrade database: {:?}", error),
+            }
+        },
+    );
+
+    on_done(&request, callback, |db| {
+        db.dyn_into::<IdbDatabase>().map_err(IdbError::from)
+    });
+}
+
+/// Reads the value stored under the given key, if any
+pub fn get(db: &IdbDatabase, store: &str, key: &str, callback: IdbCallback<Option<String>>) {
+    let request = db
+        .transaction_with_str(store)
+        .and_then(|transaction| transaction.object_store(store))
+        .and_then(|store| store.get(&JsValue::from_str(key)));
+
+    match request {
+        Ok(request) => on_done(&request, callback, |value| Ok(value.as_string())),
+        Err(error) => callback.emit(Err(error.into())),
+    }
+}
+
+/// Reads every value of an object store
+pub fn get_all(db: &IdbDatabase, store: &str, callback: IdbCallback<Vec<String>>) {
+    let request = db
+        .transaction_with_str(store)
+        .and_then(|transaction| transaction.object_store(store))
+        .and_then(|store| store.get_all());
+
+    match request {
+        Ok(request) => on_done(&request, callback, |values| {
+            Ok(js_sys::Array::from(&values)
+                .iter()
+                .filter_map(|value| value.as_string())
+                .collect())
+        }),
+        Err(error) => callback.emit(Err(error.into())),
+    }
+}
+
+/// Stores all of the given key-value pairs in a single transaction
+pub fn put_all(
+    db: &IdbDatabase,
+    store: &str,
+    entries: Vec<(String, String)>,
+    callback: IdbCallback<()>,
+) {
+    let transaction = match db.transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite) {
+        Ok(transaction) => transaction,
+        Err(error) => return callback.emit(Err(error.into())),
+    };
+
+    let result = transaction.object_store(store).and_then(|store| {
+        for (key, value) in entries {
+            store.put_with_key(&JsValue::from_str(&value), &JsValue::from_str(&key))?;
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => on_complete(&transaction, callback),
+        Err(error) => {
+            // Don't store anything if one of the values cannot be stored
+            let _ = transaction.abort();
+            callback.emit(Err(error.into()))
+        }
+    }
+}
+
+/// Removes every value of an object store
+pub fn clear(db: &IdbDatabase, store: &str, callback: IdbCallback<()>) {
+    let request = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)
+        .and_then(|transaction| transaction.object_store(store))
+        .and_then(|store| store.clear());
+
+    match request {
+        Ok(request) => on_done(&request, callback, |_| Ok(())),
+        Err(error) => callback.emit(Err(error.into())),
+    }
+}
+
+/// Counts the values of an object store
+pub fn count(db: &IdbDatabase, store: &str, callback: IdbCallback<u32>) {
+    let request = db
+        .transaction_with_str(store)
+        .and_then(|transaction| transaction.object_store(store))
+        .and_then(|store| store.count());
+
+    match request {
+        Ok(request) => on_done(&request, callback, |count| {
+            count
+                .as_f64()
+                .map(|count| count as u32)
+                .ok_or_else(|| IdbError("The count is not a number".to_string()))
+        }),
+        Err(error) => callback.emit(Err(error.into())),
+    }
+}
+
+fn factory() -> Result<web_sys::IdbFactory, IdbError> {
+    web_sys::window()
+        .ok_or_else(|| IdbError("No window available".to_string()))?
+        .indexed_db()?
+        .ok_or_else(|| IdbError("IndexedDB is not supported by this browser".to_string()))
+}
+
+/// Emits the converted result of a request once it succeeds (or the error if it fails)
+fn on_done<T, F>(request: &IdbRequest, callback: IdbCallback<T>, convert: F)
+where
+    T: 'static,
+    F: FnOnce(JsValue) -> Result<T, IdbError> + 'static,
+{
+    let done_request = request.clone();
+    once(request, &["success", "error"], move |event| {
+        let result = if event.type_() == "success" {
+            done_request
+                .result()
+                .map_err(IdbError::from)
+                .and_then(convert)
+        } else {
+            Err(done_request
+                .error()
+                .ok()
+                .flatten()
+                .map(|exception| IdbError(exception.message()))
+                .unwrap_or_else(|| IdbError("The request failed".to_string())))
+        };
+
+        callback.emit(result)
+    });
+}
+
+/// Emits `Ok(())` once a transaction is committed (or the error if it is aborted)
+fn on_complete(transaction: &IdbTransaction, callback: IdbCallback<()>) {
+    // A failing transaction may fire several error events, but is only aborted once
+    let done_transaction = transaction.clone();
+    once(transaction, &["complete", "abort"], move |event| {
+        if event.type_() == "complete" {
+            return callback.emit(Ok(()));
+        }
+
+        let error = done_transaction
+            .error()
+            .map(|exception| IdbError(exception.message()))
+            .unwrap_or_else(|| IdbError("The transaction was aborted".to_string()));
+
+        callback.emit(Err(error))
+    });
+}
+
+/// Calls the handler with the first of the given events, and removes the listener again
+///
+/// Unlike `Closure::once_into_js`, this doesn't leak if some of the events never fire
+/// (e.g. a request either succeeds or fails).
+fn once<F>(target: &EventTarget, events: &'static [&'static str], handler: F)
+where
+    F: FnOnce(Event) + 'static,
+{
+    let slot: Rc<RefCell<Option<Closure<dyn FnMut(Event)>>>> = Rc::new(RefCell::new(None));
+    let mut handler = Some(handler);
+
+    let listener_target = target.clone();
+    let listener_slot = slot.clone();
+    let listener = Closure::wrap(Box::new(move |event: Event| {
+        // Dropping the listener while it runs is fine, it is only freed once it returns
+        let listener = listener_slot.borrow_mut().take();
+
+        if let Some(listener) = &listener {
+            for name in events {
+                let _ = listener_target
+                    .remove_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+            }
+        }
+
+        if let Some(handler) = handler.take() {
+            handler(event);
+        }
+    }) as Box<dyn FnMut(Event)>);
+
+    for name in events {
+        if let Err(error) =
+            target.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())
+        {
+            log::error!("Cannot listen to {} events: {:?}", name, error);
+        }
+    }
+
+    *slot.borrow_mut() = Some(listener);
+}
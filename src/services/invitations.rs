@@ -0,0 +1,42 @@
+use crate::types::Role;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How long new invitations can be used by default
+pub const DEFAULT_VALIDITY_DAYS: u32 = 7;
+
+/// An invitation to join an inventory, as issued by sfi-server
+///
+/// Anyone knowing the token can accept the invitation (once logged in), until it expires
+/// or is revoked by an admin of the inventory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Invitation {
+    pub token: String,
+    pub inventory_uuid: Uuid,
+    pub inventory_name: String,
+
+    /// The role the recipient gets in the inventory
+    pub role: Role,
+    pub expires: DateTime<Utc>,
+}
+
+/// What an admin asks sfi-server for when inviting someone
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewInvitation {
+    pub role: Role,
+    pub valid_for_days: u32,
+}
+
+impl Invitation {
+    pub fn is_expired(&self) -> bool {
+        self.expires <= Utc::now()
+    }
+
+    /// The link to be shared with the recipient, pointing to this instance of sfi-web
+    pub fn url(&self) -> String {
+        let origin = yew::utils::window().location().origin().unwrap_or_default();
+
+        format!("{}/invite/{}", origin, self.token)
+    }
+}
@@ -1,2 +1,15 @@
+pub mod api;
 pub mod auth;
+pub mod barcode;
+pub mod catalogue;
+pub mod config;
 pub mod data;
+pub mod ean;
+pub mod events;
+pub mod idb;
+pub mod invitations;
+pub mod storage;
+pub mod sync;
+pub mod tabs;
+pub mod totp;
+pub mod validation;
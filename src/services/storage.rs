@@ -0,0 +1,213 @@
+//! Where the data agent keeps its event log
+//!
+//! IndexedDB stores every event individually (keyed by its UUID), so only the events which
+//! changed need to be written. Browsers without IndexedDB fall back to localStorage, where
+//! the whole log is stored as a single entry.
+
+use super::{
+    events::{EventLog, LoggedEvent},
+    idb::{self, IdbError},
+};
+use sfi_core::core::Inventory;
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+use web_sys::IdbDatabase;
+use yew::{
+    format::Json,
+    services::{storage::Area, StorageService},
+    Callback,
+};
+
+const DATABASE_NAME: &'static str = "sfi-data";
+const DATABASE_VERSION: u32 = 1;
+const EVENTS_STORE: &'static str = "events";
+
+/// The localStorage key of the whole event log
+const EVENT_STORE_KEY: &'static str = "sfi.events.store";
+
+/// The localStorage key of the data stored before the event log was introduced
+const SIMPLE_STORE_KEY: &'static str = "sfi.simple_data.store";
+
+/// The callback type used for the results of storage operations
+pub type StorageCallback<T> = Callback<Result<T, StorageError>>;
+
+/// Describes why the event log couldn't be loaded or saved
+#[derive(Debug, Clone)]
+pub struct StorageError(pub String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Storage error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<IdbError> for StorageError {
+    fn from(error: IdbError) -> Self {
+        Self(error.0)
+    }
+}
+
+/// The storage backend of the event log
+pub enum EventStorage {
+    IndexedDb(IdbDatabase),
+    LocalStorage(StorageService),
+}
+
+impl EventStorage {
+    /// Opens IndexedDB, falling back to localStorage if it is unavailable
+    pub fn open(callback: StorageCallback<EventStorage>) {
+        idb::open(
+            DATABASE_NAME,
+            DATABASE_VERSION,
+            &[EVENTS_STORE],
+            Callback::from(move |result: Result<IdbDatabase, IdbError>| {
+                let storage = match result {
+                    Ok(db) => Ok(EventStorage::IndexedDb(db)),
+
+                    // E.g. in private browsing mode
+                    Err(error) => {
+                        log::warn!("Cannot use IndexedDB, using localStorage: {}", error);
+
+                        StorageService::new(Area::Local)
+                            .map(EventStorage::LocalStorage)
+                            .map_err(|error| StorageError(error.to_string()))
+                    }
+                };
+
+                callback.emit(storage)
+            }),
+        );
+    }
+
+    /// Loads the stored event log
+    ///
+    /// A log stored in localStorage by earlier versions is loaded as changed,
+    /// so that it is moved to IndexedDB once saved.
+    pub fn load(&self, callback: StorageCallback<EventLog>) {
+        match self {
+            EventStorage::IndexedDb(db) => idb::get_all(
+                db,
+                EVENTS_STORE,
+                Callback::from(move |result: Result<Vec<String>, IdbError>| {
+                    let values = match result {
+                        Ok(values) => values,
+                        Err(error) => return callback.emit(Err(error.into())),
+                    };
+
+                    let events: Vec<LoggedEvent> = values
+                        .iter()
+                        .filter_map(|json| match serde_json::from_str(json) {
+                            Ok(logged) => Some(logged),
+                            Err(error) => {
+                                log::warn!("Skipping an invalid stored event: {}", error);
+                                None
+                            }
+                        })
+                        .collect();
+
+                    if !events.is_empty() {
+                        return callback.emit(Ok(EventLog::from_events(events)));
+                    }
+
+                    let mut event_log = StorageService::new(Area::Local)
+                        .map(|storage| restore_local(&storage))
+                        .unwrap_or_default();
+
+                    event_log.mark_all_changed();
+                    callback.emit(Ok(event_log))
+                }),
+            ),
+            EventStorage::LocalStorage(storage) => callback.emit(Ok(restore_local(storage))),
+        }
+    }
+
+    /// Saves the changes of the event log, emitting the saved events once done
+    pub fn save(&mut self, event_log: &EventLog, callback: StorageCallback<Vec<LoggedEvent>>) {
+        let changes = event_log.changes();
+
+        if changes.is_empty() {
+            return callback.emit(Ok(changes));
+        }
+
+        match self {
+            EventStorage::IndexedDb(db) => {
+                let entries = changes
+                    .iter()
+                    .map(|logged| {
+                        serde_json::to_string(logged).map(|json| (logged.uuid.to_string(), json))
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(error) => return callback.emit(Err(StorageError(error.to_string()))),
+                };
+
+                idb::put_all(
+                    db,
+                    EVENTS_STORE,
+                    entries,
+                    Callback::from(move |result: Result<(), IdbError>| match result {
+                        Ok(()) => {
+                            // The log has been moved to IndexedDB
+                            forget_local();
+                            callback.emit(Ok(changes.clone()))
+                        }
+                        Err(error) => callback.emit(Err(error.into())),
+                    }),
+                );
+            }
+            EventStorage::LocalStorage(storage) => {
+                storage.store(EVENT_STORE_KEY, Json(event_log));
+
+                // The event log supersedes the simple store
+                storage.remove(SIMPLE_STORE_KEY);
+
+                callback.emit(Ok(changes))
+            }
+        }
+    }
+
+    /// Removes every stored event
+    pub fn clear(&mut self, callback: StorageCallback<()>) {
+        match self {
+            EventStorage::IndexedDb(db) => idb::clear(
+                db,
+                EVENTS_STORE,
+                Callback::from(move |result: Result<(), IdbError>| {
+                    callback.emit(result.map_err(StorageError::from))
+                }),
+            ),
+            EventStorage::LocalStorage(storage) => {
+                storage.remove(EVENT_STORE_KEY);
+                storage.remove(SIMPLE_STORE_KEY);
+
+                callback.emit(Ok(()))
+            }
+        }
+    }
+}
+
+/// Restores the event log from localStorage, migrating the data stored before it was introduced
+fn restore_local(storage: &StorageService) -> EventLog {
+    if let Json(Ok(event_log)) = storage.restore(EVENT_STORE_KEY) {
+        event_log
+    } else if let Json(Ok(store)) = storage.restore(SIMPLE_STORE_KEY) {
+        let store: Vec<Arc<RwLock<Inventory>>> = store;
+        EventLog::from_inventories(&store)
+    } else {
+        EventLog::default()
+    }
+}
+
+/// Removes the event log (and older data) from localStorage
+fn forget_local() {
+    if let Ok(mut storage) = StorageService::new(Area::Local) {
+        storage.remove(EVENT_STORE_KEY);
+        storage.remove(SIMPLE_STORE_KEY);
+    }
+}
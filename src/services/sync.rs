@@ -0,0 +1,34 @@
+use super::events::LoggedEvent;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often the local events are synchronised with sfi-server while logged in
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The progress of the synchronisation with sfi-server, persisted between sessions
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct SyncState {
+    /// The opaque server-side position up to which remote events have been pulled
+    pub cursor: Option<String>,
+}
+
+/// A batch of remote events, as returned by sfi-server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PullResponse {
+    /// The events of every inventory the user can read, recorded after the requested cursor
+    pub events: Vec<LoggedEvent>,
+
+    /// The cursor to be used for the next pull
+    pub cursor: String,
+
+    /// Whether the server has more events after this batch
+    pub has_more: bool,
+}
+
+/// The acknowledgement of pushed events by sfi-server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushResponse {
+    /// The UUIDs of the events which have been accepted (or were already known)
+    pub accepted: Vec<Uuid>,
+}
@@ -0,0 +1,68 @@
+//! Lets the open tabs of this app tell each other about their changes
+//!
+//! Messages are sent as JSON over a `BroadcastChannel`, which never delivers them to the
+//! sending tab itself.
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{BroadcastChannel, MessageEvent};
+use yew::Callback;
+
+/// A channel to the other tabs, which is closed once dropped
+pub struct TabChannel {
+    channel: BroadcastChannel,
+
+    // Keep the listener alive for as long as the channel is open
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl TabChannel {
+    /// Opens the channel with the given name, or returns `None` if the browser doesn't support it
+    pub fn open<T>(name: &str, callback: Callback<T>) -> Option<Self>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let channel = match BroadcastChannel::new(name) {
+            Ok(channel) => channel,
+            Err(error) => {
+                log::warn!("Cannot open the channel {}: {:?}", name, error);
+                return None;
+            }
+        };
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            match event
+                .data()
+                .as_string()
+                .map(|json| serde_json::from_str(&json))
+            {
+                Some(Ok(message)) => callback.emit(message),
+                _ => log::warn!("Ignoring an invalid message from another tab"),
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Some(Self {
+            channel,
+            _on_message: on_message,
+        })
+    }
+
+    /// Sends a message to every other tab
+    pub fn post<T: Serialize>(&self, message: &T) {
+        let result = serde_json::to_string(message)
+            .map_err(|error| JsValue::from_str(&error.to_string()))
+            .and_then(|json| self.channel.post_message(&JsValue::from_str(&json)));
+
+        if let Err(error) = result {
+            log::warn!("Cannot inform the other tabs: {:?}", error);
+        }
+    }
+}
+
+impl Drop for TabChannel {
+    fn drop(&mut self) {
+        self.channel.close();
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+/// The HTTP status sfi-server responds with when a correct password still needs a TOTP code
+pub const TOTP_REQUIRED_STATUS: u16 = 428;
+
+/// Whether the current user has enabled two-factor authentication
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TotpStatus {
+    pub enabled: bool,
+}
+
+/// A new TOTP secret, which only becomes active once a first code was verified
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TotpEnrolment {
+    pub secret: String,
+
+    /// The `otpauth://` URI to be scanned by authenticator apps
+    pub otpauth_url: String,
+}
+
+/// A code from the authenticator app, e.g. to verify an enrolment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TotpCode {
+    pub code: String,
+}
+
+/// The single-use codes which can replace a TOTP code if the authenticator is lost
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Removes whitespace from a code as typed (or pasted) by the user
+pub fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_plain_codes() {
+        assert_eq!(normalize_code("123456"), "123456");
+    }
+
+    #[test]
+    fn removes_whitespace() {
+        assert_eq!(normalize_code(" 123 456\n"), "123456");
+        assert_eq!(normalize_code("abcd-\tefgh"), "abcd-efgh");
+    }
+
+    #[test]
+    fn keeps_empty_codes_empty() {
+        assert_eq!(normalize_code(" \t "), "");
+    }
+}
@@ -0,0 +1,244 @@
+/// The number of characters a user name must (at least, or at most) have
+pub const MIN_NAME_LENGTH: usize = 3;
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// The number of characters a password must have at least
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Checks a user name against the rules of sfi-server, describing the first one violated
+///
+/// Names consist of letters, digits, `.`, `_` and `-`, and start with a letter or digit.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let length = name.chars().count();
+
+    if !(MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&length) {
+        return Err(format!(
+            "The name must have between {} and {} characters",
+            MIN_NAME_LENGTH, MAX_NAME_LENGTH
+        ));
+    }
+
+    if !name.chars().next().is_some_and(char::is_alphanumeric) {
+        return Err("The name must start with a letter or digit".to_string());
+    }
+
+    if let Some(invalid) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == '.' || *c == '_' || *c == '-'))
+    {
+        return Err(format!("The name cannot contain \"{}\"", invalid));
+    }
+
+    Ok(())
+}
+
+/// Checks a base URL of sfi-server, describing the first problem found
+///
+/// The URL needs an `http` or `https` scheme and a host, and cannot have a query or fragment.
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let lowercase = url.to_ascii_lowercase();
+    let rest = match ["http://", "https://"]
+        .iter()
+        .find(|scheme| lowercase.starts_with(*scheme))
+    {
+        Some(scheme) => &url[scheme.len()..],
+        None => return Err("The URL must start with http:// or https://".to_string()),
+    };
+
+    if let Some(invalid) = url
+        .chars()
+        .find(|c| c.is_whitespace() || *c == '?' || *c == '#')
+    {
+        return Err(format!("The URL cannot contain \"{}\"", invalid));
+    }
+
+    // The host (and port) ends at the path, if any
+    let host = rest.split('/').next().unwrap_or_default();
+
+    if host.is_empty() {
+        return Err("The URL must contain a host".to_string());
+    }
+
+    if let Some(invalid) = host
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || ".-:[]".contains(*c)))
+    {
+        return Err(format!("The host cannot contain \"{}\"", invalid));
+    }
+
+    Ok(())
+}
+
+/// A rough estimate of how hard a password is to guess
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    TooShort,
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl PasswordStrength {
+    /// Rates a password by its length and the kinds of characters it uses
+    pub fn of(password: &str) -> Self {
+        let length = password.chars().count();
+
+        if length < MIN_PASSWORD_LENGTH {
+            return PasswordStrength::TooShort;
+        }
+
+        let kinds = [
+            password.chars().any(char::is_lowercase),
+            password.chars().any(char::is_uppercase),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ]
+        .iter()
+        .filter(|used| **used)
+        .count();
+
+        // Long passphrases make up for using fewer kinds of characters
+        let score = kinds + (length >= 12) as usize + (length >= 16) as usize;
+
+        match score {
+            0..=2 => PasswordStrength::Weak,
+            3 => PasswordStrength::Fair,
+            _ => PasswordStrength::Strong,
+        }
+    }
+
+    /// Whether sfi-web lets users choose a password of this strength
+    pub fn is_acceptable(self) -> bool {
+        self >= PasswordStrength::Fair
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            PasswordStrength::TooShort => {
+                format!("Too short, use at least {} characters", MIN_PASSWORD_LENGTH)
+            }
+            PasswordStrength::Weak => {
+                "Weak, use a longer password or mix in digits and symbols".to_string()
+            }
+            PasswordStrength::Fair => "Fair".to_string(),
+            PasswordStrength::Strong => "Strong".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_names_within_the_length_limits() {
+        assert!(validate_name("abc").is_ok());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn rejects_names_outside_the_length_limits() {
+        assert!(validate_name("ab").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn counts_characters_instead_of_bytes() {
+        assert!(validate_name("äöü").is_ok());
+        assert!(validate_name(&"ä".repeat(MAX_NAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn rejects_names_not_starting_with_a_letter_or_digit() {
+        assert_eq!(
+            validate_name(".abc"),
+            Err("The name must start with a letter or digit".to_string())
+        );
+        assert!(validate_name("_abc").is_err());
+        assert!(validate_name("1abc").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        assert_eq!(
+            validate_name("a b"),
+            Err("The name cannot contain \" \"".to_string())
+        );
+        assert!(validate_name("a.b_c-d").is_ok());
+    }
+
+    #[test]
+    fn accepts_http_and_https_urls() {
+        assert!(validate_url("http://localhost:8080").is_ok());
+        assert!(validate_url("https://sfi.example.com/api/").is_ok());
+        assert!(validate_url("HTTPS://[::1]:8080").is_ok());
+    }
+
+    #[test]
+    fn rejects_urls_without_a_supported_scheme() {
+        assert_eq!(
+            validate_url("sfi.example.com"),
+            Err("The URL must start with http:// or https://".to_string())
+        );
+        assert!(validate_url("ftp://sfi.example.com").is_err());
+    }
+
+    #[test]
+    fn rejects_urls_without_a_host() {
+        assert_eq!(
+            validate_url("https:///api"),
+            Err("The URL must contain a host".to_string())
+        );
+        assert!(validate_url("https://").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_characters_in_urls() {
+        assert_eq!(
+            validate_url("https://sfi.example .com"),
+            Err("The URL cannot contain \" \"".to_string())
+        );
+        assert!(validate_url("https://sfi.example.com/?page=1").is_err());
+        assert!(validate_url("https://user@sfi.example.com").is_err());
+    }
+
+    #[test]
+    fn rates_short_passwords() {
+        assert_eq!(PasswordStrength::of("aB3$efg"), PasswordStrength::TooShort);
+        assert_eq!(PasswordStrength::of(""), PasswordStrength::TooShort);
+    }
+
+    #[test]
+    fn rates_by_kinds_of_characters_from_8_characters() {
+        assert_eq!(PasswordStrength::of("abcdefgh"), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("abcdEFgh"), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("abcdEF12"), PasswordStrength::Fair);
+        assert_eq!(PasswordStrength::of("abcdE$12"), PasswordStrength::Strong);
+    }
+
+    #[test]
+    fn rates_longer_passwords_higher() {
+        assert_eq!(PasswordStrength::of("abcdefghijk"), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("abcdefghijkl"), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("abcdefGHIJKL"), PasswordStrength::Fair);
+        assert_eq!(
+            PasswordStrength::of("abcdefghijklmno"),
+            PasswordStrength::Weak
+        );
+        assert_eq!(
+            PasswordStrength::of("abcdefghijklmnop"),
+            PasswordStrength::Fair
+        );
+        assert_eq!(
+            PasswordStrength::of("abcdefghIJKLMNOP"),
+            PasswordStrength::Strong
+        );
+    }
+
+    #[test]
+    fn accepts_fair_passwords() {
+        assert!(!PasswordStrength::Weak.is_acceptable());
+        assert!(PasswordStrength::Fair.is_acceptable());
+        assert!(PasswordStrength::Strong.is_acceptable());
+    }
+}
@@ -1 +1,484 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sfi_core::core::{Inventory, Item, Unit};
+use std::fmt;
+use uuid::Uuid;
 
+/// Amounts which differ by less than this are considered equal
+///
+/// Summing up fractional consumptions (e.g. three times a third) rarely adds up exactly.
+const AMOUNT_TOLERANCE: f64 = 1e-9;
+
+/// Details about a unit which are managed by sfi-web, in addition to those of sfi-core
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnitDetails {
+    /// The best-before (or use-by) date of the unit
+    pub expires: Option<NaiveDate>,
+
+    /// How much of the product the unit contained initially
+    #[serde(default)]
+    pub quantity: Quantity,
+
+    /// Every time (some of) the unit was eaten or thrown away
+    #[serde(default)]
+    pub history: Vec<Consumption>,
+}
+
+/// An amount of a product
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    pub amount: f64,
+    pub measure: Measure,
+}
+
+/// The measurement unit of a quantity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Measure {
+    #[default]
+    Pieces,
+    Grams,
+    Millilitres,
+}
+
+/// Some amount of a unit which was eaten or thrown away
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Consumption {
+    pub at: DateTime<Utc>,
+    pub amount: f64,
+    pub kind: ConsumptionKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsumptionKind {
+    Eaten,
+    Discarded,
+}
+
+/// How soon a unit expires
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Urgency {
+    Expired,
+    Soon,
+    Upcoming,
+    Later,
+    Unknown,
+}
+
+/// A unit which expires soon, together with where to find it
+#[derive(Clone, Debug)]
+pub struct ExpiringUnit {
+    pub inventory_uuid: Uuid,
+    pub inventory_name: String,
+    pub item_uuid: Uuid,
+    pub item_name: String,
+    pub unit: Unit,
+    pub details: UnitDetails,
+    pub expires: NaiveDate,
+}
+
+/// What the product catalogue knows about an EAN
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub ean: String,
+    pub name: String,
+    pub brand: Option<String>,
+    pub category: Option<String>,
+
+    /// How many days the product usually keeps after buying it
+    pub shelf_life_days: Option<u32>,
+}
+
+/// What to search for across all inventories
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Matched (case-insensitively) against item names, EANs and unit names
+    pub text: String,
+
+    /// Only search this inventory
+    pub inventory: Option<Uuid>,
+
+    /// Only find units expiring within this many days
+    pub expires_within: Option<i64>,
+
+    /// Only search inventories owned by this user
+    pub owner: Option<Uuid>,
+}
+
+/// An item matching a search, together with its matching units
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub inventory_uuid: Uuid,
+    pub inventory_name: String,
+    pub item: Item,
+    pub units: Vec<(Unit, UnitDetails)>,
+}
+
+/// The role of a user in an inventory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Owner,
+    Admin,
+    Writer,
+    Reader,
+}
+
+/// Something a user may or may not be allowed to do with an inventory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Create, edit, delete, merge and consume items and units
+    EditContents,
+
+    /// Rename the inventory
+    EditInventory,
+
+    /// Add and remove members or change their roles
+    ManageMembers,
+
+    TransferOwnership,
+    DeleteInventory,
+}
+
+/// Who has which role in an inventory
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Membership {
+    pub owner: Uuid,
+    pub admins: Vec<Uuid>,
+    pub writables: Vec<Uuid>,
+    pub readables: Vec<Uuid>,
+}
+
+impl UnitDetails {
+    /// Returns the number of days until the unit expires (negative if already expired)
+    pub fn days_left(&self) -> Option<i64> {
+        self.expires
+            .map(|expires| (expires - Local::today().naive_local()).num_days())
+    }
+
+    pub fn urgency(&self) -> Urgency {
+        match self.days_left() {
+            None => Urgency::Unknown,
+            Some(days) if days < 0 => Urgency::Expired,
+            Some(days) if days <= 2 => Urgency::Soon,
+            Some(days) if days <= 7 => Urgency::Upcoming,
+            Some(_) => Urgency::Later,
+        }
+    }
+
+    /// Describes the expiry date in a human-readable way, e.g. "expires in 3 days"
+    pub fn describe_expiry(&self) -> String {
+        match self.days_left() {
+            None => "No expiry date".to_string(),
+            Some(days) if days < -1 => format!("Expired {} days ago", -days),
+            Some(-1) => "Expired yesterday".to_string(),
+            Some(0) => "Expires today".to_string(),
+            Some(1) => "Expires tomorrow".to_string(),
+            Some(days) => format!("Expires in {} days", days),
+        }
+    }
+
+    /// Returns the amount which has neither been eaten nor thrown away yet
+    pub fn remaining(&self) -> f64 {
+        let consumed: f64 = self.history.iter().map(|entry| entry.amount).sum();
+        let remaining = self.quantity.amount - consumed;
+
+        if remaining > AMOUNT_TOLERANCE {
+            remaining
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_used_up(&self) -> bool {
+        self.remaining() == 0.0
+    }
+
+    /// Records the consumption of the given amount (or everything that is left with `None`)
+    ///
+    /// Returns the amount which was actually recorded, which is never more than what remains.
+    pub fn consume(&mut self, amount: Option<f64>, kind: ConsumptionKind) -> f64 {
+        let remaining = self.remaining();
+        let amount = amount.map_or(remaining, |amount| amount.max(0.0).min(remaining));
+
+        if amount <= AMOUNT_TOLERANCE {
+            return 0.0;
+        }
+
+        self.history.push(Consumption {
+            at: Utc::now(),
+            amount,
+            kind,
+        });
+
+        amount
+    }
+
+    /// The total amount which was eaten or thrown away
+    pub fn total(&self, kind: ConsumptionKind) -> f64 {
+        self.history
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.amount)
+            .sum()
+    }
+}
+
+impl Default for Quantity {
+    fn default() -> Self {
+        Self {
+            amount: 1.0,
+            measure: Measure::default(),
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.measure.key())
+    }
+}
+
+impl Measure {
+    pub const ALL: [Measure; 3] = [Measure::Pieces, Measure::Grams, Measure::Millilitres];
+
+    /// The identifier used for this measure in forms
+    pub fn key(&self) -> &'static str {
+        match self {
+            Measure::Pieces => "pcs",
+            Measure::Grams => "g",
+            Measure::Millilitres => "ml",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|measure| measure.key() == key)
+    }
+
+    /// The human-readable name of this measure
+    pub fn name(&self) -> &'static str {
+        match self {
+            Measure::Pieces => "pieces",
+            Measure::Grams => "grams",
+            Measure::Millilitres => "millilitres",
+        }
+    }
+
+    /// Formats an amount of this measure, e.g. "3 pcs" or "250 g"
+    pub fn describe(&self, amount: f64) -> String {
+        Quantity {
+            amount,
+            measure: *self,
+        }
+        .to_string()
+    }
+}
+
+impl Role {
+    /// The roles which can be given to members other than the owner
+    pub const MEMBER_ROLES: [Role; 3] = [Role::Admin, Role::Writer, Role::Reader];
+
+    /// The identifier used for this role in forms
+    pub fn key(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Admin => "admin",
+            Role::Writer => "writer",
+            Role::Reader => "reader",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::MEMBER_ROLES
+            .iter()
+            .copied()
+            .find(|role| role.key() == key)
+    }
+
+    /// The human-readable name of this role
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Owner => "Owner",
+            Role::Admin => "Admin",
+            Role::Writer => "Can edit",
+            Role::Reader => "Can view",
+        }
+    }
+
+    /// Checks whether users with this role may perform an action (everyone may read)
+    pub fn allows(&self, action: Action) -> bool {
+        match action {
+            Action::EditContents => *self != Role::Reader,
+            Action::EditInventory | Action::ManageMembers => {
+                *self == Role::Owner || *self == Role::Admin
+            }
+            Action::TransferOwnership | Action::DeleteInventory => *self == Role::Owner,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            Action::EditContents => "edit the items and units of this inventory",
+            Action::EditInventory => "edit this inventory",
+            Action::ManageMembers => "change the members of this inventory",
+            Action::TransferOwnership => "transfer the ownership of this inventory",
+            Action::DeleteInventory => "delete this inventory",
+        };
+
+        write!(f, "You aren't allowed to {}", action)
+    }
+}
+
+impl Membership {
+    pub fn role_of(&self, user: Uuid) -> Option<Role> {
+        if self.owner == user {
+            Some(Role::Owner)
+        } else if self.admins.contains(&user) {
+            Some(Role::Admin)
+        } else if self.writables.contains(&user) {
+            Some(Role::Writer)
+        } else if self.readables.contains(&user) {
+            Some(Role::Reader)
+        } else {
+            None
+        }
+    }
+
+    /// Lists every member other than the owner, together with their role
+    pub fn members(&self) -> Vec<(Uuid, Role)> {
+        let admins = self.admins.iter().map(|uuid| (*uuid, Role::Admin));
+        let writables = self.writables.iter().map(|uuid| (*uuid, Role::Writer));
+        let readables = self.readables.iter().map(|uuid| (*uuid, Role::Reader));
+
+        admins.chain(writables).chain(readables).collect()
+    }
+
+    /// Gives a member (or a new user) a role other than the owner's one
+    pub fn set_role(&mut self, user: Uuid, role: Role) {
+        if self.owner == user {
+            return;
+        }
+
+        self.remove(user);
+
+        match role {
+            Role::Owner => self.transfer_ownership(user),
+            Role::Admin => self.admins.push(user),
+            Role::Writer => self.writables.push(user),
+            Role::Reader => self.readables.push(user),
+        }
+    }
+
+    /// Revokes every access of a member (the owner cannot be removed)
+    pub fn remove(&mut self, user: Uuid) {
+        self.admins.retain(|uuid| *uuid != user);
+        self.writables.retain(|uuid| *uuid != user);
+        self.readables.retain(|uuid| *uuid != user);
+    }
+
+    /// Makes another user the owner, keeping the previous owner as an admin
+    pub fn transfer_ownership(&mut self, user: Uuid) {
+        if self.owner == user {
+            return;
+        }
+
+        self.remove(user);
+        self.admins.push(self.owner);
+        self.owner = user;
+    }
+}
+
+impl From<&Inventory> for Membership {
+    fn from(inventory: &Inventory) -> Self {
+        Self {
+            owner: inventory.owner,
+            admins: inventory.admins.clone(),
+            writables: inventory.writables.clone(),
+            readables: inventory.readables.clone(),
+        }
+    }
+}
+
+impl Urgency {
+    /// The CSS class used to colour-code the urgency
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            Urgency::Expired => "sfi-expired",
+            Urgency::Soon => "sfi-expires-soon",
+            Urgency::Upcoming => "sfi-expires-upcoming",
+            Urgency::Later => "sfi-expires-later",
+            Urgency::Unknown => "",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(amount: f64) -> UnitDetails {
+        UnitDetails {
+            quantity: Quantity {
+                amount,
+                measure: Measure::Pieces,
+            },
+            ..UnitDetails::default()
+        }
+    }
+
+    #[test]
+    fn consumes_partially() {
+        let mut details = details(3.0);
+
+        assert_eq!(details.consume(Some(1.0), ConsumptionKind::Eaten), 1.0);
+        assert_eq!(details.remaining(), 2.0);
+        assert!(!details.is_used_up());
+    }
+
+    #[test]
+    fn consumes_everything_that_is_left() {
+        let mut details = details(3.0);
+        details.consume(Some(1.0), ConsumptionKind::Eaten);
+
+        assert_eq!(details.consume(None, ConsumptionKind::Discarded), 2.0);
+        assert!(details.is_used_up());
+        assert_eq!(details.total(ConsumptionKind::Eaten), 1.0);
+        assert_eq!(details.total(ConsumptionKind::Discarded), 2.0);
+    }
+
+    #[test]
+    fn never_consumes_more_than_remains() {
+        let mut details = details(1.0);
+
+        assert_eq!(details.consume(Some(5.0), ConsumptionKind::Eaten), 1.0);
+        assert_eq!(details.consume(Some(1.0), ConsumptionKind::Eaten), 0.0);
+        assert_eq!(details.history.len(), 1);
+    }
+
+    #[test]
+    fn is_used_up_despite_rounding_errors() {
+        let mut details = details(1.0);
+
+        for _ in 0..10 {
+            details.consume(Some(0.1), ConsumptionKind::Eaten);
+        }
+
+        assert!(details.is_used_up());
+        assert_eq!(details.remaining(), 0.0);
+        assert_eq!(details.consume(None, ConsumptionKind::Eaten), 0.0);
+        assert_eq!(details.history.len(), 10);
+    }
+
+    #[test]
+    fn ignores_empty_consumptions() {
+        let mut details = details(1.0);
+
+        assert_eq!(details.consume(Some(0.0), ConsumptionKind::Eaten), 0.0);
+        assert_eq!(details.consume(Some(-1.0), ConsumptionKind::Eaten), 0.0);
+        assert!(details.history.is_empty());
+    }
+}